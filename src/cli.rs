@@ -1,11 +1,15 @@
-use clap::Parser;
+use std::ops::RangeInclusive;
+
+use clap::{Parser, Subcommand};
+
+use crate::schema::{MIN_SCHEMA_VERSION, SCHEMA_VERSION};
 
 /// QuickSys - A fast system information collector
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
 pub struct Cli {
     /// Pretty-print JSON output
-    #[clap(long)]
+    #[clap(long, global = true)]
     pub pretty: bool,
     
     /// Select specific fields to include in output (comma-separated)
@@ -17,15 +21,163 @@ pub struct Cli {
     #[clap(long)]
     pub no_tally: bool,
     
-    /// Probe Tally HTTP endpoint (default: 127.0.0.1:9000)
+    /// Probe Tally HTTP endpoint (default: port from tally.ini, else 127.0.0.1:9000)
     #[clap(long)]
     pub tally_http: Option<String>,
     
+    /// Search for the Tally gateway: ports from tally.ini, ports Tally
+    /// processes listen on, then the --tally-ports range
+    #[clap(long, conflicts_with = "tally_http")]
+    pub tally_discover: bool,
+    
+    /// Candidate ports for --tally-discover
+    #[clap(long, value_name = "START-END", requires = "tally_discover",
+           default_value = crate::collector::tally::DEFAULT_DISCOVERY_PORTS, value_parser = parse_port_range)]
+    pub tally_ports: RangeInclusive<u16>,
+    
+    /// Connect to Tally's ODBC server through this DSN (builds with tally-odbc)
+    #[clap(long, value_name = "DSN")]
+    pub tally_odbc_dsn: Option<String>,
+    
+    /// Connect to Tally's ODBC server through this driver on the gateway port
+    #[clap(long, value_name = "DRIVER", conflicts_with = "tally_odbc_dsn")]
+    pub tally_odbc_driver: Option<String>,
+    
+    /// Count ledgers, stock items and vouchers of each company loaded in Tally
+    #[clap(long)]
+    pub tally_stats: bool,
+    
+    /// Extra application rules: a TOML file or a directory of them; repeatable.
+    /// An app with the id of a built-in rule replaces it.
+    #[clap(long, value_name = "PATH")]
+    pub app_rules: Vec<std::path::PathBuf>,
+    
+    /// Shorten Tally license serial numbers like the Windows product ID
+    #[clap(long, global = true)]
+    pub mask_serials: bool,
+    
+    /// Warn about Tally companies whose newest backup is older than this
+    #[clap(long, value_name = "DAYS", default_value_t = crate::collector::tally::backup::DEFAULT_MAX_AGE_DAYS)]
+    pub backup_max_age_days: u64,
+    
+    /// Exit with status 2 when the output carries warnings, for schedulers and monitoring
+    #[clap(long, conflicts_with = "http")]
+    pub check: bool,
+    
     /// Global timeout in milliseconds
-    #[clap(long, default_value = "500")]
+    #[clap(long, default_value = "500", global = true)]
     pub timeout_ms: u64,
     
+    /// Emit an older output layout for parsers that have not been updated yet
+    #[clap(long, default_value_t = SCHEMA_VERSION,
+           value_parser = clap::value_parser!(u32).range(MIN_SCHEMA_VERSION as i64..=SCHEMA_VERSION as i64))]
+    pub schema_version: u32,
+    
     /// Start local HTTP server on specified port
     #[clap(long)]
     pub http: Option<u16>,
-}
\ No newline at end of file
+    
+    /// Inventory a Windows volume mounted at this path (e.g. /mnt/win) from its
+    /// registry hives instead of the running system
+    #[clap(long, value_name = "ROOT", conflicts_with = "http")]
+    pub offline_windows: Option<std::path::PathBuf>,
+    
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Print the JSON Schema of the output document
+    Schema {
+        /// Print the OpenAPI document for the HTTP server instead
+        #[clap(long)]
+        openapi: bool,
+    },
+    
+    /// Print the version resource of a Windows executable (works on any platform)
+    FileVersion {
+        /// Path to an .exe or .dll
+        path: std::path::PathBuf,
+    },
+    
+    /// Tally diagnostics
+    Tally {
+        #[clap(subcommand)]
+        command: TallyCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TallyCommand {
+    /// Send the product request to a Tally XML gateway and print what it answered
+    Probe {
+        /// Gateway address
+        #[clap(long, default_value = crate::collector::tally::DEFAULT_TALLY_ENDPOINT)]
+        endpoint: String,
+    },
+    
+    /// Export a report from a Tally XML gateway and print it as JSON
+    Query {
+        /// Gateway address
+        #[clap(long, default_value = crate::collector::tally::DEFAULT_TALLY_ENDPOINT)]
+        endpoint: String,
+        
+        /// Report name, e.g. "Trial Balance", "Day Book" or "List of Accounts"
+        #[clap(long)]
+        report: String,
+        
+        /// Company to export from (default: the active company)
+        #[clap(long)]
+        company: Option<String>,
+        
+        /// Period start: 2024-04-01, 20240401 or 1-Apr-2024
+        #[clap(long, value_parser = parse_query_date)]
+        from: Option<String>,
+        
+        /// Period end, in the same formats as --from
+        #[clap(long, value_parser = parse_query_date)]
+        to: Option<String>,
+        
+        /// Print Tally's XML response unchanged
+        #[clap(long)]
+        raw: bool,
+    },
+    
+    /// List the company folders in a Tally data directory without starting Tally
+    Data {
+        /// Data directory (or a single company folder)
+        path: std::path::PathBuf,
+    },
+    
+    /// Detect Tally installations from a registry snapshot instead of the live registry
+    Registry {
+        /// SOFTWARE hive file, or a JSON object of HKLM key paths holding their values
+        path: std::path::PathBuf,
+    },
+}
+
+/// Parses `9000-9010` (or a single port) into an inclusive range
+fn parse_port_range(value: &str) -> Result<RangeInclusive<u16>, String> {
+    let (start, end) = value.split_once('-').unwrap_or((value, value));
+    let port = |text: &str| text.trim().parse::<u16>().map_err(|_| format!("invalid port: {}", text.trim()));
+    let (start, end) = (port(start)?, port(end)?);
+    if start > end {
+        return Err(format!("range starts after it ends: {}", value));
+    }
+    Ok(start..=end)
+}
+
+/// Parses a report period date into the `YYYYMMDD` form Tally expects
+fn parse_query_date(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    let iso = value.len() == 10
+        && value.bytes().enumerate().all(|(i, b)| if i == 4 || i == 7 { b == b'-' } else { b.is_ascii_digit() });
+    // The ISO form goes through the same validation as Tally's own
+    let date = if iso {
+        crate::utils::parse_tally_date(&value.replace('-', ""))
+    } else {
+        crate::utils::parse_tally_date(value)
+    };
+    date.map(|date| date.replace('-', "")).ok_or_else(|| format!("not a date: {}", value))
+}
@@ -0,0 +1,275 @@
+//! Evaluation of rules against a registry, a file system, desktop entries
+//! and the package database. Each matcher returns every hit with what it
+//! could read there; merging hits into installations is up to the caller.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::rules::{AppRule, VersionSource};
+use crate::collector::pe;
+use crate::collector::registry::RegistrySource;
+use crate::models::InstalledPackage;
+use crate::utils::wildcard_match;
+
+/// Uninstall entries of the 64-bit view; the 32-bit view is derived
+const UNINSTALL_KEY: &str = "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall";
+
+/// One place a rule matched
+#[derive(Debug, Clone)]
+pub struct RuleMatch {
+    /// Evidence source: "registry_uninstall", "registry_vendor", "file",
+    /// "file_version", "desktop_file" or "package"
+    pub source: &'static str,
+    /// Registry key, file path or package that matched
+    pub detail: String,
+    /// What names the product there: DisplayName, subkey name, install
+    /// directory, desktop entry name or package name
+    pub label: String,
+    pub version: Option<String>,
+    /// Install directory as the application sees it
+    pub install_path: Option<String>,
+    /// The install directory on this host, for file hits
+    pub host_path: Option<PathBuf>,
+}
+
+/// Uninstall entries, then the rule's own keys, each in the 64-bit view
+/// before the 32-bit one
+pub fn registry_matches(rule: &AppRule, registry: &dyn RegistrySource) -> Vec<RuleMatch> {
+    let mut found = Vec::new();
+
+    if !rule.uninstall.is_empty() {
+        for base in registry_views(UNINSTALL_KEY) {
+            for subkey in registry.subkeys(&base) {
+                let key = format!("{}\\{}", base, subkey);
+                let Some(display_name) = registry.value(&key, "DisplayName") else {
+                    continue;
+                };
+                if !rule.uninstall.iter().any(|u| wildcard_match(&u.display_name, &display_name, false)) {
+                    continue;
+                }
+                found.push(RuleMatch {
+                    source: "registry_uninstall",
+                    detail: format!("HKLM\\{}", key),
+                    label: display_name,
+                    version: registry.value(&key, "DisplayVersion"),
+                    install_path: registry.value(&key, "InstallLocation"),
+                    host_path: None,
+                });
+            }
+        }
+    }
+
+    for registry_rule in &rule.registry {
+        for pattern in registry_views(&registry_rule.key) {
+            for key in expand_key(registry, &pattern) {
+                let read = |name: &Option<String>| name.as_deref().and_then(|name| registry.value(&key, name));
+                found.push(RuleMatch {
+                    source: "registry_vendor",
+                    detail: format!("HKLM\\{}", key),
+                    label: key.rsplit('\\').next().unwrap_or(&key).to_string(),
+                    version: read(&registry_rule.version_value),
+                    install_path: read(&registry_rule.path_value),
+                    host_path: None,
+                });
+            }
+        }
+    }
+
+    found
+}
+
+/// A key below SOFTWARE and its counterpart in the 32-bit view
+fn registry_views(key: &str) -> Vec<String> {
+    let mut views = vec![key.to_string()];
+    let mut parts = key.splitn(2, '\\');
+    if let (Some(software), Some(rest)) = (parts.next(), parts.next()) {
+        let already_32bit = rest.split('\\').next().is_some_and(|c| c.eq_ignore_ascii_case("WOW6432Node"));
+        if software.eq_ignore_ascii_case("SOFTWARE") && !already_32bit {
+            views.push(format!("{}\\WOW6432Node\\{}", software, rest));
+        }
+    }
+    views
+}
+
+/// Existing keys matching a path whose components may hold wildcards.
+/// Literal components keep the rule's spelling; wildcards take the subkey's.
+fn expand_key(registry: &dyn RegistrySource, pattern: &str) -> Vec<String> {
+    let mut components = pattern.split('\\').filter(|c| !c.is_empty());
+    let Some(first) = components.next() else {
+        return Vec::new();
+    };
+
+    let mut keys = vec![first.to_string()];
+    for component in components {
+        let wildcard = component.contains(['*', '?']);
+        keys = keys
+            .iter()
+            .flat_map(|key| {
+                registry
+                    .subkeys(key)
+                    .into_iter()
+                    .filter(|name| wildcard_match(component, name, true))
+                    .map(|name| format!("{}\\{}", key, if wildcard { name.as_str() } else { component }))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+    keys
+}
+
+/// Files matching the rule's paths: `C:\` paths below `drive_c`, Unix paths
+/// below `unix_root`. Paths for a root that is not given are skipped.
+pub fn file_matches(rule: &AppRule, drive_c: Option<&Path>, unix_root: Option<&Path>) -> Vec<RuleMatch> {
+    let mut found: Vec<RuleMatch> = Vec::new();
+
+    for file_rule in &rule.files {
+        let path = file_rule.path.trim();
+        let (root, pattern, windows) = match path.get(..2) {
+            Some(drive) if drive.eq_ignore_ascii_case("c:") => (drive_c, &path[2..], true),
+            _ if path.starts_with('/') => (unix_root, path, false),
+            // Other drives are not mapped anywhere
+            _ => (None, path, true),
+        };
+        let Some(root) = root else {
+            continue;
+        };
+
+        for (file, names) in glob(root, pattern) {
+            let Some(dir) = file.parent() else {
+                continue;
+            };
+            let dir_names = &names[..names.len() - 1];
+            let install_path = if windows {
+                format!("C:\\{}", dir_names.join("\\"))
+            } else {
+                format!("/{}", dir_names.join("/"))
+            };
+            let version = match file_rule.version {
+                VersionSource::Pe => pe::display_version(&file),
+                VersionSource::None => None,
+            };
+            found.push(RuleMatch {
+                source: if file_rule.version == VersionSource::Pe { "file_version" } else { "file" },
+                detail: file.to_string_lossy().into_owned(),
+                label: install_path.clone(),
+                version,
+                install_path: Some(install_path),
+                host_path: Some(dir.to_path_buf()),
+            });
+        }
+    }
+
+    // Directories before what is inside them, like a tree listing
+    found.sort_by(|a, b| a.host_path.cmp(&b.host_path).then_with(|| a.detail.cmp(&b.detail)));
+    found.dedup_by(|a, b| a.detail == b.detail);
+    found
+}
+
+/// Files below `root` matching `pattern` component by component,
+/// case-insensitively, with the names as found on disk
+fn glob(root: &Path, pattern: &str) -> Vec<(PathBuf, Vec<String>)> {
+    let components: Vec<&str> = pattern.split(['\\', '/']).filter(|c| !c.is_empty()).collect();
+    let mut paths = vec![(root.to_path_buf(), Vec::new())];
+
+    for (index, component) in components.iter().enumerate() {
+        let last = index == components.len() - 1;
+        let mut next = Vec::new();
+        for (dir, names) in &paths {
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().into_owned();
+                let path = entry.path();
+                // Only the last component names a file
+                if !wildcard_match(component, &name, true) || (if last { !path.is_file() } else { !path.is_dir() }) {
+                    continue;
+                }
+                let mut names = names.clone();
+                names.push(name);
+                next.push((path, names));
+            }
+        }
+        paths = next;
+    }
+
+    if components.is_empty() {
+        return Vec::new();
+    }
+    paths
+}
+
+/// Desktop entries in `dirs` whose name matches one of the rule's patterns.
+/// Hidden entries count as removed, as the specification says.
+pub fn desktop_matches(rule: &AppRule, dirs: &[PathBuf]) -> Vec<RuleMatch> {
+    if rule.desktop.is_empty() {
+        return Vec::new();
+    }
+
+    let mut found = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut files: Vec<PathBuf> = entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|ext| ext == "desktop"))
+            .collect();
+        files.sort();
+
+        for file in files {
+            let Some(name) = fs::read_to_string(&file).ok().and_then(|text| desktop_entry_name(&text)) else {
+                continue;
+            };
+            if rule.desktop.iter().any(|pattern| wildcard_match(pattern, &name, true)) {
+                found.push(RuleMatch {
+                    source: "desktop_file",
+                    detail: file.to_string_lossy().into_owned(),
+                    label: name,
+                    version: None,
+                    install_path: None,
+                    host_path: None,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// `Name=` of the `[Desktop Entry]` group, unless the entry is hidden
+fn desktop_entry_name(text: &str) -> Option<String> {
+    let mut in_entry = false;
+    let mut name = None;
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_entry = line == "[Desktop Entry]";
+            continue;
+        }
+        if !in_entry {
+            continue;
+        }
+        match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("Name", value)) if name.is_none() => name = Some(value.to_string()),
+            Some(("Hidden", "true")) => return None,
+            _ => {}
+        }
+    }
+    name.filter(|n| !n.is_empty())
+}
+
+/// Installed packages whose name matches one of the rule's patterns
+pub fn package_matches(rule: &AppRule, packages: &[InstalledPackage]) -> Vec<RuleMatch> {
+    packages
+        .iter()
+        .filter(|package| rule.packages.iter().any(|pattern| wildcard_match(pattern, &package.name, true)))
+        .map(|package| RuleMatch {
+            source: "package",
+            detail: format!("{}: {} {}", package.source, package.name, package.version.as_deref().unwrap_or("")).trim_end().to_string(),
+            label: package.name.clone(),
+            version: package.version.clone(),
+            install_path: None,
+            host_path: None,
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+//! Rule-driven application detection.
+//!
+//! Each application is described by a rule (see `builtin.toml`): registry
+//! keys, file paths, desktop entries and package names that reveal it. The
+//! engine evaluates the rules against whatever the platform offers and
+//! reports one `DetectedApp` per rule that matched. Tally runs on its own
+//! built-in rule and is reported under `apps.tally` instead.
+
+pub mod engine;
+pub mod packages;
+pub mod rules;
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::collector::evidence;
+use crate::collector::registry::RegistrySource;
+use crate::models::{DetectedApp, InstalledPackage};
+use engine::RuleMatch;
+use rules::{AppRule, TALLY_RULE};
+
+/// What the rules are evaluated against
+#[derive(Default)]
+pub struct DetectionSources<'a> {
+    /// Registries of Windows systems: the live one, a mounted volume's or Wine prefixes'
+    pub registries: Vec<&'a dyn RegistrySource>,
+    /// Where drive C: of those systems can be read
+    pub drives: Vec<PathBuf>,
+    /// Root of the host's own file system, for Unix paths
+    pub unix_root: Option<PathBuf>,
+    /// Directories holding .desktop files
+    pub desktop_dirs: Vec<PathBuf>,
+    pub packages: &'a [InstalledPackage],
+}
+
+/// Evaluates every rule except Tally's; apps nothing matched are left out
+pub fn detect_apps(rules: &[AppRule], sources: &DetectionSources) -> Vec<DetectedApp> {
+    rules
+        .iter()
+        .filter(|rule| rule.id != TALLY_RULE)
+        .filter_map(|rule| {
+            let mut matches = Vec::new();
+            for registry in &sources.registries {
+                matches.extend(engine::registry_matches(rule, *registry));
+            }
+            for drive in &sources.drives {
+                matches.extend(engine::file_matches(rule, Some(drive), None));
+            }
+            if let Some(root) = &sources.unix_root {
+                matches.extend(engine::file_matches(rule, None, Some(root)));
+            }
+            matches.extend(engine::desktop_matches(rule, &sources.desktop_dirs));
+            matches.extend(engine::package_matches(rule, sources.packages));
+
+            (!matches.is_empty()).then(|| detected_app(rule, matches))
+        })
+        .collect()
+}
+
+/// One entry for all of a rule's hits; the first version and path found win
+fn detected_app(rule: &AppRule, matches: Vec<RuleMatch>) -> DetectedApp {
+    let mut app = DetectedApp {
+        id: rule.id.clone(),
+        name: rule.name.clone(),
+        vendor: rule.vendor.clone(),
+        category: rule.category.clone(),
+        ..Default::default()
+    };
+
+    for hit in matches {
+        if app.version.is_none() {
+            app.version = hit.version.filter(|v| !v.is_empty());
+        }
+        if app.install_path.is_none() {
+            app.install_path = hit.install_path.filter(|p| !p.is_empty());
+        }
+        if !app.detection_sources.iter().any(|s| s == hit.source) {
+            app.detection_sources.push(hit.source.to_string());
+        }
+        evidence::add_evidence(&mut app.evidence, hit.source, &hit.detail);
+    }
+    app.confidence = Some(evidence::confidence(&app.evidence));
+    app
+}
+
+/// `applications` below `$XDG_DATA_HOME` (default `~/.local/share`) and each
+/// of `$XDG_DATA_DIRS` (default `/usr/local/share:/usr/share` below the host root)
+pub fn desktop_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+    dirs.extend(data_home);
+
+    match env::var("XDG_DATA_DIRS").ok().filter(|v| !v.is_empty()) {
+        Some(data_dirs) => dirs.extend(data_dirs.split(':').filter(|d| !d.is_empty()).map(PathBuf::from)),
+        None => {
+            let root = packages::host_root();
+            dirs.extend(["usr/local/share", "usr/share"].iter().map(|dir| root.join(dir)));
+        }
+    }
+
+    dirs.into_iter().map(|dir| dir.join("applications")).collect()
+}
@@ -0,0 +1,30 @@
+//! Alpine packages, from apk's installed database. apk records the build
+//! time of a package but not when it was installed.
+
+use std::fs;
+use std::path::Path;
+
+use super::stanzas;
+use crate::models::InstalledPackage;
+
+const INSTALLED: &str = "lib/apk/db/installed";
+
+/// Stanzas of one-letter fields: `P:` name, `V:` version, `A:` architecture
+pub fn read_packages(root: &Path) -> Vec<InstalledPackage> {
+    let Ok(text) = fs::read_to_string(root.join(INSTALLED)) else {
+        return Vec::new();
+    };
+
+    stanzas(&text, ':')
+        .filter_map(|stanza| {
+            let field = |name: &str| stanza.iter().find(|(f, _)| *f == name).map(|(_, value)| value.to_string());
+            Some(InstalledPackage {
+                name: field("P")?,
+                version: field("V"),
+                architecture: field("A"),
+                source: "apk".to_string(),
+                install_date: None,
+            })
+        })
+        .collect()
+}
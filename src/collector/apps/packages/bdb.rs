@@ -0,0 +1,140 @@
+//! Read-only reader for Berkeley DB hash databases, enough to list the
+//! stored values. rpm before 4.16 keeps its `Packages` database this way.
+//!
+//! Pages are scanned in file order rather than through the hash buckets,
+//! so values come out unordered. Files are in the byte order of the machine
+//! that wrote them; both orders are read.
+
+/// `DB_HASHMAGIC` in the metadata page
+const HASH_MAGIC: u32 = 0x0006_1561;
+
+/// Every page starts with this header; item offsets follow it
+const PAGE_HEADER_LEN: usize = 26;
+
+const P_HASH_UNSORTED: u8 = 2;
+const P_OVERFLOW: u8 = 7;
+const P_HASH: u8 = 13;
+
+/// Item stored on the hash page itself
+const H_KEYDATA: u8 = 1;
+/// Item too big for the page, stored in a chain of overflow pages
+const H_OFFPAGE: u8 = 3;
+
+/// The data items of every key/data pair. None when the file is not a hash
+/// database.
+pub fn hash_values(db: &[u8]) -> Option<Vec<Vec<u8>>> {
+    let magic = db.get(12..16)?;
+    let order = if u32::from_le_bytes(magic.try_into().ok()?) == HASH_MAGIC {
+        ByteOrder::Little
+    } else if u32::from_be_bytes(magic.try_into().ok()?) == HASH_MAGIC {
+        ByteOrder::Big
+    } else {
+        return None;
+    };
+    let page_size = order.u32(db, 20)? as usize;
+    if !(512..=65536).contains(&page_size) {
+        return None;
+    }
+    let database = Database { data: db, page_size, order };
+
+    let mut values = Vec::new();
+    for number in 1..db.len() / page_size {
+        let page = database.page(number as u32)?;
+        if page[25] != P_HASH && page[25] != P_HASH_UNSORTED {
+            continue;
+        }
+
+        // A corrupt page or item loses only its own values
+        let entries = order.u16(page, 20).map_or(0, usize::from);
+        let Some(offsets) = (0..entries)
+            .map(|index| order.u16(page, PAGE_HEADER_LEN + index * 2).map(usize::from))
+            .collect::<Option<Vec<_>>>()
+        else {
+            continue;
+        };
+
+        // Keys and data alternate; items fill the page from the end, so each
+        // one ends where the previous one starts
+        for index in (1..entries).step_by(2) {
+            let (start, end) = (offsets[index], offsets[index - 1]);
+            let Some(item) = page.get(start..end) else {
+                continue;
+            };
+            match item.first() {
+                Some(&H_KEYDATA) => values.push(item[1..].to_vec()),
+                Some(&H_OFFPAGE) => {
+                    if let (Some(first), Some(length)) = (order.u32(item, 4), order.u32(item, 8)) {
+                        values.extend(database.overflow(first, length as usize));
+                    }
+                }
+                // Duplicates do not occur in rpm's database
+                _ => {}
+            }
+        }
+    }
+    Some(values)
+}
+
+#[derive(Clone, Copy)]
+enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    fn u16(self, data: &[u8], offset: usize) -> Option<u16> {
+        let bytes = data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(match self {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        })
+    }
+
+    fn u32(self, data: &[u8], offset: usize) -> Option<u32> {
+        let bytes = data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match self {
+            ByteOrder::Little => u32::from_le_bytes(bytes),
+            ByteOrder::Big => u32::from_be_bytes(bytes),
+        })
+    }
+}
+
+struct Database<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    order: ByteOrder,
+}
+
+impl<'a> Database<'a> {
+    fn page(&self, number: u32) -> Option<&'a [u8]> {
+        let start = number as usize * self.page_size;
+        self.data.get(start..start + self.page_size)
+    }
+
+    /// `length` bytes from the overflow chain starting at page `first`;
+    /// each page says in its free-space offset how many bytes it holds.
+    /// `length` comes from the file, so the value grows only as pages are read.
+    fn overflow(&self, first: u32, length: usize) -> Option<Vec<u8>> {
+        if length > self.data.len() {
+            return None;
+        }
+        let mut value = Vec::new();
+        let mut next = first;
+        let mut hops = 0;
+        while value.len() < length {
+            hops += 1;
+            if next == 0 || hops > self.data.len() / self.page_size {
+                return None;
+            }
+            let page = self.page(next)?;
+            if page[25] != P_OVERFLOW {
+                return None;
+            }
+            let held = self.order.u16(page, 22)? as usize;
+            value.extend_from_slice(page.get(PAGE_HEADER_LEN..PAGE_HEADER_LEN + held)?);
+            next = self.order.u32(page, 16)?;
+        }
+        value.truncate(length);
+        Some(value)
+    }
+}
@@ -0,0 +1,55 @@
+//! Debian and Ubuntu packages, from dpkg's status file
+
+use std::fs;
+use std::path::Path;
+
+use super::{modified, stanzas};
+use crate::models::InstalledPackage;
+
+/// dpkg's record of every package it knows, installed or not
+const STATUS: &str = "var/lib/dpkg/status";
+
+/// File lists of the installed packages; written when a package is unpacked
+const INFO: &str = "var/lib/dpkg/info";
+
+pub fn read_packages(root: &Path) -> Vec<InstalledPackage> {
+    let Ok(text) = fs::read_to_string(root.join(STATUS)) else {
+        return Vec::new();
+    };
+    let info = root.join(INFO);
+    parse_status(&text)
+        .into_iter()
+        .map(|mut package| {
+            // Multi-arch packages name the list after the architecture too
+            let arch_list = package.architecture.as_ref().map(|arch| format!("{}:{}.list", package.name, arch));
+            package.install_date = arch_list
+                .iter()
+                .chain([format!("{}.list", package.name)].iter())
+                .find_map(|list| modified(&info.join(list)));
+            package
+        })
+        .collect()
+}
+
+/// Stanzas whose Status ends in "installed"; removed packages that left
+/// configuration behind say "config-files" instead
+fn parse_status(text: &str) -> Vec<InstalledPackage> {
+    let mut packages = Vec::new();
+
+    for stanza in stanzas(text, ':') {
+        let field = |name: &str| stanza.iter().find(|(f, _)| *f == name).map(|(_, value)| value.to_string());
+        let installed = field("Status").is_some_and(|status| status.split_whitespace().last() == Some("installed"));
+        let Some(name) = field("Package").filter(|_| installed) else {
+            continue;
+        };
+        packages.push(InstalledPackage {
+            name,
+            version: field("Version"),
+            architecture: field("Architecture"),
+            source: "dpkg".to_string(),
+            install_date: None,
+        });
+    }
+
+    packages
+}
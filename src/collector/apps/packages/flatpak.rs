@@ -0,0 +1,72 @@
+//! Flatpak apps, from the deployments in the system and per-user installations.
+//!
+//! Each app has a `current` link to its active `<arch>/<branch>`, whose
+//! `active` link names the deployed commit. The version comes from the
+//! release list in the app's AppStream metadata, as software centres show it.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::modified;
+use crate::models::InstalledPackage;
+
+const SYSTEM_APPS: &str = "var/lib/flatpak/app";
+const USER_APPS: &str = ".local/share/flatpak/app";
+
+pub fn read_packages(root: &Path, home: Option<&Path>) -> Vec<InstalledPackage> {
+    let mut dirs = vec![root.join(SYSTEM_APPS)];
+    dirs.extend(home.map(|home| home.join(USER_APPS)));
+
+    let mut packages = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let id = entry.file_name().to_string_lossy().into_owned();
+            if let Some(package) = read_app(&entry.path(), id) {
+                packages.push(package);
+            }
+        }
+    }
+    packages
+}
+
+fn read_app(app_dir: &Path, id: String) -> Option<InstalledPackage> {
+    // current -> x86_64/stable
+    let current = fs::read_link(app_dir.join("current")).ok()?;
+    let arch = current.iter().next().map(|c| c.to_string_lossy().into_owned());
+    let deployment = app_dir.join(&current).join("active");
+    if !deployment.is_dir() {
+        return None;
+    }
+
+    Some(InstalledPackage {
+        version: appstream_version(&deployment, &id),
+        name: id,
+        architecture: arch,
+        source: "flatpak".to_string(),
+        // A deployment is written once, when the commit is installed
+        install_date: modified(&fs::canonicalize(&deployment).unwrap_or(deployment)),
+    })
+}
+
+/// The first `<release version="...">` of the app's metainfo (or older
+/// appdata) file; releases are listed newest first
+fn appstream_version(deployment: &Path, id: &str) -> Option<String> {
+    let candidates: Vec<PathBuf> = ["metainfo", "appdata"]
+        .iter()
+        .flat_map(|dir| {
+            let dir = deployment.join("files/share").join(dir);
+            [dir.join(format!("{}.metainfo.xml", id)), dir.join(format!("{}.appdata.xml", id))]
+        })
+        .collect();
+    let text = candidates.iter().find_map(|path| fs::read_to_string(path).ok())?;
+
+    let release = &text[text.find("<release ")?..];
+    let release = &release[..release.find('>')?];
+    let value = &release[release.find("version=")? + "version=".len()..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    Some(value[..value.find(quote)?].to_string())
+}
@@ -0,0 +1,62 @@
+//! Installed packages on Linux hosts, read from the package databases on
+//! disk so no package manager has to be run
+
+pub mod apk;
+pub mod bdb;
+pub mod dpkg;
+pub mod flatpak;
+pub mod rpm;
+pub mod snap;
+pub mod sqlite;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::InstalledPackage;
+use crate::utils::format_system_time;
+
+/// Root of the host file system: `/`, or `$HOST_ROOT` when the host is
+/// mounted elsewhere, as in monitoring containers
+pub fn host_root() -> PathBuf {
+    env::var_os("HOST_ROOT").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/"))
+}
+
+/// Every package below `root`, grouped by source (dpkg, rpm, apk, Flatpak,
+/// Snap) and sorted by name within each. Flatpak apps installed per user
+/// are looked for in `$HOME` too.
+pub fn installed_packages(root: &Path) -> Vec<InstalledPackage> {
+    let home = env::var_os("HOME").map(PathBuf::from);
+    let sources = [
+        dpkg::read_packages(root),
+        rpm::read_packages(root),
+        apk::read_packages(root),
+        flatpak::read_packages(root, home.as_deref()),
+        snap::read_packages(root),
+    ];
+
+    let mut packages = Vec::new();
+    for mut found in sources {
+        found.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+        packages.extend(found);
+    }
+    packages
+}
+
+/// Modification time of a file, for sources that only record installs that way
+fn modified(path: &Path) -> Option<String> {
+    fs::metadata(path).and_then(|m| m.modified()).ok().map(format_system_time)
+}
+
+/// Stanzas of `Field: value` lines separated by blank lines, as dpkg and apk
+/// write them. Continuation lines (starting with a space) are skipped.
+fn stanzas(text: &str, separator: char) -> impl Iterator<Item = Vec<(&str, &str)>> {
+    text.split("\n\n").map(move |stanza| {
+        stanza
+            .lines()
+            .filter(|line| !line.starts_with([' ', '\t']))
+            .filter_map(|line| line.split_once(separator))
+            .map(|(field, value)| (field.trim(), value.trim()))
+            .collect()
+    })
+}
@@ -0,0 +1,112 @@
+//! Fedora, RHEL and SUSE packages, from the headers in rpm's database
+
+use std::fs;
+use std::path::Path;
+
+use super::{bdb, sqlite};
+use crate::models::InstalledPackage;
+use crate::utils::format_unix_time;
+
+/// Database directories, newest layout first; `/var/lib/rpm` is often a
+/// link to the other, so the first one found is the only one read
+const DATABASE_DIRS: [&str; 2] = ["usr/lib/sysimage/rpm", "var/lib/rpm"];
+
+/// Header tags
+const TAG_NAME: u32 = 1000;
+const TAG_VERSION: u32 = 1001;
+const TAG_RELEASE: u32 = 1002;
+const TAG_EPOCH: u32 = 1003;
+const TAG_INSTALLTIME: u32 = 1008;
+const TAG_ARCH: u32 = 1022;
+
+/// Header data types
+const TYPE_INT32: u32 = 4;
+const TYPE_STRING: u32 = 6;
+const TYPE_I18NSTRING: u32 = 9;
+
+/// Imported signing keys are stored as packages of this name
+const PUBKEY_PACKAGE: &str = "gpg-pubkey";
+
+pub fn read_packages(root: &Path) -> Vec<InstalledPackage> {
+    for dir in DATABASE_DIRS {
+        let dir = root.join(dir);
+        let headers = if let Ok(db) = fs::read(dir.join("rpmdb.sqlite")) {
+            // Packages (hnum INTEGER PRIMARY KEY, blob BLOB NOT NULL)
+            sqlite::read_table(&db, "Packages")
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|row| match row.into_iter().nth(1) {
+                    Some(sqlite::SqlValue::Blob(blob)) => Some(blob),
+                    _ => None,
+                })
+                .collect()
+        } else if let Ok(db) = fs::read(dir.join("Packages")) {
+            bdb::hash_values(&db).unwrap_or_default()
+        } else {
+            continue;
+        };
+
+        return headers
+            .iter()
+            .filter_map(|header| parse_header(header))
+            .filter(|package| package.name != PUBKEY_PACKAGE)
+            .collect();
+    }
+    Vec::new()
+}
+
+/// Reads a header as the database stores it: index entry count and data
+/// length, the index entries (tag, type, offset, count) and the data, all
+/// big-endian
+fn parse_header(header: &[u8]) -> Option<InstalledPackage> {
+    let entries = read_u32(header, 0)? as usize;
+    let data_len = read_u32(header, 4)? as usize;
+    let data_start = entries.checked_mul(16)?.checked_add(8)?;
+    let data = header.get(data_start..data_start.checked_add(data_len)?)?;
+
+    let (mut name, mut version, mut release, mut arch) = (None, None, None, None);
+    let (mut epoch, mut install_time) = (None, None);
+    for entry in 0..entries {
+        let at = 8 + entry * 16;
+        let (tag, kind, offset) = (read_u32(header, at)?, read_u32(header, at + 4)?, read_u32(header, at + 8)? as usize);
+        match (tag, kind) {
+            (TAG_NAME | TAG_VERSION | TAG_RELEASE | TAG_ARCH, TYPE_STRING | TYPE_I18NSTRING) => {
+                let text = data.get(offset..)?;
+                let text = String::from_utf8_lossy(&text[..text.iter().position(|&b| b == 0)?]).into_owned();
+                match tag {
+                    TAG_NAME => name = Some(text),
+                    TAG_VERSION => version = Some(text),
+                    TAG_RELEASE => release = Some(text),
+                    _ => arch = Some(text),
+                }
+            }
+            (TAG_EPOCH, TYPE_INT32) => epoch = read_u32(data, offset),
+            (TAG_INSTALLTIME, TYPE_INT32) => install_time = read_u32(data, offset),
+            _ => {}
+        }
+    }
+
+    // Shown the way `rpm -q` shows it, with the epoch dpkg-style in front
+    let version = version.map(|version| {
+        let version = match release {
+            Some(release) => format!("{}-{}", version, release),
+            None => version,
+        };
+        match epoch {
+            Some(epoch) if epoch > 0 => format!("{}:{}", epoch, version),
+            _ => version,
+        }
+    });
+
+    Some(InstalledPackage {
+        name: name?,
+        version,
+        architecture: arch,
+        source: "rpm".to_string(),
+        install_date: install_time.map(|time| format_unix_time(time as u64)),
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset.checked_add(4)?)?.try_into().ok()?))
+}
@@ -0,0 +1,77 @@
+//! Snaps, from the `meta/snap.yaml` of each mounted snap's current revision
+
+use std::fs;
+use std::path::Path;
+
+use super::modified;
+use crate::models::InstalledPackage;
+
+/// Mount points of the installed snaps, one directory per revision
+const SNAP_MOUNTS: &str = "snap";
+
+/// The snap files themselves, `<name>_<revision>.snap`
+const SNAP_FILES: &str = "var/lib/snapd/snaps";
+
+pub fn read_packages(root: &Path) -> Vec<InstalledPackage> {
+    let Ok(entries) = fs::read_dir(root.join(SNAP_MOUNTS)) else {
+        return Vec::new();
+    };
+
+    let mut packages = Vec::new();
+    for entry in entries.flatten() {
+        // current -> 4173
+        let Ok(revision) = fs::read_link(entry.path().join("current")) else {
+            continue;
+        };
+        let Ok(yaml) = fs::read_to_string(entry.path().join(&revision).join("meta/snap.yaml")) else {
+            continue;
+        };
+        let Some((name, version, arch)) = parse_snap_yaml(&yaml) else {
+            continue;
+        };
+        let snap_file = root.join(SNAP_FILES).join(format!("{}_{}.snap", name, revision.to_string_lossy()));
+        packages.push(InstalledPackage {
+            install_date: modified(&snap_file),
+            name,
+            version,
+            // Snaps without `architectures` are built for the host's
+            architecture: arch,
+            source: "snap".to_string(),
+        });
+    }
+    packages
+}
+
+/// Name, version and first architecture from the top-level keys of
+/// snap.yaml; `architectures` may be a flow list or a block list
+fn parse_snap_yaml(yaml: &str) -> Option<(String, Option<String>, Option<String>)> {
+    let unquote = |value: &str| value.trim().trim_matches(['"', '\'']).to_string();
+
+    let (mut name, mut version, mut arch) = (None, None, None);
+    let mut in_architectures = false;
+    for line in yaml.lines() {
+        if in_architectures {
+            if let Some(item) = line.trim_start().strip_prefix("- ") {
+                arch = arch.or_else(|| Some(unquote(item)));
+                continue;
+            }
+            in_architectures = false;
+        }
+        if line.starts_with([' ', '\t']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key {
+            "name" => name = Some(unquote(value)),
+            "version" => version = Some(unquote(value)),
+            "architectures" => match value.trim().strip_prefix('[').and_then(|list| list.strip_suffix(']')) {
+                Some(list) => arch = list.split(',').map(unquote).find(|a| !a.is_empty()),
+                None => in_architectures = true,
+            },
+            _ => {}
+        }
+    }
+    Some((name?, version.filter(|v| !v.is_empty()), arch))
+}
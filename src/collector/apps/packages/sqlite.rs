@@ -0,0 +1,220 @@
+//! Read-only reader for SQLite database files, enough to list the rows of a
+//! table. rpm 4.16 and later keep their database this way.
+//!
+//! Only the main file is read; changes still in a `-wal` file are not seen.
+
+/// Every SQLite 3 file starts with this string
+const MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// The file header before the first page's b-tree header
+const FILE_HEADER_LEN: usize = 100;
+
+const INTERIOR_TABLE_PAGE: u8 = 0x05;
+const LEAF_TABLE_PAGE: u8 = 0x0d;
+
+/// A column value of a row
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+/// Rows of `table` in rowid order, as stored (an `INTEGER PRIMARY KEY`
+/// column reads as NULL). None when the file is not a database or the
+/// table does not exist.
+pub fn read_table(db: &[u8], table: &str) -> Option<Vec<Vec<SqlValue>>> {
+    let database = Database::open(db)?;
+
+    // sqlite_schema (page 1): type, name, tbl_name, rootpage, sql
+    let root = database.rows(1)?.into_iter().find_map(|row| match row.as_slice() {
+        [SqlValue::Text(kind), SqlValue::Text(name), _, SqlValue::Integer(root), ..]
+            if kind == "table" && name.eq_ignore_ascii_case(table) =>
+        {
+            u32::try_from(*root).ok()
+        }
+        _ => None,
+    })?;
+    database.rows(root)
+}
+
+struct Database<'a> {
+    data: &'a [u8],
+    page_size: usize,
+    /// Page size less the bytes extensions reserve at the end of each page
+    usable: usize,
+}
+
+impl<'a> Database<'a> {
+    fn open(data: &'a [u8]) -> Option<Self> {
+        if !data.starts_with(MAGIC) || data.len() < FILE_HEADER_LEN {
+            return None;
+        }
+        let page_size = match u16::from_be_bytes([data[16], data[17]]) {
+            1 => 65536,
+            size => size as usize,
+        };
+        let usable = page_size.checked_sub(data[20] as usize)?;
+        if page_size < 512 || usable < 480 {
+            return None;
+        }
+        Some(Self { data, page_size, usable })
+    }
+
+    /// Page `number` (1-based)
+    fn page(&self, number: u32) -> Option<&'a [u8]> {
+        let start = (number as usize).checked_sub(1)? * self.page_size;
+        self.data.get(start..start + self.page_size)
+    }
+
+    fn page_count(&self) -> usize {
+        self.data.len() / self.page_size
+    }
+
+    /// Rows of the table b-tree rooted at `root`, left to right
+    fn rows(&self, root: u32) -> Option<Vec<Vec<SqlValue>>> {
+        let mut rows = Vec::new();
+        let mut pending = vec![root];
+        let mut visited = 0;
+
+        while let Some(number) = pending.pop() {
+            // A corrupt file must not send the walk around in circles
+            visited += 1;
+            if visited > self.page_count() {
+                return None;
+            }
+
+            let page = self.page(number)?;
+            let header = if number == 1 { FILE_HEADER_LEN } else { 0 };
+            let kind = *page.get(header)?;
+            let cells = read_u16(page, header + 3)? as usize;
+
+            match kind {
+                LEAF_TABLE_PAGE => {
+                    for index in 0..cells {
+                        let offset = read_u16(page, header + 8 + index * 2)? as usize;
+                        rows.push(decode_record(&self.leaf_payload(page, offset)?)?);
+                    }
+                }
+                INTERIOR_TABLE_PAGE => {
+                    // Pushed in reverse so the leftmost child is visited first
+                    pending.push(read_u32(page, header + 8)?);
+                    for index in (0..cells).rev() {
+                        let offset = read_u16(page, header + 12 + index * 2)? as usize;
+                        pending.push(read_u32(page, offset)?);
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        Some(rows)
+    }
+
+    /// Payload of a table leaf cell, following overflow pages
+    fn leaf_payload(&self, page: &[u8], offset: usize) -> Option<Vec<u8>> {
+        let (size, used) = read_varint(page.get(offset..)?)?;
+        let (_rowid, rowid_len) = read_varint(page.get(offset + used..)?)?;
+        let start = offset + used + rowid_len;
+        let size = size as usize;
+
+        // Spill rules from the file format: keep as much as fits locally
+        let max_local = self.usable - 35;
+        if size <= max_local {
+            return page.get(start..start + size).map(<[u8]>::to_vec);
+        }
+        let min_local = (self.usable - 12) * 32 / 255 - 23;
+        let spill = min_local + (size - min_local) % (self.usable - 4);
+        let local = if spill <= max_local { spill } else { min_local };
+
+        let mut payload = page.get(start..start + local)?.to_vec();
+        let mut next = read_u32(page, start + local)?;
+        let mut hops = 0;
+        while payload.len() < size {
+            hops += 1;
+            if next == 0 || hops > self.page_count() {
+                return None;
+            }
+            let overflow = self.page(next)?;
+            let take = (size - payload.len()).min(self.usable - 4);
+            payload.extend_from_slice(overflow.get(4..4 + take)?);
+            next = read_u32(overflow, 0)?;
+        }
+        Some(payload)
+    }
+}
+
+/// Splits a record into its values: a header of serial types, then the body
+fn decode_record(record: &[u8]) -> Option<Vec<SqlValue>> {
+    let (header_len, mut position) = read_varint(record)?;
+    let header_len = header_len as usize;
+
+    let mut types = Vec::new();
+    while position < header_len {
+        let (serial_type, used) = read_varint(record.get(position..header_len)?)?;
+        types.push(serial_type);
+        position += used;
+    }
+
+    let mut body = header_len;
+    let mut values = Vec::with_capacity(types.len());
+    for serial_type in types {
+        let (value, len) = match serial_type {
+            0 => (SqlValue::Null, 0),
+            1..=6 => {
+                let len = [1, 2, 3, 4, 6, 8][serial_type as usize - 1];
+                let bytes = record.get(body..body + len)?;
+                // Big-endian two's complement, sign-extended from the first byte
+                let mut value = if bytes[0] & 0x80 != 0 { -1i64 } else { 0 };
+                for byte in bytes {
+                    value = (value << 8) | *byte as i64;
+                }
+                (SqlValue::Integer(value), len)
+            }
+            7 => {
+                let bytes: [u8; 8] = record.get(body..body + 8)?.try_into().ok()?;
+                (SqlValue::Real(f64::from_be_bytes(bytes)), 8)
+            }
+            8 => (SqlValue::Integer(0), 0),
+            9 => (SqlValue::Integer(1), 0),
+            n if n >= 12 && n % 2 == 0 => {
+                let len = ((n - 12) / 2) as usize;
+                (SqlValue::Blob(record.get(body..body + len)?.to_vec()), len)
+            }
+            n if n >= 13 => {
+                let len = ((n - 13) / 2) as usize;
+                (SqlValue::Text(String::from_utf8_lossy(record.get(body..body + len)?).into_owned()), len)
+            }
+            // 10 and 11 are reserved
+            _ => return None,
+        };
+        values.push(value);
+        body += len;
+    }
+    Some(values)
+}
+
+/// A big-endian varint of up to nine bytes, and the bytes it took
+fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (index, byte) in data.iter().take(9).enumerate() {
+        if index == 8 {
+            return Some(((value << 8) | *byte as u64, 9));
+        }
+        value = (value << 7) | (*byte & 0x7f) as u64;
+        if byte & 0x80 == 0 {
+            return Some((value, index + 1));
+        }
+    }
+    None
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
@@ -0,0 +1,162 @@
+//! Rule files: which registry keys, files, desktop entries and packages
+//! reveal an application. See builtin.toml for the format.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+
+/// Rules compiled into the binary
+const BUILTIN_RULES: &str = include_str!("builtin.toml");
+
+/// Id of the built-in rule Tally detection runs on; Tally is reported under
+/// `apps.tally` with far more detail than a rule can describe
+pub const TALLY_RULE: &str = "tally";
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleFile {
+    #[serde(default)]
+    app: Vec<AppRule>,
+}
+
+/// How to recognise one application
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AppRule {
+    /// Stable identifier, e.g. "busy"; reported as the app's `id`
+    pub id: String,
+    pub name: String,
+    pub vendor: Option<String>,
+    /// Free-form grouping, e.g. "accounting", "antivirus" or "printer"
+    pub category: Option<String>,
+    #[serde(default)]
+    pub uninstall: Vec<UninstallRule>,
+    #[serde(default)]
+    pub registry: Vec<RegistryRule>,
+    #[serde(default)]
+    pub files: Vec<FileRule>,
+    /// Wildcards on the `Name=` of .desktop files
+    #[serde(default)]
+    pub desktop: Vec<String>,
+    /// Wildcards on installed package names
+    #[serde(default)]
+    pub packages: Vec<String>,
+}
+
+/// Uninstall entries whose DisplayName matches
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UninstallRule {
+    pub display_name: String,
+}
+
+/// A key of the application's own below HKLM
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RegistryRule {
+    /// `*` in a component matches any subkey
+    pub key: String,
+    pub version_value: Option<String>,
+    pub path_value: Option<String>,
+}
+
+/// A file whose presence reveals the application
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileRule {
+    pub path: String,
+    #[serde(default)]
+    pub version: VersionSource,
+}
+
+/// Where a file rule takes the version from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionSource {
+    #[default]
+    None,
+    /// The version resource of a Windows executable
+    Pe,
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    Io(PathBuf, io::Error),
+    /// A file that is not valid TOML or not in the rule format
+    Parse(PathBuf, String),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleError::Io(path, e) => write!(f, "cannot read rules from {}: {}", path.display(), e),
+            RuleError::Parse(path, e) => write!(f, "invalid rule file {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// The rules compiled into the binary, Tally's among them
+pub fn builtin_rules() -> &'static [AppRule] {
+    static RULES: OnceLock<Vec<AppRule>> = OnceLock::new();
+    RULES.get_or_init(|| parse_rules(BUILTIN_RULES, Path::new("builtin.toml")).expect("built-in rules are valid"))
+}
+
+/// The built-in rule Tally detection runs on
+pub fn tally_rule() -> &'static AppRule {
+    builtin_rules().iter().find(|rule| rule.id == TALLY_RULE).expect("built-in rules describe Tally")
+}
+
+/// Parses a rule file; `origin` names it in errors
+pub fn parse_rules(text: &str, origin: &Path) -> Result<Vec<AppRule>, RuleError> {
+    let file: RuleFile = toml::from_str(text).map_err(|e| RuleError::Parse(origin.to_path_buf(), e.to_string()))?;
+    for rule in &file.app {
+        if rule.id.trim().is_empty() {
+            return Err(RuleError::Parse(origin.to_path_buf(), format!("app \"{}\" has an empty id", rule.name)));
+        }
+    }
+    Ok(file.app)
+}
+
+/// Loads a rule file, or every `*.toml` file of a directory in name order.
+/// The Tally rule is built in and cannot be replaced.
+pub fn load_rules(path: &Path) -> Result<Vec<AppRule>, RuleError> {
+    let io_error = |e| RuleError::Io(path.to_path_buf(), e);
+
+    if !path.is_dir() {
+        let text = fs::read_to_string(path).map_err(io_error)?;
+        let rules = parse_rules(&text, path)?;
+        if rules.iter().any(|rule| rule.id == TALLY_RULE) {
+            return Err(RuleError::Parse(path.to_path_buf(), format!("app id \"{}\" is reserved", TALLY_RULE)));
+        }
+        return Ok(rules);
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(io_error)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|file| file.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("toml")))
+        .collect();
+    files.sort();
+
+    let mut rules = Vec::new();
+    for file in files {
+        rules = merge_rules(rules, load_rules(&file)?);
+    }
+    Ok(rules)
+}
+
+/// Adds `extra` to `rules`; an app with an id already present replaces it
+pub fn merge_rules(mut rules: Vec<AppRule>, extra: Vec<AppRule>) -> Vec<AppRule> {
+    for rule in extra {
+        match rules.iter_mut().find(|existing| existing.id == rule.id) {
+            Some(existing) => *existing = rule,
+            None => rules.push(rule),
+        }
+    }
+    rules
+}
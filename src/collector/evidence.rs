@@ -0,0 +1,56 @@
+//! Evidence behind each detected installation and the confidence computed
+//! from it, so a leftover registry entry can be told from a live install
+
+use crate::models::{Evidence, TallyInfo};
+
+/// Weight of each kind of evidence; the score is their sum, capped at 100.
+/// Registry entries survive uninstalls, files on disk are stronger and a
+/// running instance answering is the strongest. A package counts as much as
+/// a file: the package manager vouches for the files it installed.
+const WEIGHTS: [(&str, u32); 11] = [
+    ("registry_uninstall", 15),
+    ("registry_vendor", 10),
+    ("desktop_file", 25),
+    ("file", 35),
+    ("file_version", 35),
+    ("executable", 35),
+    ("package", 35),
+    ("tally_ini", 10),
+    ("process", 50),
+    ("http", 50),
+    ("odbc", 50),
+];
+
+/// Sources that prove the same thing and count once
+const SAME_SIGNAL: [(&str, &str); 2] = [("executable", "file_version"), ("file", "file_version")];
+
+/// Records a signal; the same source and detail are recorded once
+pub fn add_evidence(evidence: &mut Vec<Evidence>, source: &str, detail: &str) {
+    if evidence.iter().any(|e| e.source == source && e.detail == detail) {
+        return;
+    }
+    evidence.push(Evidence {
+        source: source.to_string(),
+        detail: detail.to_string(),
+    });
+}
+
+/// Sets `confidence` of every installation from its evidence
+pub fn score_installations(installs: &mut [TallyInfo]) {
+    for install in installs.iter_mut() {
+        install.confidence = Some(confidence(&install.evidence));
+    }
+}
+
+/// 0-100: each kind of evidence counts once, however often it was seen
+pub fn confidence(evidence: &[Evidence]) -> u8 {
+    let seen = |source: &str| evidence.iter().any(|e| e.source == source);
+
+    let score: u32 = WEIGHTS
+        .iter()
+        .filter(|(source, _)| seen(source))
+        .filter(|(source, _)| !SAME_SIGNAL.iter().any(|(dup, of)| dup == source && seen(of)))
+        .map(|(_, weight)| weight)
+        .sum();
+    score.min(100) as u8
+}
@@ -0,0 +1,240 @@
+//! Read-only parser for Windows registry hive files (regf).
+//!
+//! Lets registry lookups run against hives copied from, or mounted off, a
+//! Windows volume on any platform. Only what inventory needs is supported:
+//! key lookup by path, subkey enumeration and string/integer values.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Hive bins start after the 4 KiB base block; cell offsets are relative to it
+const HIVE_BINS_OFFSET: usize = 0x1000;
+
+/// Set in `nk`/`vk` flags when the name is stored as Latin-1 rather than UTF-16
+const COMPRESSED_NAME: u16 = 0x0020;
+const VALUE_COMPRESSED_NAME: u16 = 0x0001;
+
+/// Set in the `vk` data size when the data is stored in the offset field itself
+const DATA_INLINE: u32 = 0x8000_0000;
+
+/// Values above this size are stored as `db` big-data records
+const MAX_CELL_DATA: usize = 16344;
+
+const REG_SZ: u32 = 1;
+const REG_EXPAND_SZ: u32 = 2;
+const REG_DWORD: u32 = 4;
+const REG_MULTI_SZ: u32 = 7;
+const REG_QWORD: u32 = 11;
+
+#[derive(Debug)]
+pub enum HiveError {
+    Io(io::Error),
+    /// The file does not start with a `regf` base block
+    NotHive,
+    /// The root cell is missing or is not a key
+    BadRoot,
+}
+
+impl fmt::Display for HiveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HiveError::Io(e) => write!(f, "I/O error: {}", e),
+            HiveError::NotHive => write!(f, "not a registry hive (missing regf signature)"),
+            HiveError::BadRoot => write!(f, "registry hive has no valid root key"),
+        }
+    }
+}
+
+impl From<io::Error> for HiveError {
+    fn from(e: io::Error) -> Self {
+        HiveError::Io(e)
+    }
+}
+
+/// A registry hive loaded into memory
+pub struct Hive {
+    data: Vec<u8>,
+    root: usize,
+}
+
+/// A key inside a hive
+#[derive(Clone, Copy)]
+pub struct HiveKey<'a> {
+    hive: &'a Hive,
+    /// Offset of the `nk` cell data within the file
+    offset: usize,
+}
+
+impl Hive {
+    pub fn open(path: &Path) -> Result<Hive, HiveError> {
+        Hive::from_bytes(fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Hive, HiveError> {
+        if data.get(0..4) != Some(b"regf") {
+            return Err(HiveError::NotHive);
+        }
+        let root_cell = read_u32(&data, 0x24).ok_or(HiveError::NotHive)?;
+
+        let mut hive = Hive { data, root: 0 };
+        hive.root = hive.cell(root_cell).filter(|&o| hive.signature(o) == Some(*b"nk")).ok_or(HiveError::BadRoot)?;
+        Ok(hive)
+    }
+
+    pub fn root(&self) -> HiveKey<'_> {
+        HiveKey { hive: self, offset: self.root }
+    }
+
+    /// Looks up a backslash-separated path below the root, case-insensitively
+    pub fn key(&self, path: &str) -> Option<HiveKey<'_>> {
+        path.split('\\')
+            .filter(|part| !part.is_empty())
+            .try_fold(self.root(), |key, part| key.subkey(part))
+    }
+
+    /// File offset of the data of the cell at `cell_offset`, if it is in bounds
+    fn cell(&self, cell_offset: u32) -> Option<usize> {
+        if cell_offset == u32::MAX {
+            return None;
+        }
+        let start = HIVE_BINS_OFFSET.checked_add(cell_offset as usize)?;
+        // Allocated cells have a negative size
+        let size = read_u32(&self.data, start)? as i32;
+        let length = size.checked_neg().filter(|l| *l >= 4)? as usize;
+        (start + length <= self.data.len()).then_some(start + 4)
+    }
+
+    /// Size of the data of the cell whose data starts at `offset`, as `cell` checked it
+    fn cell_len(&self, offset: usize) -> usize {
+        read_u32(&self.data, offset - 4).map_or(0, |size| (size as i32).unsigned_abs() as usize - 4)
+    }
+
+    fn signature(&self, offset: usize) -> Option<[u8; 2]> {
+        self.data.get(offset..offset + 2).map(|s| [s[0], s[1]])
+    }
+
+    /// Appends the `nk` offsets listed by a subkey list cell (`lf`, `lh`, `li` or `ri`)
+    fn collect_subkeys(&self, list: usize, keys: &mut Vec<usize>, depth: u8) {
+        let Some(count) = read_u16(&self.data, list + 2) else {
+            return;
+        };
+        let (stride, nested) = match self.signature(list) {
+            Some([b'l', b'f']) | Some([b'l', b'h']) => (8, false),
+            Some([b'l', b'i']) => (4, false),
+            // Index roots point at further lists; they never nest deeper than one level
+            Some([b'r', b'i']) if depth == 0 => (4, true),
+            _ => return,
+        };
+
+        // A corrupt count must not reach past the list cell
+        let count = (count as usize).min(self.cell_len(list).saturating_sub(4) / stride);
+        for i in 0..count {
+            let Some(target) = read_u32(&self.data, list + 4 + i * stride).and_then(|c| self.cell(c)) else {
+                continue;
+            };
+            if nested {
+                self.collect_subkeys(target, keys, depth + 1);
+            } else if self.signature(target) == Some(*b"nk") {
+                keys.push(target);
+            }
+        }
+    }
+}
+
+impl<'a> HiveKey<'a> {
+    pub fn name(&self) -> String {
+        let data = &self.hive.data;
+        let flags = read_u16(data, self.offset + 0x02).unwrap_or(0);
+        let length = read_u16(data, self.offset + 0x48).unwrap_or(0) as usize;
+        let start = self.offset + 0x4C;
+        decode_name(data.get(start..start + length).unwrap_or(&[]), flags & COMPRESSED_NAME != 0)
+    }
+
+    pub fn subkeys(&self) -> Vec<HiveKey<'a>> {
+        let data = &self.hive.data;
+        let count = read_u32(data, self.offset + 0x14).unwrap_or(0);
+        let mut offsets = Vec::new();
+        if count > 0 {
+            if let Some(list) = read_u32(data, self.offset + 0x1C).and_then(|c| self.hive.cell(c)) {
+                self.hive.collect_subkeys(list, &mut offsets, 0);
+            }
+        }
+        offsets.into_iter().map(|offset| HiveKey { hive: self.hive, offset }).collect()
+    }
+
+    pub fn subkey(&self, name: &str) -> Option<HiveKey<'a>> {
+        self.subkeys().into_iter().find(|key| key.name().eq_ignore_ascii_case(name))
+    }
+
+    /// A value as text: strings as stored (multi-strings joined with newlines),
+    /// integers in decimal. `""` names the default value.
+    pub fn value(&self, name: &str) -> Option<String> {
+        let data = &self.hive.data;
+        let list = self.hive.cell(read_u32(data, self.offset + 0x28)?)?;
+        // The count comes from the file; the list cell bounds how many entries there can be
+        let count = (read_u32(data, self.offset + 0x24)? as usize).min(self.hive.cell_len(list) / 4);
+
+        (0..count)
+            .filter_map(|i| read_u32(data, list + i * 4).and_then(|c| self.hive.cell(c)))
+            .filter(|&vk| self.hive.signature(vk) == Some(*b"vk"))
+            .find(|&vk| value_name(data, vk).eq_ignore_ascii_case(name))
+            .and_then(|vk| self.value_data(vk))
+    }
+
+    fn value_data(&self, vk: usize) -> Option<String> {
+        let data = &self.hive.data;
+        let size = read_u32(data, vk + 0x04)?;
+        let value_type = read_u32(data, vk + 0x0C)?;
+
+        let bytes = if size & DATA_INLINE != 0 {
+            let length = (size & !DATA_INLINE).min(4) as usize;
+            data.get(vk + 0x08..vk + 0x08 + length)?
+        } else if size as usize <= MAX_CELL_DATA {
+            let start = self.hive.cell(read_u32(data, vk + 0x08)?)?;
+            data.get(start..start + size as usize)?
+        } else {
+            // Larger values are split across a `db` big-data record, which inventory never needs
+            return None;
+        };
+
+        match value_type {
+            REG_SZ | REG_EXPAND_SZ => Some(utf16_string(bytes).split('\0').next().unwrap_or("").to_string()),
+            REG_MULTI_SZ => Some(
+                utf16_string(bytes).split('\0').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("\n"),
+            ),
+            REG_DWORD => read_u32(bytes, 0).map(|v| v.to_string()),
+            REG_QWORD => bytes.get(0..8).map(|b| u64::from_le_bytes(b.try_into().unwrap_or([0; 8])).to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn value_name(data: &[u8], vk: usize) -> String {
+    let length = read_u16(data, vk + 0x02).unwrap_or(0) as usize;
+    let flags = read_u16(data, vk + 0x10).unwrap_or(0);
+    let start = vk + 0x14;
+    decode_name(data.get(start..start + length).unwrap_or(&[]), flags & VALUE_COMPRESSED_NAME != 0)
+}
+
+fn decode_name(bytes: &[u8], compressed: bool) -> String {
+    if compressed {
+        bytes.iter().map(|&b| b as char).collect()
+    } else {
+        utf16_string(bytes)
+    }
+}
+
+fn utf16_string(bytes: &[u8]) -> String {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|p| u16::from_le_bytes([p[0], p[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
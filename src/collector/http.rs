@@ -1,51 +1,265 @@
 #[cfg(feature = "http")]
-use axum::{Router, routing::get, extract::Path, Json};
+use axum::{Router, routing::get, extract::{Path, Query, State}, Json};
+#[cfg(feature = "http")]
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+#[cfg(feature = "http")]
+use axum::response::{IntoResponse, sse::{Event, KeepAlive, Sse}};
+#[cfg(feature = "http")]
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+#[cfg(feature = "http")]
+use axum::response::Response;
+#[cfg(feature = "http")]
+use axum::{extract::MatchedPath, http::Request, middleware::{self, Next}};
+#[cfg(feature = "http")]
+use futures_util::stream::{self, Stream};
+#[cfg(feature = "http")]
+use tower_http::compression::{CompressionLayer, Predicate, predicate::{DefaultPredicate, NotForContentType}};
+use serde::Deserialize;
 use serde_json::Value;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
 use std::net::SocketAddr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::Collector;
+use crate::schema::{SCHEMA_VERSION, convert_to_version};
+use crate::utils::{json_patch_diff, sha256_hex};
+
+/// Default push interval for streaming clients
+const DEFAULT_STREAM_INTERVAL_MS: u64 = 1000;
+
+/// Lower bound on the push interval so a client cannot make us re-collect in a tight loop
+const MIN_STREAM_INTERVAL_MS: u64 = 250;
+
+/// Upper bound on the push interval; anything longer is refused rather than fed to the timer
+const MAX_STREAM_INTERVAL_MS: u64 = 60 * 60 * 1000;
+
+/// Collector metadata that differs between any two collections; a patch touching nothing
+/// else is not worth pushing
+const VOLATILE_PATHS: &[&str] = &["/collector/duration_ms"];
+
+/// Shared server state: the collector used for re-collection and the startup snapshot
+pub struct AppState {
+    collector: Arc<Collector>,
+    /// Published once the first collection finishes; the server is not ready before
+    snapshot: OnceLock<Snapshot>,
+    schema_version: u32,
+    /// Request counters keyed by route
+    requests: Mutex<BTreeMap<String, RequestCounters>>,
+    /// Latest re-collection for the streaming clients; locked while collecting
+    /// so clients ticking together wait for one collection
+    #[cfg(feature = "http")]
+    latest: tokio::sync::Mutex<Option<Arc<SharedCollection>>>,
+}
+
+/// The startup collection served by `/api/info`, with its validators
+struct Snapshot {
+    document: Value,
+    /// Weak validator derived from the snapshot content
+    etag: String,
+    /// When the snapshot was collected, reported as `Last-Modified`
+    collected_at: SystemTime,
+}
+
+/// A full re-collection, before any client's selection is applied
+struct SharedCollection {
+    /// Counts collections so a client can tell one it has not seen yet
+    generation: u64,
+    started_at: Instant,
+    duration_ms: u64,
+    document: Value,
+}
+
+/// Per-route request counts, bucketed by status class
+#[derive(Debug, Default)]
+struct RequestCounters {
+    total: u64,
+    by_class: [u64; 5],
+}
+
+/// Query parameters accepted by the streaming endpoints
+#[derive(Debug, Deserialize)]
+pub struct StreamParams {
+    /// Sections to include, same syntax as `--select` (e.g. memory,disks)
+    select: Option<String>,
+
+    /// Push interval in milliseconds, at most `MAX_STREAM_INTERVAL_MS`
+    interval_ms: Option<u64>,
+
+    /// `snapshot` pushes the full document every tick, `patch` (default) pushes JSON Patch deltas
+    mode: Option<String>,
+}
+
+/// A single message pushed to a streaming client
+enum StreamUpdate {
+    Snapshot(Value),
+    Patch(Vec<Value>),
+}
+
+/// The HTTP server, running on a thread of its own
+#[cfg(feature = "http")]
+pub struct ServerHandle {
+    state: Arc<AppState>,
+    thread: std::thread::JoinHandle<()>,
+}
 
 #[cfg(feature = "http")]
-pub fn start_server(port: u16, system_info: Value) {
+impl ServerHandle {
+    /// Hands the server the first collection; until then `/api/info` and
+    /// `/readyz` answer 503
+    pub fn publish(&self, system_info: Value) {
+        let snapshot = Snapshot {
+            etag: snapshot_etag(&system_info),
+            document: system_info,
+            collected_at: SystemTime::now(),
+        };
+        let _ = self.state.snapshot.set(snapshot);
+    }
+
+    /// Blocks for as long as the server runs
+    pub fn wait(self) -> std::thread::Result<()> {
+        self.thread.join()
+    }
+}
+
+/// Binds `port` and serves in the background, so the server already answers
+/// (as not ready) while the first collection runs
+#[cfg(feature = "http")]
+pub fn spawn_server(port: u16, collector: Arc<Collector>, schema_version: u32) -> Result<ServerHandle, String> {
     use tokio::runtime::Runtime;
-    
+
     // Create a new runtime for the HTTP server
-    let rt = Runtime::new().unwrap();
-    
-    // Wrap system_info in an Arc for thread-safe sharing
-    let shared_info = Arc::new(system_info);
-    
+    let rt = Runtime::new().map_err(|e| format!("cannot start the HTTP runtime: {}", e))?;
+
+    // Wrap the collector in an Arc for thread-safe sharing; the snapshot follows later
+    let shared_state = Arc::new(AppState {
+        collector,
+        snapshot: OnceLock::new(),
+        schema_version,
+        requests: Mutex::new(BTreeMap::new()),
+        latest: tokio::sync::Mutex::new(None),
+    });
+
     // Build the application with routes
     let app = Router::new()
         .route("/", get(|| async { "QuickSys API Server" }))
         .route("/api/info", get(get_all_info))
         .route("/api/info/:path", get(get_info_by_path))
-        .with_state(shared_info);
-    
-    // Run the server
+        .route("/api/stream", get(get_stream))
+        .route("/api/ws", get(get_ws))
+        .route("/api/openapi.json", get(get_openapi))
+        .route("/api/diagnostics", get(get_diagnostics))
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/readyz", get(get_readyz))
+        .route_layer(middleware::from_fn_with_state(shared_state.clone(), count_requests))
+        .with_state(shared_state.clone())
+        // Negotiates gzip/br/zstd from Accept-Encoding. Event streams are left
+        // uncompressed because the encoder would buffer events before flushing.
+        .layer(CompressionLayer::new().compress_when(
+            DefaultPredicate::new().and(NotForContentType::const_new("text/event-stream")),
+        ));
+
+    // Bind here so a port in use is reported before anything is collected
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    rt.block_on(async {
-        axum::Server::bind(&addr)
+    let listener = std::net::TcpListener::bind(addr)
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|e| format!("cannot listen on {}: {}", addr, e))?;
+    let server = {
+        let _runtime = rt.enter();
+        axum::Server::from_tcp(listener)
+            .map_err(|e| format!("cannot listen on {}: {}", addr, e))?
             .serve(app.into_make_service())
-            .await
-            .unwrap();
+    };
+
+    let thread = std::thread::spawn(move || {
+        if let Err(e) = rt.block_on(server) {
+            eprintln!("HTTP server failed: {}", e);
+        }
     });
+
+    Ok(ServerHandle { state: shared_state, thread })
 }
 
 #[cfg(feature = "http")]
-async fn get_all_info(state: axum::extract::State<Arc<Value>>) -> Json<Value> {
-    Json(state.as_ref().clone())
+async fn get_all_info(state: State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    conditional_json(&state, &headers, Value::clone)
 }
 
 #[cfg(feature = "http")]
 async fn get_info_by_path(
-    state: axum::extract::State<Arc<Value>>,
+    state: State<Arc<AppState>>,
+    headers: HeaderMap,
     Path(path): Path<String>,
-) -> Json<Value> {
-    let info = state.as_ref();
-    
+) -> Response {
+    conditional_json(&state, &headers, |snapshot| lookup_path(snapshot, &path))
+}
+
+/// Answers with 304 when the client already holds the current snapshot,
+/// otherwise with the JSON body `body` makes of it and fresh validators.
+/// Before the first collection has finished there is nothing to serve yet.
+#[cfg(feature = "http")]
+fn conditional_json(state: &AppState, headers: &HeaderMap, body: impl FnOnce(&Value) -> Value) -> Response {
+    let Some(snapshot) = state.snapshot.get() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "collecting").into_response();
+    };
+
+    let mut response = if is_not_modified(snapshot, headers) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        Json(body(&snapshot.document)).into_response()
+    };
+
+    let response_headers = response.headers_mut();
+    if let Ok(etag) = HeaderValue::from_str(&snapshot.etag) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    if let Ok(last_modified) = HeaderValue::from_str(&httpdate::fmt_http_date(snapshot.collected_at)) {
+        response_headers.insert(header::LAST_MODIFIED, last_modified);
+    }
+    // Clients may cache, but must revalidate with the validators above
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+    response
+}
+
+/// Evaluates `If-None-Match`, falling back to `If-Modified-Since` only when
+/// no entity tag was sent (RFC 9110 section 13.2.2)
+#[cfg(feature = "http")]
+fn is_not_modified(snapshot: &Snapshot, headers: &HeaderMap) -> bool {
+    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) {
+        return if_none_match.split(',').map(str::trim).any(|tag| {
+            // Weak comparison: a W/ prefix on either side does not prevent a match
+            tag == "*" || tag.trim_start_matches("W/") == snapshot.etag.trim_start_matches("W/")
+        });
+    }
+
+    if let Some(if_modified_since) = headers.get(header::IF_MODIFIED_SINCE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| httpdate::parse_http_date(v).ok())
+    {
+        // HTTP dates have one-second resolution, so compare at that granularity
+        let seconds = |t: SystemTime| t.duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        return seconds(snapshot.collected_at) <= seconds(if_modified_since);
+    }
+
+    false
+}
+
+/// Derives an entity tag from the serialized snapshot. It is weak because
+/// the compression layer sends it with every encoding of the same content,
+/// and a strong tag has to differ per representation (RFC 9110 section 8.8.3).
+fn snapshot_etag(snapshot: &Value) -> String {
+    let serialized = serde_json::to_vec(snapshot).unwrap_or_default();
+    format!("W/\"{}\"", &sha256_hex(&serialized)[..32])
+}
+
+/// Looks up a dot-separated path in the snapshot
+fn lookup_path(info: &Value, path: &str) -> Value {
+
     // Split the path by dots
     let parts: Vec<&str> = path.split('.').collect();
-    
+
     // Navigate through the JSON structure
     let mut current = info;
     for part in parts {
@@ -53,9 +267,252 @@ async fn get_info_by_path(
             current = next;
         } else {
             // Path not found, return empty object
-            return Json(serde_json::json!({}));
+            return serde_json::json!({});
         }
     }
-    
-    Json(current.clone())
-}
\ No newline at end of file
+
+    current.clone()
+}
+
+#[cfg(feature = "http")]
+async fn get_openapi() -> Json<Value> {
+    Json(crate::schema::openapi_document())
+}
+
+/// Ready once the first collection has been published
+#[cfg(feature = "http")]
+async fn get_readyz(state: State<Arc<AppState>>) -> Response {
+    if state.snapshot.get().is_some() {
+        "ready".into_response()
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "collecting").into_response()
+    }
+}
+
+/// Build features, probe timings, recent errors, uptime and request counters
+#[cfg(feature = "http")]
+async fn get_diagnostics(state: State<Arc<AppState>>) -> Json<Value> {
+    let mut diagnostics = state.collector.diagnostics().to_json();
+
+    let requests: BTreeMap<String, Value> = state.requests.lock()
+        .map(|requests| {
+            requests.iter()
+                .map(|(route, counters)| {
+                    let [c1, c2, c3, c4, c5] = counters.by_class;
+                    (route.clone(), serde_json::json!({
+                        "total": counters.total,
+                        "1xx": c1, "2xx": c2, "3xx": c3, "4xx": c4, "5xx": c5,
+                    }))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    if let Some(obj) = diagnostics.as_object_mut() {
+        obj.insert("ready".to_string(), Value::Bool(state.snapshot.get().is_some()));
+        obj.insert("requests".to_string(), serde_json::json!(requests));
+    }
+
+    Json(diagnostics)
+}
+
+/// Middleware counting requests per matched route and status class
+#[cfg(feature = "http")]
+async fn count_requests<B>(
+    State(state): State<Arc<AppState>>,
+    matched_path: Option<MatchedPath>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let route = matched_path
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    let response = next.run(request).await;
+
+    if let Ok(mut requests) = state.requests.lock() {
+        let counters = requests.entry(route).or_default();
+        counters.total += 1;
+        let class = (response.status().as_u16() / 100).clamp(1, 5) as usize;
+        counters.by_class[class - 1] += 1;
+    }
+
+    response
+}
+
+/// Server-Sent Events stream of re-collected snapshots or patches
+#[cfg(feature = "http")]
+async fn get_stream(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StreamParams>,
+) -> Response {
+    use futures_util::StreamExt;
+
+    let Some(interval) = stream_interval(&params) else {
+        return interval_too_long();
+    };
+    let updates = update_stream(state, params, interval).map(|update| {
+        let event = match update {
+            StreamUpdate::Snapshot(value) => Event::default().event("snapshot").json_data(value),
+            StreamUpdate::Patch(ops) => Event::default().event("patch").json_data(ops),
+        };
+
+        // Serializing a serde_json::Value cannot fail, but fall back to a comment just in case
+        Ok::<_, Infallible>(event.unwrap_or_else(|_| Event::default().comment("serialization error")))
+    });
+
+    Sse::new(updates).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// WebSocket stream of re-collected snapshots or patches
+#[cfg(feature = "http")]
+async fn get_ws(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<StreamParams>,
+) -> Response {
+    let Some(interval) = stream_interval(&params) else {
+        return interval_too_long();
+    };
+    ws.on_upgrade(move |socket| handle_ws(socket, state, params, interval))
+}
+
+#[cfg(feature = "http")]
+async fn handle_ws(mut socket: WebSocket, state: Arc<AppState>, params: StreamParams, interval: Duration) {
+    use futures_util::StreamExt;
+
+    let updates = update_stream(state, params, interval);
+    futures_util::pin_mut!(updates);
+
+    while let Some(update) = updates.next().await {
+        let message = match update {
+            StreamUpdate::Snapshot(value) => serde_json::json!({"type": "snapshot", "data": value}),
+            StreamUpdate::Patch(ops) => serde_json::json!({"type": "patch", "ops": ops}),
+        };
+
+        // Stop pushing as soon as the client goes away
+        if socket.send(Message::Text(message.to_string())).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// The push interval a client asked for, raised to the minimum, or `None` when it is
+/// longer than `MAX_STREAM_INTERVAL_MS`
+#[cfg(feature = "http")]
+fn stream_interval(params: &StreamParams) -> Option<Duration> {
+    let interval_ms = params.interval_ms.unwrap_or(DEFAULT_STREAM_INTERVAL_MS);
+    (interval_ms <= MAX_STREAM_INTERVAL_MS).then(|| Duration::from_millis(interval_ms.max(MIN_STREAM_INTERVAL_MS)))
+}
+
+#[cfg(feature = "http")]
+fn interval_too_long() -> Response {
+    let message = format!("interval_ms must not exceed {}", MAX_STREAM_INTERVAL_MS);
+    (StatusCode::BAD_REQUEST, message).into_response()
+}
+
+/// Builds the per-client update stream shared by the SSE and WebSocket endpoints.
+/// The first item is always a full snapshot; later items follow the requested mode.
+#[cfg(feature = "http")]
+fn update_stream(state: Arc<AppState>, params: StreamParams, max_age: Duration) -> impl Stream<Item = StreamUpdate> {
+    let patch_mode = params.mode.as_deref() != Some("snapshot");
+
+    let mut interval = tokio::time::interval(max_age);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    let initial: (tokio::time::Interval, Option<Value>, u64) = (interval, None, 0);
+
+    stream::unfold(initial, move |(mut interval, previous, seen)| {
+        let state = state.clone();
+        let select = params.select.clone();
+
+        async move {
+            let (mut previous, mut seen) = (previous, seen);
+
+            loop {
+                interval.tick().await;
+
+                // A client that cannot collect ends its stream but not the server
+                let collection = shared_collection(&state, seen, max_age).await?;
+                seen = collection.generation;
+                let current = render_snapshot(&state, &collection, select.clone());
+
+                let update = match &previous {
+                    Some(previous) if patch_mode => {
+                        let ops = json_patch_diff(previous, &current);
+                        // Nothing but the timing changed; the SSE keep-alive covers idle periods.
+                        // `previous` stays the last document sent, so the next patch applies to it.
+                        if ops.iter().all(|op| VOLATILE_PATHS.iter().any(|path| op["path"] == *path)) {
+                            continue;
+                        }
+                        StreamUpdate::Patch(ops)
+                    }
+                    _ => StreamUpdate::Snapshot(current.clone()),
+                };
+
+                previous = Some(current);
+                return Some((update, (interval, previous, seen)));
+            }
+        }
+    })
+}
+
+/// The latest re-collection when another client made it since this one's
+/// last tick (`seen`) and it is less than `max_age` old; otherwise collects
+/// anew. Each tick therefore collects once however many clients are connected.
+#[cfg(feature = "http")]
+async fn shared_collection(state: &Arc<AppState>, seen: u64, max_age: Duration) -> Option<Arc<SharedCollection>> {
+    let mut latest = state.latest.lock().await;
+    if let Some(collection) = latest.as_ref() {
+        if collection.generation != seen && collection.started_at.elapsed() < max_age {
+            return Some(collection.clone());
+        }
+    }
+
+    let generation = latest.as_ref().map_or(0, |c| c.generation) + 1;
+
+    // Collection is blocking (WMI, registry), keep it off the async workers
+    let collect_state = state.clone();
+    let collected = tokio::task::spawn_blocking(move || {
+        let started_at = Instant::now();
+        let document = collect_state.collector.collect();
+        SharedCollection {
+            generation,
+            started_at,
+            duration_ms: started_at.elapsed().as_millis() as u64,
+            document,
+        }
+    })
+    .await;
+
+    match collected {
+        Ok(collection) => {
+            let collection = Arc::new(collection);
+            *latest = Some(collection.clone());
+            Some(collection)
+        }
+        // The runtime is shutting down; the stream simply ends
+        Err(e) if e.is_cancelled() => None,
+        // A panicking probe is not survivable (release builds abort), so pass it on
+        Err(e) => std::panic::resume_unwind(e.into_panic()),
+    }
+}
+
+/// Shapes a shared collection for one client the same way the CLI shapes a single run
+fn render_snapshot(state: &AppState, collection: &SharedCollection, select: Option<String>) -> Value {
+    let collector = &state.collector;
+
+    let mut result = collection.document.clone();
+
+    if let Some(fields) = select {
+        result = collector.filter_fields(result, fields);
+    }
+
+    collector.add_metadata(&mut result, collection.duration_ms);
+
+    if state.schema_version != SCHEMA_VERSION {
+        result = convert_to_version(result, state.schema_version);
+    }
+
+    result
+}
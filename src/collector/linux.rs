@@ -0,0 +1,145 @@
+//! Collector for Linux and other non-Windows hosts. Tally is looked for in
+//! the user's Wine prefixes, applications with the detection rules and
+//! packages in the host's package databases; the hardware sections are
+//! placeholders from the mock collector.
+
+use serde_json::{Value, json};
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::diagnostics::Diagnostics;
+use crate::collector::apps::{self, packages, rules::{self, AppRule}, DetectionSources};
+use crate::collector::registry::RegistrySource;
+use crate::collector::tally::{backup, license, wine, GatewayOptions};
+use crate::mock_collector;
+use crate::models::{AppsInfo, DetectedApp, InstalledPackage};
+use crate::utils::timeout_duration;
+
+pub struct Collector {
+    timeout: Duration,
+    tally_enabled: bool,
+    tally_gateway: GatewayOptions,
+    mask_serials: bool,
+    backup_max_age_days: u64,
+    app_rules: Vec<AppRule>,
+    diagnostics: Diagnostics,
+}
+
+impl Collector {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: timeout_duration(timeout_ms),
+            tally_enabled: true,
+            tally_gateway: GatewayOptions::default(),
+            mask_serials: false,
+            backup_max_age_days: backup::DEFAULT_MAX_AGE_DAYS,
+            app_rules: rules::builtin_rules().to_vec(),
+            diagnostics: Diagnostics::new(),
+        }
+    }
+    
+    pub fn disable_tally_detection(&mut self) {
+        self.tally_enabled = false;
+    }
+    
+    pub fn set_tally_http_endpoint(&mut self, endpoint: String) {
+        self.tally_gateway.endpoint = Some(endpoint);
+    }
+    
+    pub fn enable_tally_port_discovery(&mut self, ports: RangeInclusive<u16>) {
+        self.tally_gateway.discovery = Some(ports);
+    }
+    
+    pub fn enable_tally_company_stats(&mut self) {
+        self.tally_gateway.company_stats = true;
+    }
+    
+    pub fn set_tally_odbc_dsn(&mut self, dsn: String) {
+        self.tally_gateway.odbc_dsn = Some(dsn);
+    }
+    
+    pub fn set_tally_odbc_driver(&mut self, driver: String) {
+        self.tally_gateway.odbc_driver = Some(driver);
+    }
+    
+    pub fn mask_license_serials(&mut self) {
+        self.mask_serials = true;
+    }
+    
+    pub fn set_backup_max_age_days(&mut self, days: u64) {
+        self.backup_max_age_days = days;
+    }
+    
+    pub fn set_app_rules(&mut self, rules: Vec<AppRule>) {
+        self.app_rules = rules;
+    }
+    
+    pub fn collect(&self) -> Value {
+        // The hardware probes are Windows-only, so this host reports placeholders
+        let mut system_info = self.diagnostics.time_probe("mock", mock_collector::placeholder_system_info);
+        
+        // Look for Tally in the user's Wine prefixes
+        let tally = self.tally_enabled.then(|| {
+            let mut tally_info = self.diagnostics.time_probe("tally", || {
+                wine::collect_wine_tally_info(self.timeout, &self.tally_gateway, &self.diagnostics)
+            });
+            if self.mask_serials {
+                license::mask_serials(&mut tally_info);
+            }
+            system_info.warnings.extend(backup::stale_backup_warnings(&mut tally_info, self.backup_max_age_days));
+            tally_info
+        });
+        // Collect the other applications the rules describe and the installed packages
+        let root = packages::host_root();
+        let installed = self.diagnostics.time_probe("packages", || packages::installed_packages(&root));
+        let detected = self.diagnostics.time_probe("apps", || self.detect_apps(root, &installed));
+        if tally.is_some() || !detected.is_empty() || !installed.is_empty() {
+            system_info.apps = Some(AppsInfo { tally, detected, packages: installed });
+        }
+        
+        self.diagnostics.finish_collection();
+        
+        serde_json::to_value(system_info).unwrap_or_else(|e| {
+            self.diagnostics.record_error("collect", format!("serialization failed: {}", e));
+            json!({})
+        })
+    }
+    
+    /// Evaluates the rules against the Wine prefixes and the host itself
+    fn detect_apps(&self, root: PathBuf, installed: &[InstalledPackage]) -> Vec<DetectedApp> {
+        let prefixes = wine::discover_prefixes();
+        let registries: Vec<_> = prefixes.iter().flat_map(|prefix| wine::prefix_registries(prefix)).collect();
+        let sources = DetectionSources {
+            registries: registries.iter().map(|registry| registry as &dyn RegistrySource).collect(),
+            drives: prefixes.iter().map(|prefix| prefix.join("drive_c")).collect(),
+            unix_root: Some(root),
+            desktop_dirs: apps::desktop_dirs(),
+            packages: installed,
+        };
+        apps::detect_apps(&self.app_rules, &sources)
+    }
+    
+    #[cfg(feature = "http")]
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+    
+    pub fn filter_fields(&self, value: Value, selector: String) -> Value {
+        let fields = crate::utils::parse_field_selector(&selector);
+        crate::utils::filter_json_fields(value, fields)
+    }
+    
+    pub fn add_metadata(&self, value: &mut Value, duration_ms: u64) {
+        if let Value::Object(obj) = value {
+            let collector_info = json!({
+                "name": "QuickSys",
+                "version": env!("CARGO_PKG_VERSION"),
+                "schema_version": crate::schema::SCHEMA_VERSION,
+                "duration_ms": duration_ms
+            });
+            
+            obj.insert("collector".to_string(), collector_info);
+        }
+    }
+}
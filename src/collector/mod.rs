@@ -1,32 +1,79 @@
+#[cfg(windows)]
 mod os;
+#[cfg(windows)]
 mod device;
+#[cfg(windows)]
 mod cpu;
+#[cfg(windows)]
 mod memory;
+#[cfg(windows)]
 mod disk;
+#[cfg(windows)]
 mod network;
-mod tally;
+
+pub mod apps;
+pub mod evidence;
+pub mod hive;
+pub mod offline;
+pub mod pe;
+pub mod process;
+pub mod registry;
+pub mod tally;
 
 #[cfg(feature = "http")]
 pub mod http;
+#[cfg(not(windows))]
+mod linux;
 
+#[cfg(windows)]
+use std::ops::RangeInclusive;
+#[cfg(windows)]
+use std::path::PathBuf;
+#[cfg(windows)]
 use std::time::Duration;
+#[cfg(windows)]
 use serde_json::{Value, json};
 
+#[cfg(windows)]
+use crate::diagnostics::Diagnostics;
+#[cfg(windows)]
 use crate::models::SystemInfo;
-use crate::utils::{parse_field_selector, filter_json_fields};
+#[cfg(windows)]
+use apps::{rules::{self as app_rules, AppRule}, DetectionSources};
+#[cfg(windows)]
+use registry::LiveRegistry;
+#[cfg(windows)]
+use tally::GatewayOptions;
+#[cfg(windows)]
+use crate::utils::{parse_field_selector, filter_json_fields, timeout_duration};
+
+// Non-Windows builds share the same surface through the Linux collector
+#[cfg(not(windows))]
+pub use linux::Collector;
 
+#[cfg(windows)]
 pub struct Collector {
     timeout: Duration,
     tally_enabled: bool,
-    tally_http_endpoint: Option<String>,
+    tally_gateway: GatewayOptions,
+    mask_serials: bool,
+    backup_max_age_days: u64,
+    app_rules: Vec<AppRule>,
+    diagnostics: Diagnostics,
 }
 
+#[cfg(windows)]
 impl Collector {
     pub fn new(timeout_ms: u64) -> Self {
         Self {
-            timeout: Duration::from_millis(timeout_ms),
+            timeout: timeout_duration(timeout_ms),
             tally_enabled: true,
-            tally_http_endpoint: Some("127.0.0.1:9000".to_string()),
+            // Resolved from tally.ini during detection unless set explicitly
+            tally_gateway: GatewayOptions::default(),
+            mask_serials: false,
+            backup_max_age_days: tally::backup::DEFAULT_MAX_AGE_DAYS,
+            app_rules: app_rules::builtin_rules().to_vec(),
+            diagnostics: Diagnostics::new(),
         }
     }
     
@@ -35,40 +82,97 @@ impl Collector {
     }
     
     pub fn set_tally_http_endpoint(&mut self, endpoint: String) {
-        self.tally_http_endpoint = Some(endpoint);
+        self.tally_gateway.endpoint = Some(endpoint);
+    }
+    
+    pub fn enable_tally_port_discovery(&mut self, ports: RangeInclusive<u16>) {
+        self.tally_gateway.discovery = Some(ports);
+    }
+    
+    pub fn enable_tally_company_stats(&mut self) {
+        self.tally_gateway.company_stats = true;
+    }
+    
+    pub fn set_tally_odbc_dsn(&mut self, dsn: String) {
+        self.tally_gateway.odbc_dsn = Some(dsn);
+    }
+    
+    pub fn set_tally_odbc_driver(&mut self, driver: String) {
+        self.tally_gateway.odbc_driver = Some(driver);
+    }
+    
+    pub fn mask_license_serials(&mut self) {
+        self.mask_serials = true;
+    }
+    
+    pub fn set_backup_max_age_days(&mut self, days: u64) {
+        self.backup_max_age_days = days;
+    }
+    
+    pub fn set_app_rules(&mut self, rules: Vec<AppRule>) {
+        self.app_rules = rules;
     }
     
     pub fn collect(&self) -> Value {
         let mut system_info = SystemInfo::default();
+        let diagnostics = &self.diagnostics;
         
         // Collect OS information
-        system_info.os = Some(os::collect_os_info());
+        system_info.os = Some(diagnostics.time_probe("os", os::collect_os_info));
         
         // Collect device information
-        system_info.device = Some(device::collect_device_info());
+        system_info.device = Some(diagnostics.time_probe("device", device::collect_device_info));
         
         // Collect CPU information
-        system_info.cpu = Some(cpu::collect_cpu_info());
+        system_info.cpu = Some(diagnostics.time_probe("cpu", cpu::collect_cpu_info));
         
         // Collect memory information
-        system_info.memory = Some(memory::collect_memory_info());
+        system_info.memory = Some(diagnostics.time_probe("memory", memory::collect_memory_info));
         
         // Collect disk information
-        system_info.disks = Some(disk::collect_disk_info());
+        system_info.disks = Some(diagnostics.time_probe("disk", disk::collect_disk_info));
         
         // Collect network information
-        system_info.network = Some(network::collect_network_info());
+        system_info.network = Some(diagnostics.time_probe("network", network::collect_network_info));
         
         // Collect Tally information if enabled
-        if self.tally_enabled {
-            let tally_info = tally::collect_tally_info(self.timeout, self.tally_http_endpoint.clone());
-            system_info.apps = Some(crate::models::AppsInfo {
-                tally: Some(tally_info),
+        let tally = self.tally_enabled.then(|| {
+            let mut tally_info = diagnostics.time_probe("tally", || {
+                tally::collect_tally_info(self.timeout, &self.tally_gateway, diagnostics)
             });
+            if self.mask_serials {
+                tally::license::mask_serials(&mut tally_info);
+            }
+            system_info.warnings.extend(tally::backup::stale_backup_warnings(&mut tally_info, self.backup_max_age_days));
+            tally_info
+        });
+        
+        // Collect the other applications the rules describe
+        let detected = diagnostics.time_probe("apps", || {
+            let registry = LiveRegistry::new();
+            let sources = DetectionSources {
+                registries: vec![&registry],
+                drives: vec![PathBuf::from("C:\\")],
+                ..Default::default()
+            };
+            apps::detect_apps(&self.app_rules, &sources)
+        });
+        if tally.is_some() || !detected.is_empty() {
+            system_info.apps = Some(crate::models::AppsInfo { tally, detected, ..Default::default() });
         }
         
+        diagnostics.finish_collection();
+        
         // Convert to JSON Value
-        serde_json::to_value(system_info).unwrap_or_else(|_| json!({}))
+        serde_json::to_value(system_info).unwrap_or_else(|e| {
+            diagnostics.record_error("collect", format!("serialization failed: {}", e));
+            json!({})
+        })
+    }
+    
+    #[cfg(feature = "http")]
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
     }
     
     pub fn filter_fields(&self, value: Value, selector: String) -> Value {
@@ -81,6 +185,7 @@ impl Collector {
             let collector_info = json!({
                 "name": "QuickSys",
                 "version": env!("CARGO_PKG_VERSION"),
+                "schema_version": crate::schema::SCHEMA_VERSION,
                 "duration_ms": duration_ms
             });
             
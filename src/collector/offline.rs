@@ -0,0 +1,88 @@
+//! Inventory of a Windows volume mounted on another system (`--offline-windows`).
+//!
+//! Reads the registry hives under `Windows\System32\config` instead of the
+//! live registry, so the result describes the installed system, not the host.
+
+use std::path::Path;
+
+use super::apps::{detect_apps, rules::AppRule, DetectionSources};
+use super::hive::Hive;
+use super::registry::{read_current_version, HiveRegistry, RegistrySource};
+use super::tally::installations::resolve_case_insensitive;
+use super::tally::backup::stale_backup_warnings;
+use super::tally::license::mask_serials as mask_license_serials;
+use super::tally::offline::collect_offline_tally_info;
+use crate::models::{AppsInfo, DeviceInfo, OsInfo, SystemInfo};
+
+const SOFTWARE_HIVE: &str = "Windows\\System32\\config\\SOFTWARE";
+const SYSTEM_HIVE: &str = "Windows\\System32\\config\\SYSTEM";
+
+/// Collects OS, device, Tally and other application information from the
+/// volume mounted at `root`. Fails when the SOFTWARE hive cannot be read;
+/// the SYSTEM hive is optional.
+pub fn collect_offline(
+    root: &Path,
+    tally_enabled: bool,
+    mask_serials: bool,
+    backup_max_age_days: u64,
+    app_rules: &[AppRule],
+) -> Result<SystemInfo, String> {
+    let software_path = resolve_case_insensitive(root, SOFTWARE_HIVE);
+    let software = Hive::open(&software_path).map_err(|e| format!("{}: {}", software_path.display(), e))?;
+    let system = Hive::open(&resolve_case_insensitive(root, SYSTEM_HIVE)).ok();
+    let has_system = system.is_some();
+    let registry = HiveRegistry::new(software, system);
+
+    let mut warnings = Vec::new();
+    let tally = tally_enabled.then(|| {
+        let mut installs = collect_offline_tally_info(root, &registry);
+        if mask_serials {
+            mask_license_serials(&mut installs);
+        }
+        warnings = stale_backup_warnings(&mut installs, backup_max_age_days);
+        installs
+    });
+    let sources = DetectionSources {
+        registries: vec![&registry],
+        drives: vec![root.to_path_buf()],
+        ..Default::default()
+    };
+    let detected = detect_apps(app_rules, &sources);
+    let apps = (tally.is_some() || !detected.is_empty()).then_some(AppsInfo { tally, detected, ..Default::default() });
+
+    Ok(SystemInfo {
+        os: Some(offline_os_info(&registry)),
+        device: has_system.then(|| DeviceInfo {
+            hostname: registry.value("SYSTEM\\CurrentControlSet\\Control\\ComputerName\\ComputerName", "ComputerName"),
+            ..Default::default()
+        }),
+        apps,
+        warnings,
+        ..Default::default()
+    })
+}
+
+/// The same `CurrentVersion` values the live collector reads, plus the
+/// architecture the live collector asks the OS for
+fn offline_os_info(registry: &dyn RegistrySource) -> OsInfo {
+    let mut os_info = OsInfo {
+        family: "Windows".to_string(),
+        ..Default::default()
+    };
+    read_current_version(registry, &mut os_info);
+
+    os_info.arch = registry
+        .value("SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment", "PROCESSOR_ARCHITECTURE")
+        .map(|arch| {
+            match arch.to_ascii_uppercase().as_str() {
+                "AMD64" => "x86_64",
+                "ARM64" => "arm64",
+                "X86" => "x86",
+                "ARM" => "arm",
+                _ => "unknown",
+            }
+            .to_string()
+        });
+
+    os_info
+}
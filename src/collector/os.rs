@@ -18,22 +18,7 @@ pub fn collect_os_info() -> OsInfo {
     }
     
     // Get Windows edition and version from registry
-    if let Ok(hklm) = winreg::RegKey::predef(winreg::enums::HKEY_LOCAL_MACHINE) {
-        if let Ok(current_version) = hklm.open_subkey("SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion") {
-            os_info.edition = current_version.get_value::<String, _>("EditionID").ok();
-            os_info.version = current_version.get_value::<String, _>("DisplayVersion").ok();
-            os_info.product_id = current_version.get_value::<String, _>("ProductId")
-                .ok()
-                .map(|id| {
-                    // Only include partial product ID for privacy
-                    if id.len() > 8 {
-                        format!("{}...", &id[0..5])
-                    } else {
-                        id
-                    }
-                });
-        }
-    }
+    super::registry::read_current_version(&super::registry::LiveRegistry::new(), &mut os_info);
     
     // Get system architecture
     unsafe {
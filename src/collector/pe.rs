@@ -0,0 +1,308 @@
+//! Reader for the version resource (VS_VERSIONINFO) of PE executables.
+//!
+//! Pure Rust so file versions can be read on any platform, including from a
+//! mounted Windows volume or a Wine prefix.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::models::FileVersionInfo;
+
+/// Resource type ID of version resources
+const RT_VERSION: u32 = 16;
+
+/// Index of the resource table in the optional header data directories
+const RESOURCE_DIRECTORY: usize = 2;
+
+/// `dwSignature` of VS_FIXEDFILEINFO
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xFEEF_04BD;
+
+/// Language/code page pair of US English Unicode string tables
+const PREFERRED_STRING_TABLE: &str = "040904b0";
+
+#[derive(Debug)]
+pub enum PeError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file is not a PE image
+    NotPe(&'static str),
+    /// A header or resource points outside the file
+    Truncated(&'static str),
+    /// The image carries no version resource
+    NoVersionResource,
+}
+
+impl fmt::Display for PeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeError::Io(e) => write!(f, "I/O error: {}", e),
+            PeError::NotPe(reason) => write!(f, "not a PE image: {}", reason),
+            PeError::Truncated(what) => write!(f, "truncated {}", what),
+            PeError::NoVersionResource => write!(f, "no version resource"),
+        }
+    }
+}
+
+impl From<io::Error> for PeError {
+    fn from(e: io::Error) -> Self {
+        PeError::Io(e)
+    }
+}
+
+/// Reads the version resource of the executable at `path`
+pub fn read_version_info(path: &Path) -> Result<FileVersionInfo, PeError> {
+    parse_version_info(&fs::read(path)?)
+}
+
+/// The version to report for an executable, preferring the product version
+/// vendors show in their About screens
+pub fn display_version(path: &Path) -> Option<String> {
+    let info = read_version_info(path).ok()?;
+    info.product_version.or(info.file_version).or(info.fixed_product_version)
+}
+
+/// Extracts the version resource from the bytes of a PE image
+pub fn parse_version_info(image: &[u8]) -> Result<FileVersionInfo, PeError> {
+    let resource = version_resource(image)?;
+    parse_vs_versioninfo(resource)
+}
+
+struct Section {
+    virtual_address: u32,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+}
+
+/// Locates the raw bytes of the first RT_VERSION resource
+fn version_resource(image: &[u8]) -> Result<&[u8], PeError> {
+    if image.get(0..2) != Some(b"MZ") {
+        return Err(PeError::NotPe("missing MZ signature"));
+    }
+    let pe_offset = read_u32(image, 0x3C).ok_or(PeError::Truncated("DOS header"))? as usize;
+    if image.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+        return Err(PeError::NotPe("missing PE signature"));
+    }
+
+    // COFF file header
+    let coff = pe_offset + 4;
+    let section_count = read_u16(image, coff + 2).ok_or(PeError::Truncated("COFF header"))? as usize;
+    let optional_size = read_u16(image, coff + 16).ok_or(PeError::Truncated("COFF header"))? as usize;
+
+    // Optional header: only the data directory layout differs between PE32 and PE32+
+    let optional = coff + 20;
+    let (count_offset, directories_offset) = match read_u16(image, optional) {
+        Some(0x10B) => (92, 96),
+        Some(0x20B) => (108, 112),
+        Some(_) => return Err(PeError::NotPe("unknown optional header magic")),
+        None => return Err(PeError::Truncated("optional header")),
+    };
+    let directory_count = read_u32(image, optional + count_offset).ok_or(PeError::Truncated("optional header"))? as usize;
+    if directory_count <= RESOURCE_DIRECTORY {
+        return Err(PeError::NoVersionResource);
+    }
+    let directory = optional + directories_offset + RESOURCE_DIRECTORY * 8;
+    let resource_rva = read_u32(image, directory).ok_or(PeError::Truncated("data directories"))?;
+    if resource_rva == 0 {
+        return Err(PeError::NoVersionResource);
+    }
+
+    let sections: Vec<Section> = (0..section_count)
+        .map(|i| {
+            let header = optional + optional_size + i * 40;
+            Some(Section {
+                virtual_size: read_u32(image, header + 8)?,
+                virtual_address: read_u32(image, header + 12)?,
+                raw_size: read_u32(image, header + 16)?,
+                raw_offset: read_u32(image, header + 20)?,
+            })
+        })
+        .collect::<Option<_>>()
+        .ok_or(PeError::Truncated("section table"))?;
+
+    let rva_to_offset = |rva: u32| -> Option<usize> {
+        sections.iter().find_map(|s| {
+            let size = s.virtual_size.max(s.raw_size);
+            let delta = rva.checked_sub(s.virtual_address).filter(|d| *d < size)?;
+            (delta < s.raw_size).then(|| s.raw_offset as usize + delta as usize)
+        })
+    };
+
+    let root = rva_to_offset(resource_rva).ok_or(PeError::Truncated("resource section"))?;
+    let resources = &image[root.min(image.len())..];
+
+    // Type → name → language; the first name and language are taken
+    let names = find_entry(resources, 0, Some(RT_VERSION))?.ok_or(PeError::NoVersionResource)?;
+    let languages = find_entry(resources, subdirectory(names)?, None)?.ok_or(PeError::NoVersionResource)?;
+    let data_entry = find_entry(resources, subdirectory(languages)?, None)?.ok_or(PeError::NoVersionResource)?;
+    if data_entry & 0x8000_0000 != 0 {
+        return Err(PeError::NotPe("malformed resource tree"));
+    }
+
+    let data_rva = read_u32(resources, data_entry).ok_or(PeError::Truncated("resource data entry"))?;
+    let data_size = read_u32(resources, data_entry + 4).ok_or(PeError::Truncated("resource data entry"))? as usize;
+    let start = rva_to_offset(data_rva).ok_or(PeError::Truncated("version resource"))?;
+    image.get(start..start + data_size).ok_or(PeError::Truncated("version resource"))
+}
+
+/// Looks up an entry of the resource directory at `directory`, returning the
+/// offset it points to. `None` as the ID matches the first entry.
+fn find_entry(resources: &[u8], directory: usize, id: Option<u32>) -> Result<Option<usize>, PeError> {
+    let named = read_u16(resources, directory + 12).ok_or(PeError::Truncated("resource directory"))? as usize;
+    let ids = read_u16(resources, directory + 14).ok_or(PeError::Truncated("resource directory"))? as usize;
+
+    for i in 0..named + ids {
+        let entry = directory + 16 + i * 8;
+        let name = read_u32(resources, entry).ok_or(PeError::Truncated("resource directory"))?;
+        let target = read_u32(resources, entry + 4).ok_or(PeError::Truncated("resource directory"))?;
+
+        // Named entries have the high bit set and never match a numeric type
+        let matches = match id {
+            Some(id) => name & 0x8000_0000 == 0 && name == id,
+            None => true,
+        };
+        if matches {
+            return Ok(Some(target as usize));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Offset of the directory an entry points to; the high bit marks directories
+fn subdirectory(target: usize) -> Result<usize, PeError> {
+    if target & 0x8000_0000 == 0 {
+        return Err(PeError::NotPe("malformed resource tree"));
+    }
+    Ok(target & 0x7FFF_FFFF)
+}
+
+/// One node of the VS_VERSIONINFO tree
+struct Block<'a> {
+    key: String,
+    /// 1 for text values, 0 for binary
+    value_type: u16,
+    value: &'a [u8],
+    children: &'a [u8],
+}
+
+/// Parses the block at the start of `data`, returning it and the bytes after it
+fn parse_block(data: &[u8]) -> Option<(Block<'_>, &[u8])> {
+    let length = read_u16(data, 0)? as usize;
+    let value_length = read_u16(data, 2)? as usize;
+    let value_type = read_u16(data, 4)?;
+    if length < 6 || length > data.len() {
+        return None;
+    }
+    let block = &data[..length];
+
+    let (key, key_end) = read_utf16z(block, 6)?;
+    let value_start = align4(key_end).min(length);
+    // Text lengths count UTF-16 units, binary lengths count bytes
+    let value_bytes = if value_type == 1 { value_length * 2 } else { value_length };
+    let value_end = (value_start + value_bytes).min(length);
+    let children_start = align4(value_end).min(length);
+
+    let rest = &data[align4(length).min(data.len())..];
+    Some((
+        Block {
+            key,
+            value_type,
+            value: &block[value_start..value_end],
+            children: &block[children_start..],
+        },
+        rest,
+    ))
+}
+
+fn blocks(mut data: &[u8]) -> Vec<Block<'_>> {
+    let mut blocks = Vec::new();
+    while let Some((block, rest)) = parse_block(data) {
+        blocks.push(block);
+        data = rest;
+    }
+    blocks
+}
+
+fn parse_vs_versioninfo(resource: &[u8]) -> Result<FileVersionInfo, PeError> {
+    let (root, _) = parse_block(resource).ok_or(PeError::Truncated("VS_VERSIONINFO"))?;
+    if root.key != "VS_VERSION_INFO" {
+        return Err(PeError::NotPe("version resource has an unexpected key"));
+    }
+
+    let mut info = FileVersionInfo::default();
+
+    if read_u32(root.value, 0) == Some(FIXED_FILE_INFO_SIGNATURE) {
+        info.fixed_file_version = fixed_version(root.value, 8);
+        info.fixed_product_version = fixed_version(root.value, 16);
+    }
+
+    // Prefer the US English table, else whichever comes first
+    let tables: Vec<Block> = blocks(root.children)
+        .into_iter()
+        .filter(|b| b.key == "StringFileInfo")
+        .flat_map(|b| blocks(b.children))
+        .collect();
+    let table = tables
+        .iter()
+        .find(|t| t.key.eq_ignore_ascii_case(PREFERRED_STRING_TABLE))
+        .or_else(|| tables.first());
+
+    if let Some(table) = table {
+        for string in blocks(table.children) {
+            let value = if string.value_type == 1 {
+                read_utf16z(string.value, 0).map(|(text, _)| text.trim().to_string())
+            } else {
+                None
+            };
+            let value = value.filter(|v| !v.is_empty());
+
+            match string.key.as_str() {
+                "FileVersion" => info.file_version = value,
+                "ProductVersion" => info.product_version = value,
+                "ProductName" => info.product_name = value,
+                "CompanyName" => info.company_name = value,
+                "FileDescription" => info.file_description = value,
+                _ => {}
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+/// Formats a version stored as two DWORDs (major.minor, build.revision)
+fn fixed_version(fixed: &[u8], offset: usize) -> Option<String> {
+    let most = read_u32(fixed, offset)?;
+    let least = read_u32(fixed, offset + 4)?;
+    Some(format!("{}.{}.{}.{}", most >> 16, most & 0xFFFF, least >> 16, least & 0xFFFF))
+}
+
+/// Reads a NUL-terminated UTF-16LE string, returning it and the offset after the terminator
+fn read_utf16z(data: &[u8], start: usize) -> Option<(String, usize)> {
+    let mut units = Vec::new();
+    let mut offset = start;
+    loop {
+        match read_u16(data, offset) {
+            Some(0) => return Some((String::from_utf16_lossy(&units), offset + 2)),
+            Some(unit) => units.push(unit),
+            // Values are sometimes stored without their terminator
+            None => return (!units.is_empty()).then(|| (String::from_utf16_lossy(&units), offset)),
+        }
+        offset += 2;
+    }
+}
+
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Option<u16> {
+    data.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
@@ -0,0 +1,298 @@
+//! Running Tally processes and the TCP ports they listen on.
+//!
+//! Linux reads `/proc` (or `$HOST_PROC` when the host's proc filesystem is
+//! mounted elsewhere, as in monitoring containers), which also covers Tally
+//! running under Wine. Windows asks WMI for processes and IP Helper for the
+//! listening sockets.
+
+#[cfg(not(windows))]
+use std::collections::{BTreeMap, BTreeSet};
+#[cfg(not(windows))]
+use std::env;
+#[cfg(not(windows))]
+use std::fs;
+#[cfg(not(windows))]
+use std::path::{Path, PathBuf};
+
+/// A process that looks like Tally
+#[derive(Debug, Default, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Path of the executable as Tally sees it (a Windows path under Wine)
+    pub executable: Option<String>,
+    pub command_line: Option<String>,
+    /// ISO 8601 UTC
+    pub start_time: Option<String>,
+    /// Resident set size
+    pub memory_bytes: Option<u64>,
+    pub listening_ports: Vec<u16>,
+    /// Wine prefix the process runs in, when it runs under Wine
+    pub wine_prefix: Option<String>,
+}
+
+impl ProcessInfo {
+    /// Directory of the executable, with either separator style
+    pub fn executable_dir(&self) -> Option<&str> {
+        let executable = self.executable.as_deref()?;
+        executable.rfind(['\\', '/']).map(|end| &executable[..end])
+    }
+}
+
+/// Tally.exe, TallyPrime.exe and the like; matched on the executable name
+pub fn is_tally_executable(path: &str) -> bool {
+    let name = path.rsplit(['\\', '/']).next().unwrap_or(path).to_ascii_lowercase();
+    name.starts_with("tally") && name.ends_with(".exe")
+}
+
+/// Lists the running Tally processes
+#[cfg(not(windows))]
+pub fn tally_processes() -> Vec<ProcessInfo> {
+    let root = env::var_os("HOST_PROC").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/proc"));
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Vec::new();
+    };
+
+    let mut processes: Vec<ProcessInfo> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+        .filter_map(|pid| read_process(&root.join(pid.to_string()), pid))
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+
+    if !processes.is_empty() {
+        let listening = listening_inodes(&root);
+        for process in &mut processes {
+            process.listening_ports = socket_inodes(&root.join(process.pid.to_string()))
+                .iter()
+                .filter_map(|inode| listening.get(inode).copied())
+                .collect::<BTreeSet<u16>>()
+                .into_iter()
+                .collect();
+        }
+    }
+
+    processes
+}
+
+/// Reads one `/proc/<pid>` directory, or None when it is not a Tally process
+#[cfg(not(windows))]
+fn read_process(dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let cmdline = fs::read(dir.join("cmdline")).ok()?;
+    let args: Vec<String> = cmdline
+        .split(|&b| b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+    let comm = fs::read_to_string(dir.join("comm")).unwrap_or_default();
+
+    // Wine names the process after the Windows executable; argv[0] carries its path
+    let executable = args.first().filter(|arg| is_tally_executable(arg)).cloned();
+    if executable.is_none() && !is_tally_executable(comm.trim()) {
+        return None;
+    }
+
+    let environ = fs::read(dir.join("environ")).unwrap_or_default();
+    let variable = |name: &str| {
+        environ
+            .split(|&b| b == 0)
+            .filter_map(|entry| std::str::from_utf8(entry).ok()?.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+    };
+    let under_wine = executable.as_deref().is_some_and(|path| path.as_bytes().get(1) == Some(&b':'));
+    let wine_prefix = if under_wine {
+        variable("WINEPREFIX").or_else(|| variable("HOME").map(|home| format!("{}/.wine", home.trim_end_matches('/'))))
+    } else {
+        None
+    };
+
+    Some(ProcessInfo {
+        pid,
+        executable,
+        command_line: Some(args.join(" ")).filter(|line| !line.is_empty()),
+        start_time: start_time(dir),
+        memory_bytes: resident_bytes(dir),
+        listening_ports: Vec::new(),
+        wine_prefix,
+    })
+}
+
+/// Clock ticks per second used by `/proc/<pid>/stat`; 100 on every Linux platform we ship for
+#[cfg(not(windows))]
+const CLOCK_TICKS_PER_SECOND: u64 = 100;
+
+/// Start time from field 22 of `/proc/<pid>/stat` and the boot time in `/proc/stat`
+#[cfg(not(windows))]
+fn start_time(dir: &Path) -> Option<String> {
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    // The command name is parenthesised and may contain spaces; fields resume after the last ')'
+    let fields: Vec<&str> = stat[stat.rfind(')')? + 1..].split_whitespace().collect();
+    let ticks: u64 = fields.get(19)?.parse().ok()?;
+
+    let system_stat = fs::read_to_string(dir.parent()?.join("stat")).ok()?;
+    let boot_time: u64 = system_stat.lines().find_map(|line| line.strip_prefix("btime "))?.trim().parse().ok()?;
+
+    Some(crate::utils::format_unix_time(boot_time + ticks / CLOCK_TICKS_PER_SECOND))
+}
+
+/// `VmRSS` from `/proc/<pid>/status`
+#[cfg(not(windows))]
+fn resident_bytes(dir: &Path) -> Option<u64> {
+    let status = fs::read_to_string(dir.join("status")).ok()?;
+    let value = status.lines().find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kilobytes: u64 = value.trim().trim_end_matches("kB").trim().parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+/// Socket inodes the process holds open, from the `socket:[inode]` links in `fd`
+#[cfg(not(windows))]
+fn socket_inodes(dir: &Path) -> Vec<u64> {
+    let Ok(entries) = fs::read_dir(dir.join("fd")) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| fs::read_link(entry.path()).ok())
+        .filter_map(|target| {
+            let target = target.to_string_lossy();
+            target.strip_prefix("socket:[")?.strip_suffix(']')?.parse().ok()
+        })
+        .collect()
+}
+
+/// Inode → port of every listening TCP socket in `net/tcp` and `net/tcp6`
+#[cfg(not(windows))]
+fn listening_inodes(root: &Path) -> BTreeMap<u64, u16> {
+    /// `st` column value of a socket in the LISTEN state
+    const TCP_LISTEN: &str = "0A";
+
+    let mut listening = BTreeMap::new();
+    for table in ["net/tcp", "net/tcp6"] {
+        let Ok(text) = fs::read_to_string(root.join(table)) else {
+            continue;
+        };
+        // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+        for line in text.lines().skip(1) {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&TCP_LISTEN) {
+                continue;
+            }
+            let port = fields.get(1).and_then(|local| local.rsplit(':').next()).and_then(|hex| u16::from_str_radix(hex, 16).ok());
+            let inode = fields.get(9).and_then(|inode| inode.parse::<u64>().ok());
+            if let (Some(port), Some(inode)) = (port, inode) {
+                listening.insert(inode, port);
+            }
+        }
+    }
+    listening
+}
+
+/// Lists the running Tally processes
+#[cfg(windows)]
+pub fn tally_processes() -> Vec<ProcessInfo> {
+    let Ok(com) = wmi::COMLibrary::new() else {
+        return Vec::new();
+    };
+    let Ok(wmi_con) = wmi::WMIConnection::new(com) else {
+        return Vec::new();
+    };
+    let Ok(results) = wmi_con.query::<serde_json::Value>(
+        "SELECT ProcessId, Name, ExecutablePath, CommandLine, CreationDate, WorkingSetSize FROM Win32_Process WHERE Name LIKE 'tally%.exe'",
+    ) else {
+        return Vec::new();
+    };
+
+    let listening = listening_ports();
+    let mut processes: Vec<ProcessInfo> = results
+        .iter()
+        .filter_map(|process| {
+            let pid = process.get("ProcessId")?.as_u64()? as u32;
+            let text = |key: &str| process.get(key).and_then(|v| v.as_str()).map(str::to_string);
+            // uint64 properties arrive as strings
+            let memory_bytes = process
+                .get("WorkingSetSize")
+                .and_then(|v| v.as_u64().or_else(|| v.as_str()?.parse().ok()));
+
+            Some(ProcessInfo {
+                pid,
+                executable: text("ExecutablePath").or_else(|| text("Name")),
+                command_line: text("CommandLine"),
+                start_time: text("CreationDate").and_then(|date| cim_datetime(&date)),
+                memory_bytes,
+                listening_ports: listening.iter().filter(|(owner, _)| *owner == pid).map(|(_, port)| *port).collect(),
+                wine_prefix: None,
+            })
+        })
+        .collect();
+    processes.sort_by_key(|process| process.pid);
+    processes
+}
+
+/// (owning PID, port) of every listening IPv4 and IPv6 TCP socket
+#[cfg(windows)]
+fn listening_ports() -> Vec<(u32, u16)> {
+    use windows::Win32::Foundation::BOOL;
+    use windows::Win32::NetworkManagement::IpHelper::{
+        GetExtendedTcpTable, MIB_TCP6ROW_OWNER_PID, MIB_TCPROW_OWNER_PID, TCP_TABLE_OWNER_PID_LISTENER,
+    };
+
+    const AF_INET: u32 = 2;
+    const AF_INET6: u32 = 23;
+
+    let mut ports = Vec::new();
+    for family in [AF_INET, AF_INET6] {
+        unsafe {
+            // First call to get the required buffer size
+            let mut size = 0u32;
+            GetExtendedTcpTable(None, &mut size, BOOL(0), family, TCP_TABLE_OWNER_PID_LISTENER, 0);
+            if size == 0 {
+                continue;
+            }
+
+            let mut buffer = vec![0u8; size as usize];
+            if GetExtendedTcpTable(Some(buffer.as_mut_ptr() as *mut _), &mut size, BOOL(0), family, TCP_TABLE_OWNER_PID_LISTENER, 0) != 0 {
+                continue;
+            }
+
+            // The table is a row count followed by the rows
+            let count = u32::from_ne_bytes([buffer[0], buffer[1], buffer[2], buffer[3]]) as usize;
+            let rows = buffer.as_ptr().add(std::mem::size_of::<u32>());
+            for i in 0..count {
+                // Ports are in network byte order in the low 16 bits
+                let (pid, port) = if family == AF_INET {
+                    let row = std::ptr::read_unaligned((rows as *const MIB_TCPROW_OWNER_PID).add(i));
+                    (row.dwOwningPid, row.dwLocalPort)
+                } else {
+                    let row = std::ptr::read_unaligned((rows as *const MIB_TCP6ROW_OWNER_PID).add(i));
+                    (row.dwOwningPid, row.dwLocalPort)
+                };
+                ports.push((pid, u16::from_be(port as u16)));
+            }
+        }
+    }
+
+    ports.sort();
+    ports.dedup();
+    ports
+}
+
+/// Converts a CIM datetime (`20240315103000.000000+330`, offset in minutes) to ISO 8601 UTC
+#[cfg(windows)]
+fn cim_datetime(value: &str) -> Option<String> {
+    let digits = |range: std::ops::Range<usize>| value.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (digits(0..4)?, digits(4..6)?, digits(6..8)?);
+    let (hour, minute, second) = (digits(8..10)?, digits(10..12)?, digits(12..14)?);
+    let offset_minutes = value.get(21..)?.parse::<i64>().ok()?;
+
+    // Days since the Unix epoch (proleptic Gregorian calendar)
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y.div_euclid(400);
+    let year_of_era = y - era * 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    let local = days * 86400 + hour * 3600 + minute * 60 + second;
+    let utc = local - offset_minutes * 60;
+    Some(crate::utils::format_unix_time(u64::try_from(utc).ok()?))
+}
@@ -0,0 +1,275 @@
+//! Read access to registry data, wherever it comes from.
+//!
+//! Detection code asks a `RegistrySource` for keys under HKEY_LOCAL_MACHINE
+//! (e.g. `SOFTWARE\Tally Solutions`). The live registry, hives from a mounted
+//! volume, Wine's text registry and JSON fixtures all answer the same way, so
+//! one detection routine serves every platform. Lookups are case-insensitive.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde_json::Value;
+
+use super::hive::{Hive, HiveError, HiveKey};
+use crate::models::OsInfo;
+use crate::utils::mask_identifier;
+
+/// Registry keys and values, addressed by backslash-separated paths
+pub trait RegistrySource {
+    /// Names of the immediate subkeys of `path`; empty when the key does not exist
+    fn subkeys(&self, path: &str) -> Vec<String>;
+
+    /// A value as text: strings as stored (multi-strings joined with newlines),
+    /// integers in decimal. `""` names the default value.
+    fn value(&self, path: &str, name: &str) -> Option<String>;
+}
+
+#[derive(Debug)]
+pub enum RegistryError {
+    Io(io::Error),
+    Hive(HiveError),
+    /// A JSON snapshot that is not an object of keys holding objects of values
+    Json(String),
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Io(e) => write!(f, "I/O error: {}", e),
+            RegistryError::Hive(e) => write!(f, "{}", e),
+            RegistryError::Json(e) => write!(f, "invalid registry snapshot: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for RegistryError {
+    fn from(e: io::Error) -> Self {
+        RegistryError::Io(e)
+    }
+}
+
+impl From<HiveError> for RegistryError {
+    fn from(e: HiveError) -> Self {
+        RegistryError::Hive(e)
+    }
+}
+
+/// Loads a registry snapshot: a regf hive is mounted as `SOFTWARE`,
+/// anything else is read as a JSON fixture (see `MemoryRegistry::from_json`)
+pub fn load_snapshot(path: &Path) -> Result<Box<dyn RegistrySource>, RegistryError> {
+    let data = fs::read(path)?;
+    if data.starts_with(b"regf") {
+        Ok(Box::new(HiveRegistry::new(Hive::from_bytes(data)?, None)))
+    } else {
+        Ok(Box::new(MemoryRegistry::from_json(&String::from_utf8_lossy(&data))?))
+    }
+}
+
+/// Fills the edition, display version, build and masked product ID from
+/// `SOFTWARE\Microsoft\Windows NT\CurrentVersion`. A build already known
+/// from the OS is kept.
+pub fn read_current_version(registry: &dyn RegistrySource, os_info: &mut OsInfo) {
+    const CURRENT_VERSION: &str = "SOFTWARE\\Microsoft\\Windows NT\\CurrentVersion";
+
+    os_info.edition = registry.value(CURRENT_VERSION, "EditionID");
+    os_info.version = registry.value(CURRENT_VERSION, "DisplayVersion");
+    if os_info.build.is_none() {
+        os_info.build = registry
+            .value(CURRENT_VERSION, "CurrentBuildNumber")
+            .or_else(|| registry.value(CURRENT_VERSION, "CurrentBuild"));
+    }
+    // Only include partial product ID for privacy
+    os_info.product_id = registry.value(CURRENT_VERSION, "ProductId").map(mask_identifier);
+}
+
+/// The registry of the running Windows system
+#[cfg(windows)]
+pub struct LiveRegistry {
+    hklm: winreg::RegKey,
+}
+
+#[cfg(windows)]
+impl LiveRegistry {
+    pub fn new() -> Self {
+        Self {
+            hklm: winreg::RegKey::predef(winreg::enums::HKEY_LOCAL_MACHINE),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Default for LiveRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(windows)]
+impl RegistrySource for LiveRegistry {
+    fn subkeys(&self, path: &str) -> Vec<String> {
+        match self.hklm.open_subkey(path) {
+            Ok(key) => key.enum_keys().flatten().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn value(&self, path: &str, name: &str) -> Option<String> {
+        let key = self.hklm.open_subkey(path).ok()?;
+        match key.get_value::<String, _>(name) {
+            Ok(value) => Some(value),
+            // Multi-strings and integers come back as text like the other sources
+            Err(_) => key
+                .get_value::<Vec<String>, _>(name)
+                .map(|values| values.join("\n"))
+                .or_else(|_| key.get_value::<u32, _>(name).map(|v| v.to_string()))
+                .or_else(|_| key.get_value::<u64, _>(name).map(|v| v.to_string()))
+                .ok(),
+        }
+    }
+}
+
+/// The SOFTWARE and (optionally) SYSTEM hives of a Windows installation,
+/// mounted where the live registry shows them. `SYSTEM\CurrentControlSet`
+/// resolves to the control set named by `Select\Current`.
+pub struct HiveRegistry {
+    software: Hive,
+    system: Option<Hive>,
+}
+
+impl HiveRegistry {
+    pub fn new(software: Hive, system: Option<Hive>) -> Self {
+        Self { software, system }
+    }
+
+    fn key(&self, path: &str) -> Option<HiveKey<'_>> {
+        let (root, rest) = path.split_once('\\').unwrap_or((path, ""));
+
+        if root.eq_ignore_ascii_case("SOFTWARE") {
+            return self.software.key(rest);
+        }
+        if !root.eq_ignore_ascii_case("SYSTEM") {
+            return None;
+        }
+
+        let system = self.system.as_ref()?;
+        let (set, below) = rest.split_once('\\').unwrap_or((rest, ""));
+        if !set.eq_ignore_ascii_case("CurrentControlSet") {
+            return system.key(rest);
+        }
+        let current = system
+            .key("Select")
+            .and_then(|select| select.value("Current"))
+            .and_then(|current| current.parse::<u32>().ok())
+            .unwrap_or(1);
+        system.key(&format!("ControlSet{:03}\\{}", current, below))
+    }
+}
+
+impl RegistrySource for HiveRegistry {
+    fn subkeys(&self, path: &str) -> Vec<String> {
+        self.key(path).map(|key| key.subkeys().iter().map(HiveKey::name).collect()).unwrap_or_default()
+    }
+
+    fn value(&self, path: &str, name: &str) -> Option<String> {
+        self.key(path)?.value(name)
+    }
+}
+
+/// Registry data held in memory: parsed from Wine's registry files, loaded
+/// from a JSON fixture, or built up value by value
+#[derive(Debug, Default)]
+pub struct MemoryRegistry {
+    /// Lowercase key path → original path and values by lowercase name
+    keys: BTreeMap<String, (String, BTreeMap<String, String>)>,
+}
+
+impl MemoryRegistry {
+    /// Reads a snapshot of the form
+    /// `{"SOFTWARE\\Tally Solutions\\TallyPrime": {"InstallDir": "C:\\..."}}`.
+    /// Paths may start with `HKLM\` or `HKEY_LOCAL_MACHINE\`; values may be
+    /// strings, integers or arrays of strings (multi-strings).
+    pub fn from_json(text: &str) -> Result<MemoryRegistry, RegistryError> {
+        let document: Value = serde_json::from_str(text).map_err(|e| RegistryError::Json(e.to_string()))?;
+        let Value::Object(keys) = document else {
+            return Err(RegistryError::Json("expected an object of registry keys".to_string()));
+        };
+
+        let mut registry = MemoryRegistry::default();
+        for (path, values) in keys {
+            let path = ["HKLM\\", "HKEY_LOCAL_MACHINE\\"]
+                .iter()
+                .find_map(|hive| strip_prefix_ignore_case(&path, hive))
+                .unwrap_or(&path);
+            registry.add_key(path);
+
+            let Value::Object(values) = values else {
+                return Err(RegistryError::Json(format!("values of {} must be an object", path)));
+            };
+            for (name, value) in values {
+                let text = match value {
+                    Value::String(s) => s,
+                    Value::Number(n) if n.is_u64() => n.to_string(),
+                    Value::Array(items) => items
+                        .iter()
+                        .map(|item| item.as_str().map(str::to_string))
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or_else(|| RegistryError::Json(format!("{}\\{} must list strings", path, name)))?
+                        .join("\n"),
+                    other => {
+                        return Err(RegistryError::Json(format!("unsupported value {} for {}\\{}", other, path, name)));
+                    }
+                };
+                registry.set_value(path, &name, text);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Creates `path` if it does not exist yet
+    pub fn add_key(&mut self, path: &str) {
+        let path = path.trim_matches('\\');
+        self.keys
+            .entry(path.to_ascii_lowercase())
+            .or_insert_with(|| (path.to_string(), BTreeMap::new()));
+    }
+
+    pub fn set_value(&mut self, path: &str, name: &str, value: String) {
+        self.add_key(path);
+        if let Some((_, values)) = self.keys.get_mut(&path.trim_matches('\\').to_ascii_lowercase()) {
+            values.insert(name.to_ascii_lowercase(), value);
+        }
+    }
+}
+
+impl RegistrySource for MemoryRegistry {
+    fn subkeys(&self, path: &str) -> Vec<String> {
+        let prefix = format!("{}\\", path.trim_matches('\\').to_ascii_lowercase());
+        let mut names: BTreeMap<String, String> = BTreeMap::new();
+
+        for (lower, (original, _)) in self.keys.range(prefix.clone()..) {
+            if !lower.starts_with(&prefix) {
+                break;
+            }
+            // Keys without values of their own only appear as part of a deeper path
+            let child = original[prefix.len()..].split('\\').next().unwrap_or("");
+            if !child.is_empty() {
+                names.entry(child.to_ascii_lowercase()).or_insert_with(|| child.to_string());
+            }
+        }
+
+        names.into_values().collect()
+    }
+
+    fn value(&self, path: &str, name: &str) -> Option<String> {
+        let (_, values) = self.keys.get(&path.trim_matches('\\').to_ascii_lowercase())?;
+        values.get(&name.to_ascii_lowercase()).cloned()
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = text.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix).then(|| &text[prefix.len()..])
+}
@@ -0,0 +1,181 @@
+//! Freshness of Tally backups: the newest backup archive of each company.
+//!
+//! Tally's Backup writes `TBK900.001` (ERP 9) or `TBK1800.001` style archives
+//! holding every selected company. An archive below a folder named after a
+//! company number belongs to that company. Any other archive belongs to the
+//! only company of a single-company installation; with several companies,
+//! it cannot tell which ones it holds.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::installations::{subdirectories, HostPath};
+use crate::models::{TallyBackup, TallyInfo, Warning};
+use crate::utils::format_system_time;
+
+/// Company number of the newest archive that cannot be tied to a company
+pub const UNKNOWN_COMPANY: &str = "unknown";
+
+/// Days after which a backup is stale unless `--backup-max-age-days` says otherwise
+pub const DEFAULT_MAX_AGE_DAYS: u64 = 7;
+
+/// Places shops commonly keep backups, as Tally sees them
+const COMMON_BACKUP_DIRS: [&str; 6] = [
+    "C:\\TallyBackup",
+    "C:\\Tally Backup",
+    "C:\\Tally\\Backup",
+    "D:\\TallyBackup",
+    "D:\\Tally Backup",
+    "D:\\Tally\\Backup",
+];
+
+/// One archive found on disk
+struct Archive {
+    path: PathBuf,
+    /// Company number of the folder the archive sits in, if any
+    company: Option<String>,
+    size_bytes: u64,
+    modified: SystemTime,
+}
+
+/// Newest backup of every company in the installation's data folders, from
+/// the backup directory in tally.ini, `Backup` below the install and data
+/// directories and the common locations. Companies without any backup are
+/// listed without a path. With several companies, the newest archive outside
+/// a company folder follows them as the `UNKNOWN_COMPANY` entry.
+pub fn find_backups(install: &TallyInfo, host_path: HostPath) -> Option<Vec<TallyBackup>> {
+    let companies: Vec<&str> = install.data_folders.iter().flatten().map(|f| f.company_number.as_str()).collect();
+    if companies.is_empty() {
+        return None;
+    }
+
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(config) = &install.config {
+        if let Some(dir) = &config.backup_directory {
+            candidates.push(host_path(install, dir));
+        }
+        if let Some(data) = &config.data_directory {
+            candidates.push(host_path(install, data).join("Backup"));
+        }
+    }
+    if let Some(install_path) = &install.install_path {
+        candidates.push(host_path(install, install_path).join("Backup"));
+    }
+    candidates.extend(COMMON_BACKUP_DIRS.iter().map(|dir| host_path(install, dir)));
+
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in candidates {
+        if !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    let archives: Vec<Archive> = dirs.iter().flat_map(|dir| scan_backup_directory(dir)).collect();
+    let single_company = companies.len() == 1;
+
+    let mut backups: Vec<TallyBackup> = companies
+        .into_iter()
+        .map(|company| {
+            let newest = archives
+                .iter()
+                .filter(|a| a.company.as_deref() == Some(company) || (single_company && a.company.is_none()))
+                .max_by_key(|a| a.modified);
+            backup_of(company, newest)
+        })
+        .collect();
+    if !single_company {
+        let unattributed = archives.iter().filter(|a| a.company.is_none()).max_by_key(|a| a.modified);
+        if unattributed.is_some() {
+            backups.push(backup_of(UNKNOWN_COMPANY, unattributed));
+        }
+    }
+    Some(backups)
+}
+
+fn backup_of(company: &str, newest: Option<&Archive>) -> TallyBackup {
+    let mut backup = TallyBackup {
+        company_number: company.to_string(),
+        ..Default::default()
+    };
+    if let Some(archive) = newest {
+        backup.path = Some(archive.path.to_string_lossy().into_owned());
+        backup.size_bytes = Some(archive.size_bytes);
+        backup.last_modified = Some(format_system_time(archive.modified));
+        backup.age_days = Some(SystemTime::now().duration_since(archive.modified).map_or(0, |age| age.as_secs() / 86_400));
+    }
+    backup
+}
+
+/// Marks backups older than `max_age_days` (or missing) as stale and returns
+/// a warning for each. An archive of unknown companies says nothing about
+/// any one company's freshness.
+pub fn stale_backup_warnings(installs: &mut [TallyInfo], max_age_days: u64) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+
+    for install in installs.iter_mut() {
+        let product = install.variant.clone().unwrap_or_else(|| "Tally".to_string());
+        for backup in install.backups.iter_mut().flatten().filter(|b| b.company_number != UNKNOWN_COMPANY) {
+            let message = match backup.age_days {
+                None => format!("{} company {} has no backup", product, backup.company_number),
+                Some(age) if age > max_age_days => format!(
+                    "{} company {}: newest backup is {} days old (limit {})",
+                    product, backup.company_number, age, max_age_days
+                ),
+                Some(_) => continue,
+            };
+            backup.stale = true;
+            warnings.push(Warning {
+                code: "tally_backup_stale".to_string(),
+                message,
+            });
+        }
+    }
+
+    warnings
+}
+
+/// Archives directly in `dir` and one level below it (dated or per-company folders)
+fn scan_backup_directory(dir: &Path) -> Vec<Archive> {
+    let mut archives = archives_in(dir, None);
+    for subdir in subdirectories(dir) {
+        let name = subdir.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let company = Some(name).filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        archives.extend(archives_in(&subdir, company));
+    }
+    archives
+}
+
+fn archives_in(dir: &Path, company: Option<String>) -> Vec<Archive> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|entry| is_backup_archive(&entry.file_name().to_string_lossy()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some(Archive {
+                path: entry.path(),
+                company: company.clone(),
+                size_bytes: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// `TBK900.001`, `tbk1800.012`: "TBK", digits, a dot and a numeric sequence
+fn is_backup_archive(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    let Some(rest) = upper.strip_prefix("TBK") else {
+        return false;
+    };
+    let Some((release, sequence)) = rest.split_once('.') else {
+        return false;
+    };
+    !release.is_empty()
+        && !sequence.is_empty()
+        && release.bytes().all(|b| b.is_ascii_digit())
+        && sequence.bytes().all(|b| b.is_ascii_digit())
+}
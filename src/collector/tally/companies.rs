@@ -0,0 +1,140 @@
+//! Company list discovery through the Tally XML API
+
+use std::time::Duration;
+
+use super::xml_api::{aggregate_export_envelope, collection_export_envelope, line_error, parse_records};
+use super::xml_client::{Charset, TallyHttpError, post_xml};
+use crate::models::{TallyCompany, TallyCompanyStats};
+use crate::utils::parse_tally_date;
+
+/// Inline collection names; private names avoid clashing with user TDLs
+const COMPANY_COLLECTION: &str = "QuickSys Companies";
+const MASTER_COUNT_COLLECTION: &str = "QuickSys Master Counts";
+const VOUCHER_SUMMARY_COLLECTION: &str = "QuickSys Voucher Summary";
+
+/// Asks the running Tally instance for its loaded companies
+pub fn fetch_companies(endpoint: &str, timeout: Duration) -> Result<Vec<TallyCompany>, TallyHttpError> {
+    let envelope = collection_export_envelope(
+        COMPANY_COLLECTION,
+        "Company",
+        &["Name", "GUID", "CompanyNumber", "BooksFrom", "LastVoucherDate"],
+        &["DataPath : $$SysInfo:DataPath"],
+        None,
+    );
+
+    let response = post_xml(endpoint, &envelope, Charset::Utf8, timeout)?;
+
+    if let Some(error) = line_error(&response) {
+        return Err(TallyHttpError::Malformed(format!("Tally rejected the company export: {}", error)));
+    }
+
+    Ok(parse_companies(&response))
+}
+
+/// Maps `<COMPANY>` records from an export response onto `TallyCompany`
+pub fn parse_companies(xml: &str) -> Vec<TallyCompany> {
+    parse_records(xml, "COMPANY")
+        .into_iter()
+        .filter_map(|record| {
+            let name = record.get("NAME").or_else(|| record.get("@NAME"))?.clone();
+            let field = |key: &str| record.get(key).filter(|v| !v.is_empty()).cloned();
+
+            let company_number = field("COMPANYNUMBER");
+
+            // Tally keeps each company in a folder named after its number under the data path
+            let data_path = field("DATAPATH").map(|path| {
+                let trimmed = path.trim_end_matches(['\\', '/']);
+                match &company_number {
+                    Some(number) if trimmed.rsplit(['\\', '/']).next() != Some(number.as_str()) => {
+                        format!("{}\\{}", trimmed, number)
+                    }
+                    _ => path,
+                }
+            });
+
+            Some(TallyCompany {
+                name,
+                guid: field("GUID"),
+                company_number,
+                books_from: field("BOOKSFROM").and_then(|d| parse_tally_date(&d)),
+                last_voucher_date: field("LASTVOUCHERDATE").and_then(|d| parse_tally_date(&d)),
+                data_path,
+                stats: None,
+            })
+        })
+        .collect()
+}
+
+/// Counts the company's ledgers, stock items and vouchers. Only the counts
+/// and the last voucher date leave Tally, never the entries themselves.
+pub fn fetch_company_stats(endpoint: &str, company: &str, timeout: Duration) -> Result<TallyCompanyStats, TallyHttpError> {
+    let masters = collection_export_envelope(
+        MASTER_COUNT_COLLECTION,
+        "Company",
+        &["Name"],
+        &["LedgerCount : $$NumItems:Ledger", "StockItemCount : $$NumItems:StockItem"],
+        Some(company),
+    );
+    let response = post_xml(endpoint, &masters, Charset::Utf8, timeout)?;
+    if let Some(error) = line_error(&response) {
+        return Err(TallyHttpError::Malformed(format!("Tally rejected the master count export: {}", error)));
+    }
+    let mut stats = parse_master_counts(&response, company);
+
+    // Grouped inside Tally so the response has one row per voucher type
+    let vouchers = aggregate_export_envelope(
+        VOUCHER_SUMMARY_COLLECTION,
+        "Voucher",
+        &["VoucherTypeName : $VoucherTypeName"],
+        &["VoucherCount : SUM : 1", "LastDate : MAX : $Date"],
+        Some(company),
+    );
+    let response = post_xml(endpoint, &vouchers, Charset::Utf8, timeout)?;
+    if let Some(error) = line_error(&response) {
+        return Err(TallyHttpError::Malformed(format!("Tally rejected the voucher summary export: {}", error)));
+    }
+    add_voucher_summary(&mut stats, &response);
+
+    Ok(stats)
+}
+
+/// Reads the ledger and stock item counts of `company` from a master count
+/// response; every loaded company is exported, so the others are skipped
+pub fn parse_master_counts(xml: &str, company: &str) -> TallyCompanyStats {
+    let record = parse_records(xml, "COMPANY")
+        .into_iter()
+        .find(|record| record.get("NAME").or_else(|| record.get("@NAME")).map(String::as_str) == Some(company));
+
+    let count = |key: &str| record.as_ref().and_then(|r| r.get(key)).and_then(|v| parse_count(v));
+    TallyCompanyStats {
+        ledgers: count("LEDGERCOUNT"),
+        stock_items: count("STOCKITEMCOUNT"),
+        ..Default::default()
+    }
+}
+
+/// Adds the per-type voucher counts and the newest voucher date from a
+/// voucher summary response
+pub fn add_voucher_summary(stats: &mut TallyCompanyStats, xml: &str) {
+    let mut total = 0;
+    for record in parse_records(xml, "VOUCHER") {
+        let Some(count) = record.get("VOUCHERCOUNT").and_then(|v| parse_count(v)) else {
+            continue;
+        };
+        let voucher_type = record.get("VOUCHERTYPENAME").map(|t| t.trim()).filter(|t| !t.is_empty());
+        *stats.vouchers_by_type.entry(voucher_type.unwrap_or("Unknown").to_string()).or_insert(0) += count;
+        total += count;
+
+        // ISO dates compare correctly as text, and any date beats None
+        let date = record.get("LASTDATE").and_then(|d| parse_tally_date(d));
+        if date > stats.last_voucher_date {
+            stats.last_voucher_date = date;
+        }
+    }
+    stats.vouchers = Some(total);
+}
+
+/// Tally exports numbers with grouping commas and padding, e.g. " 1,204"
+fn parse_count(value: &str) -> Option<u64> {
+    value.trim().replace(',', "").parse().ok()
+}
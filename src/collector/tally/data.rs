@@ -0,0 +1,94 @@
+//! Offline inspection of Tally company data folders
+
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use crate::models::TallyDataFolder;
+use crate::utils::format_system_time;
+
+/// Data file extension used by Tally.ERP 9
+const ERP9_EXTENSION: &str = "900";
+
+/// Data file extension used by TallyPrime
+const PRIME_EXTENSION: &str = "1800";
+
+/// Lists the company folders in a Tally data directory. Company folders are
+/// named by company number and hold a `Company.900` (ERP 9) or
+/// `Company.1800` (TallyPrime) file. A path that is itself a company folder
+/// is reported on its own.
+pub fn scan_data_directory(data_dir: &Path) -> Vec<TallyDataFolder> {
+    if let Some(folder) = inspect_company_folder(data_dir) {
+        return vec![folder];
+    }
+
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+
+    let mut folders: Vec<TallyDataFolder> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().map(|t| t.is_dir()).unwrap_or(false))
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit())
+        })
+        .filter_map(|entry| inspect_company_folder(&entry.path()))
+        .collect();
+
+    folders.sort_by(|a, b| a.company_number.cmp(&b.company_number));
+    folders
+}
+
+/// Summarises one company folder, or returns None when it holds no Company file
+fn inspect_company_folder(path: &Path) -> Option<TallyDataFolder> {
+    let mut company_file = None;
+    let mut file_count = 0u64;
+    let mut total_size_bytes = 0u64;
+    let mut newest: Option<SystemTime> = None;
+
+    for entry in fs::read_dir(path).ok()?.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some((stem, extension)) = name.rsplit_once('.') else {
+            continue;
+        };
+        if extension != ERP9_EXTENSION && extension != PRIME_EXTENSION {
+            continue;
+        }
+
+        file_count += 1;
+        total_size_bytes += metadata.len();
+        if let Ok(modified) = metadata.modified() {
+            newest = Some(newest.map_or(modified, |n| n.max(modified)));
+        }
+
+        if stem.eq_ignore_ascii_case("company") {
+            // When a migrated folder still has both, the Prime file is the live one
+            let is_prime = extension == PRIME_EXTENSION;
+            match company_file {
+                Some((true, _)) if !is_prime => {}
+                _ => company_file = Some((is_prime, metadata.len())),
+            }
+        }
+    }
+
+    let (is_prime, company_file_size_bytes) = company_file?;
+
+    Some(TallyDataFolder {
+        company_number: path.file_name()?.to_string_lossy().into_owned(),
+        path: path.to_string_lossy().into_owned(),
+        data_format: if is_prime { "TallyPrime" } else { "TallyERP9" }.to_string(),
+        company_file_size_bytes,
+        file_count,
+        total_size_bytes,
+        last_modified: newest.map(format_system_time),
+    })
+}
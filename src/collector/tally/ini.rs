@@ -0,0 +1,159 @@
+//! Parser for Tally's `tally.ini` configuration file
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::TallyConfig;
+
+/// Reads `tally.ini` from an install directory, matching the file name case-insensitively
+pub fn read_tally_ini(install_path: &Path) -> Option<TallyConfig> {
+    let ini_path = find_ini(install_path)?;
+    let bytes = fs::read(&ini_path).ok()?;
+
+    let mut config = parse_tally_ini(&decode(&bytes));
+    config.ini_path = Some(ini_path.to_string_lossy().into_owned());
+
+    // Relative data, backup and TDL paths are resolved against the install directory
+    for dir in [&mut config.data_directory, &mut config.backup_directory].into_iter().flatten() {
+        if is_relative(dir) {
+            *dir = join(install_path, dir);
+        }
+    }
+    for tdl in &mut config.tdl_files {
+        if is_relative(tdl) {
+            *tdl = join(install_path, tdl);
+        }
+    }
+
+    Some(config)
+}
+
+/// Parses the contents of a `tally.ini` file.
+///
+/// The file is a flat list of `Key=Value` lines (optionally under a `[Tally]`
+/// section). Keys are case-insensitive and may contain spaces; `Load` and
+/// `TDL` may repeat.
+pub fn parse_tally_ini(text: &str) -> TallyConfig {
+    let mut config = TallyConfig::default();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('[') {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').trim();
+        let key: String = key.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_ascii_lowercase();
+
+        match key.as_str() {
+            "data" => config.data_directory = non_empty(value),
+            "backup" | "backuppath" | "backupdirectory" => config.backup_directory = non_empty(value),
+            "port" | "serverport" => config.port = value.parse().ok(),
+            "odbcserver" => config.odbc_server = parse_bool(value),
+            "clientserver" => config.client_server = non_empty(value),
+            "defaultcompanies" => config.default_companies = parse_bool(value),
+            "load" => {
+                if let Some(company) = non_empty(value) {
+                    config.load_companies.push(company);
+                }
+            }
+            "usertdl" => config.user_tdl = parse_bool(value),
+            "tdl" => {
+                if let Some(tdl) = non_empty(value) {
+                    config.tdl_files.push(tdl);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    config
+}
+
+/// Returns true when the configuration explicitly turns the XML/ODBC server off
+#[cfg(any(feature = "tally-xml", feature = "tally-odbc"))]
+pub fn server_disabled(config: &TallyConfig) -> bool {
+    config.odbc_server == Some(false)
+        || config.client_server.as_deref().is_some_and(|mode| mode.eq_ignore_ascii_case("none") || mode.eq_ignore_ascii_case("client"))
+}
+
+fn find_ini(install_path: &Path) -> Option<PathBuf> {
+    fs::read_dir(install_path)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.eq_ignore_ascii_case("tally.ini"))
+        })
+}
+
+/// tally.ini is normally ANSI (Windows-1252), but editors sometimes save it
+/// as UTF-8 or UTF-16
+pub fn decode(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFF, 0xFE, rest @ ..] => {
+            let units: Vec<u16> = rest.chunks_exact(2).map(|p| u16::from_le_bytes([p[0], p[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest.chunks_exact(2).map(|p| u16::from_be_bytes([p[0], p[1]])).collect();
+            String::from_utf16_lossy(&units)
+        }
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        _ => match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(_) => bytes.iter().map(|&b| windows_1252(b)).collect(),
+        },
+    }
+}
+
+/// Windows-1252 matches Latin-1 except for 0x80-0x9F; the five bytes it
+/// leaves undefined keep their C1 control code points
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+        '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+/// Tally writes Yes/No; hand-edited files use the other spellings too
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "yes" | "true" | "1" => Some(true),
+        "no" | "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// Windows-style relative check that also works when running on Linux
+fn is_relative(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    let drive_letter = bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':';
+    !(drive_letter || path.starts_with('\\') || path.starts_with('/'))
+}
+
+fn join(base: &Path, relative: &str) -> String {
+    let base = base.to_string_lossy();
+    let separator = if base.contains('\\') { '\\' } else { '/' };
+    let relative = relative.strip_prefix(".\\").or_else(|| relative.strip_prefix("./")).unwrap_or(relative);
+    format!("{}{}{}", base.trim_end_matches(['\\', '/']), separator, relative)
+}
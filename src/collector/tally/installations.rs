@@ -0,0 +1,557 @@
+//! Merging of Tally installations seen by several detection methods
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+#[cfg(feature = "tally-xml")]
+use std::time::Instant;
+
+use super::{backup, data, ini, license, tdl, GatewayOptions};
+#[cfg(any(feature = "tally-xml", feature = "tally-odbc"))]
+use super::DEFAULT_TALLY_ENDPOINT;
+use crate::collector::apps::engine;
+use crate::collector::apps::rules::tally_rule;
+use crate::collector::{evidence, pe};
+use crate::collector::process::ProcessInfo;
+use crate::collector::registry::RegistrySource;
+use crate::diagnostics::Diagnostics;
+use crate::models::{TallyInfo, TallyProcess, TallyTdl};
+#[cfg(any(feature = "tally-xml", feature = "tally-odbc"))]
+use crate::models::TallyConfig;
+
+/// Public data directory TallyPrime uses when tally.ini does not name one
+const PRIME_PUBLIC_DATA: &str = "C:\\Users\\Public\\TallyPrime\\data";
+
+/// Merges the installations a registry lists under the uninstall and vendor
+/// keys of the built-in Tally rule. `map_path` turns an install location
+/// into the path to report.
+pub fn detect_in_registry(installs: &mut Vec<TallyInfo>, registry: &dyn RegistrySource, map_path: &dyn Fn(&str) -> String) {
+    for hit in engine::registry_matches(tally_rule(), registry) {
+        let path = hit.install_path.map_or_else(unknown, |p| map_path(&p));
+        let version = hit.version.unwrap_or_else(unknown);
+        merge_installation(installs, variant_from_name(&hit.label), version, path, hit.source, &hit.detail);
+    }
+}
+
+/// Merges the Tally.exe folders the built-in Tally rule finds on the drive
+/// C: at `drive_root`. Install paths are reported as Windows sees them, or
+/// with `host_paths` as the folder on this host.
+pub fn detect_on_drive(installs: &mut Vec<TallyInfo>, drive_root: &Path, host_paths: bool) {
+    for hit in engine::file_matches(tally_rule(), Some(drive_root), None) {
+        let (Some(windows_path), Some(dir)) = (hit.install_path, hit.host_path) else {
+            continue;
+        };
+        // Only the Windows path names the product; a Wine prefix may be called anything
+        let variant = variant_from_name(&windows_path);
+        let path = if host_paths { dir.to_string_lossy().into_owned() } else { windows_path };
+        merge_installation(installs, variant, hit.version.unwrap_or_else(unknown), path, hit.source, &hit.detail);
+    }
+}
+
+/// Records that `source` saw a `variant` installation (`detail` says where),
+/// merging it into an existing entry with the same install path. A hit
+/// without a path merges into the first entry of the same variant.
+pub fn merge_installation(
+    installs: &mut Vec<TallyInfo>,
+    variant: String,
+    version: String,
+    path: String,
+    source: &str,
+    detail: &str,
+) {
+    let version = known(version);
+    let path = known(path);
+
+    let existing = match &path {
+        Some(path) => {
+            let key = path_key(path);
+            installs
+                .iter()
+                .position(|i| i.install_path.as_deref().map(path_key).as_ref() == Some(&key))
+                .or_else(|| installs.iter().position(|i| i.install_path.is_none() && i.variant.as_ref() == Some(&variant)))
+        }
+        None => installs.iter().position(|i| i.variant.as_ref() == Some(&variant)),
+    };
+
+    let index = match existing {
+        Some(index) => index,
+        None => {
+            installs.push(TallyInfo {
+                variant: Some(variant),
+                install_path: path.clone(),
+                ..Default::default()
+            });
+            installs.len() - 1
+        }
+    };
+
+    let install = &mut installs[index];
+    if install.version.is_none() {
+        install.version = version;
+    }
+    if install.install_path.is_none() {
+        install.install_path = path;
+    }
+    add_source(install, source, detail);
+}
+
+/// Maps a path as Tally sees it (e.g. `C:\Program Files\TallyPrime`) to
+/// where it can be read on this host
+pub type HostPath<'a> = &'a dyn Fn(&TallyInfo, &str) -> PathBuf;
+
+/// Attaches running Tally processes to the installation whose directory they
+/// were started from. A process started from anywhere else is reported as an
+/// installation of its own.
+pub fn attach_processes(installs: &mut Vec<TallyInfo>, processes: Vec<ProcessInfo>, host_path: HostPath) {
+    for process in processes {
+        let Some(dir) = process.executable_dir() else {
+            // Without a path only a lone installation can claim the process
+            if installs.len() == 1 {
+                add_process(&mut installs[0], process);
+            }
+            continue;
+        };
+
+        // Host paths already tell Wine prefixes apart
+        let in_prefix = TallyInfo {
+            wine_prefix: process.wine_prefix.clone(),
+            ..Default::default()
+        };
+        let host_dir = host_path_key(&host_path(&in_prefix, dir));
+        let existing = installs.iter().position(|install| {
+            install
+                .install_path
+                .as_deref()
+                .is_some_and(|path| host_path_key(&host_path(install, path)) == host_dir)
+        });
+
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                let path = host_path(&in_prefix, dir).to_string_lossy().into_owned();
+                let variant = variant_from_name(process.executable.as_deref().unwrap_or(dir));
+                merge_installation(installs, variant, unknown(), path.clone(), "process", &process_detail(&process));
+                let key = path_key(&path);
+                let Some(index) = installs.iter().position(|i| i.install_path.as_deref().map(path_key).as_ref() == Some(&key)) else {
+                    continue;
+                };
+                if installs[index].wine_prefix.is_none() {
+                    installs[index].wine_prefix = process.wine_prefix.clone();
+                }
+                index
+            }
+        };
+        add_process(&mut installs[index], process);
+    }
+}
+
+fn add_process(install: &mut TallyInfo, process: ProcessInfo) {
+    add_source(install, "process", &process_detail(&process));
+    install.running.get_or_insert_with(Vec::new).push(TallyProcess {
+        pid: process.pid,
+        start_time: process.start_time,
+        memory_bytes: process.memory_bytes,
+        command_line: process.command_line,
+        listening_ports: process.listening_ports,
+    });
+}
+
+/// "pid 4242: C:\Program Files\TallyPrime\tally.exe"
+fn process_detail(process: &ProcessInfo) -> String {
+    format!("pid {}: {}", process.pid, process.executable.as_deref().unwrap_or("unknown executable"))
+}
+
+/// Fills in tally.ini settings, data folders and, when a gateway answers,
+/// live details for every installation. With discovery, gateways are
+/// searched for instead of only probing each installation's configured port.
+/// With ODBC support, the ODBC server is asked for its companies too.
+/// Gateways that fail to answer are recorded in `diagnostics`.
+// Only the XML probe adds installations, for gateways none accounts for
+#[cfg_attr(not(feature = "tally-xml"), allow(clippy::ptr_arg))]
+pub fn enrich_installations(
+    installs: &mut Vec<TallyInfo>,
+    host_path: HostPath,
+    gateway: &GatewayOptions,
+    timeout: Duration,
+    diagnostics: &Diagnostics,
+) {
+    inspect_installations(installs, host_path);
+
+    #[cfg(feature = "tally-xml")]
+    probe_gateways(installs, host_path, gateway, timeout, diagnostics);
+    // Runs after the XML probe so it can use the port that answered
+    #[cfg(feature = "tally-odbc")]
+    super::odbc::probe_odbc(installs, gateway, timeout);
+    #[cfg(not(any(feature = "tally-xml", feature = "tally-odbc")))]
+    let _ = (gateway, timeout);
+    #[cfg(not(feature = "tally-xml"))]
+    let _ = diagnostics;
+
+    evidence::score_installations(installs);
+}
+
+/// Fills in what can be read from disk: the executable version when the
+/// registry had none, tally.ini settings, the license file, company data
+/// folders and their backups
+pub fn inspect_installations(installs: &mut [TallyInfo], host_path: HostPath) {
+    for install in installs.iter_mut() {
+        if let Some(install_dir) = install.install_path.as_deref().map(|p| host_path(install, p)) {
+            if let Some(exe) = find_tally_exe(&install_dir) {
+                // Already evidence when a file_version probe read the same file
+                let exe_path = exe.to_string_lossy().into_owned();
+                if !install.evidence.iter().any(|e| e.detail == exe_path) {
+                    evidence::add_evidence(&mut install.evidence, "executable", &exe_path);
+                }
+
+                // Registry entries do not always carry a version; the executable does
+                if install.version.is_none() {
+                    if let Some(version) = pe::display_version(&exe) {
+                        install.version = Some(version);
+                        add_source(install, "file_version", &exe_path);
+                    }
+                }
+            }
+
+            // Read tally.ini next to the executable
+            install.config = ini::read_tally_ini(&install_dir);
+            if install.config.is_some() {
+                evidence::add_evidence(&mut install.evidence, "tally_ini", &install_dir.join("tally.ini").to_string_lossy());
+            }
+            install.tdls = configured_tdls(install, host_path);
+
+            // A license file is the fallback when the gateway cannot be asked
+            if install.license.is_none() {
+                install.license = license::read_license_file(&install_dir);
+            }
+        }
+
+        // Inspect company data on disk; this works even when Tally is not running
+        let data_folders: Vec<_> = data_directories(install, host_path)
+            .iter()
+            .flat_map(|dir| data::scan_data_directory(dir))
+            .collect();
+        if !data_folders.is_empty() {
+            install.data_folders = Some(data_folders);
+        }
+
+        // Backups are looked for per company, so only once the companies are known
+        install.backups = backup::find_backups(install, host_path);
+    }
+}
+
+/// TDL files tally.ini loads at startup, with the files' details
+fn configured_tdls(install: &TallyInfo, host_path: HostPath) -> Option<Vec<TallyTdl>> {
+    let mut tdls = Vec::new();
+    for path in install.config.as_ref().map(tdl::configured_tdls).unwrap_or_default() {
+        tdl::add_tdl(&mut tdls, path, &host_path(install, path), "tally_ini");
+    }
+    Some(tdls).filter(|tdls| !tdls.is_empty())
+}
+
+/// Endpoint to probe for an installation: an explicit endpoint wins; otherwise
+/// the port from tally.ini, unless the configuration turns the server off
+#[cfg(any(feature = "tally-xml", feature = "tally-odbc"))]
+pub fn gateway_endpoint(install: &TallyInfo, explicit: Option<&str>) -> Option<String> {
+    if let Some(endpoint) = explicit {
+        return Some(endpoint.to_string());
+    }
+
+    match &install.config {
+        Some(config) if ini::server_disabled(config) => None,
+        Some(TallyConfig { port: Some(port), .. }) => Some(format!("127.0.0.1:{}", port)),
+        _ => Some(DEFAULT_TALLY_ENDPOINT.to_string()),
+    }
+}
+
+/// Endpoints to probe, each with the installation that suggested it.
+/// Discovery tries every port from tally.ini and every port a Tally process
+/// listens on, then the candidate range; otherwise each installation's
+/// configured gateway is the only candidate.
+#[cfg(feature = "tally-xml")]
+fn candidate_endpoints(installs: &[TallyInfo], gateway: &GatewayOptions) -> Vec<(String, Option<usize>)> {
+    let mut candidates: Vec<(String, Option<usize>)> = Vec::new();
+    let mut add = |endpoint: String, suggested_by: Option<usize>| {
+        if !candidates.iter().any(|(e, _)| *e == endpoint) {
+            candidates.push((endpoint, suggested_by));
+        }
+    };
+
+    match &gateway.discovery {
+        None => {
+            for (index, install) in installs.iter().enumerate() {
+                if let Some(endpoint) = gateway_endpoint(install, gateway.endpoint.as_deref()) {
+                    add(endpoint, Some(index));
+                }
+            }
+        }
+        Some(ports) => {
+            for (index, install) in installs.iter().enumerate() {
+                // Tried even when tally.ini turns the server off; the setting may be stale
+                let configured = install.config.as_ref().and_then(|c| c.port);
+                for port in configured.into_iter().chain(listening_ports(install)) {
+                    add(format!("127.0.0.1:{}", port), Some(index));
+                }
+            }
+            for port in ports.clone() {
+                add(format!("127.0.0.1:{}", port), None);
+            }
+        }
+    }
+
+    candidates
+}
+
+/// Probes each candidate gateway once and credits the answer to the
+/// installation whose process listens on the port, else to the installation
+/// that suggested the endpoint, else to one of the variants it reports. All
+/// candidates share one timeout. Discovery reports a gateway no installation
+/// accounts for as an installation of its own. Only a gateway an installation
+/// or the command line points at counts as failed when it does not answer;
+/// most ports of the discovery range are expected to be closed.
+#[cfg(feature = "tally-xml")]
+fn probe_gateways(
+    installs: &mut Vec<TallyInfo>,
+    host_path: HostPath,
+    gateway: &GatewayOptions,
+    timeout: Duration,
+    diagnostics: &Diagnostics,
+) {
+    use super::{check_tally_http, companies};
+
+    let deadline = Instant::now() + timeout;
+    let time_left = || Some(deadline.saturating_duration_since(Instant::now())).filter(|left| !left.is_zero());
+
+    for (endpoint, suggested_by) in candidate_endpoints(installs, gateway) {
+        let Some(probe_timeout) = time_left() else {
+            break;
+        };
+
+        let http_info = match check_tally_http(&endpoint, probe_timeout) {
+            Ok(http_info) => http_info,
+            Err(e) => {
+                if suggested_by.is_some() {
+                    diagnostics.record_error("tally", format!("gateway {}: {}", endpoint, e));
+                }
+                continue;
+            }
+        };
+        let detail = gateway_detail(&endpoint, &http_info);
+
+        // Installations sharing a port cannot both be listening, so the listener
+        // owns it, then the installation that pointed at it. Only a gateway
+        // from the candidate range is matched by the variant it reports.
+        let port = endpoint.rsplit(':').next().and_then(|port| port.parse::<u16>().ok());
+        let owner = port
+            .and_then(|port| installs.iter().position(|i| listening_ports(i).any(|p| p == port)))
+            .or(suggested_by)
+            .or_else(|| {
+                let variant = http_info.variant.as_ref()?;
+                installs.iter().position(|i| i.variant.as_ref() == Some(variant))
+            });
+        let owner = match owner {
+            Some(owner) => owner,
+            None => {
+                installs.push(TallyInfo {
+                    variant: http_info.variant.clone(),
+                    ..Default::default()
+                });
+                installs.len() - 1
+            }
+        };
+        let install = &mut installs[owner];
+
+        // Update with potentially more accurate information from HTTP
+        if let Some(version) = http_info.version {
+            install.version = Some(version);
+        }
+        if let Some(edition) = http_info.edition {
+            install.edition = Some(edition);
+        }
+        install.gateway = Some(endpoint.clone());
+        add_source(install, "http", &detail);
+
+        // The gateway is up, so failures from here on are worth reporting
+        let failed = |what: &str, e: &dyn std::fmt::Display| {
+            diagnostics.record_error("tally", format!("{} from {}: {}", what, endpoint, e));
+        };
+
+        // The follow-up requests share what is left of the timeout and stop once it runs out
+        let Some(left) = time_left() else {
+            continue;
+        };
+        // Ask which companies are loaded
+        match companies::fetch_companies(&endpoint, left) {
+            Ok(mut companies) => {
+                if gateway.company_stats {
+                    for company in companies.iter_mut() {
+                        let Some(left) = time_left() else {
+                            break;
+                        };
+                        company.stats = companies::fetch_company_stats(&endpoint, &company.name, left)
+                            .map_err(|e| failed(&format!("statistics of {}", company.name), &e))
+                            .ok();
+                    }
+                }
+                install.companies = Some(companies);
+            }
+            Err(e) => failed("companies", &e),
+        }
+        // The running instance knows its license better than any file
+        let Some(left) = time_left() else {
+            continue;
+        };
+        match license::fetch_license(&endpoint, left) {
+            Ok(license) => install.license = Some(license),
+            Err(e) => failed("license", &e),
+        }
+        // ...and which customisations it actually loaded
+        let Some(left) = time_left() else {
+            continue;
+        };
+        let loaded = tdl::fetch_loaded_tdls(&endpoint, left).map_err(|e| failed("loaded TDLs", &e));
+        if let Ok(paths) = loaded {
+            let found: Vec<(String, PathBuf)> = paths
+                .into_iter()
+                .map(|path| {
+                    let host = host_path(install, &path);
+                    (path, host)
+                })
+                .collect();
+            if !found.is_empty() {
+                let tdls = install.tdls.get_or_insert_with(Vec::new);
+                for (path, host) in found {
+                    tdl::add_tdl(tdls, &path, &host, "http");
+                }
+            }
+        }
+    }
+}
+
+/// "127.0.0.1:9000 answered: TallyPrime 4.1.1 Gold"
+#[cfg(feature = "tally-xml")]
+fn gateway_detail(endpoint: &str, http_info: &super::TallyHttpInfo) -> String {
+    let product: Vec<&str> = [&http_info.variant, &http_info.version, &http_info.edition]
+        .iter()
+        .filter_map(|part| part.as_deref())
+        .collect();
+    if product.is_empty() {
+        format!("{} answered as a Tally gateway", endpoint)
+    } else {
+        format!("{} answered: {}", endpoint, product.join(" "))
+    }
+}
+
+/// Ports the installation's running processes listen on
+#[cfg(feature = "tally-xml")]
+fn listening_ports(install: &TallyInfo) -> impl Iterator<Item = u16> + '_ {
+    install.running.iter().flatten().flat_map(|process| process.listening_ports.iter().copied())
+}
+
+/// Data directories to scan: the one from tally.ini, else the product defaults
+fn data_directories(install: &TallyInfo, host_path: HostPath) -> Vec<PathBuf> {
+    if let Some(data) = install.config.as_ref().and_then(|c| c.data_directory.as_ref()) {
+        return vec![host_path(install, data)];
+    }
+
+    let mut dirs = Vec::new();
+    if let Some(install_path) = &install.install_path {
+        dirs.push(host_path(install, install_path).join("Data"));
+    }
+    if install.variant.as_deref() == Some("TallyPrime") {
+        dirs.push(host_path(install, PRIME_PUBLIC_DATA));
+    }
+    dirs.into_iter().filter(|d| d.is_dir()).collect()
+}
+
+/// Maps a product, display or directory name to "TallyPrime", "TallyERP9" or "Tally"
+pub fn variant_from_name(name: &str) -> String {
+    if name.contains("Prime") {
+        "TallyPrime".to_string()
+    } else if name.contains("ERP") {
+        "TallyERP9".to_string()
+    } else {
+        "Tally".to_string()
+    }
+}
+
+/// Finds Tally.exe in an install directory, matching the name case-insensitively
+pub fn find_tally_exe(install_path: &Path) -> Option<PathBuf> {
+    matching_children(install_path, "tally.exe")
+        .into_iter()
+        .find(|path| path.is_file() && path.file_name().is_some_and(|n| n.len() == "tally.exe".len()))
+}
+
+/// Resolves a Windows path below `drive_root` one component at a time,
+/// matching names case-insensitively like Windows does
+pub fn resolve_case_insensitive(drive_root: &Path, relative: &str) -> PathBuf {
+    let mut host = drive_root.to_path_buf();
+    for component in relative.split(['\\', '/']).filter(|c| !c.is_empty()) {
+        let exact = host.join(component);
+        host = if exact.exists() {
+            exact
+        } else {
+            matching_children(&host, &component.to_ascii_lowercase())
+                .into_iter()
+                .find(|p| p.file_name().is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(component)))
+                .unwrap_or(exact)
+        };
+    }
+    host
+}
+
+pub fn subdirectories(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    dirs.sort();
+    dirs
+}
+
+/// Children of `dir` whose name starts with `prefix` (lowercase), compared case-insensitively
+fn matching_children(dir: &Path, prefix: &str) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut matches: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().to_ascii_lowercase().starts_with(prefix))
+        .map(|e| e.path())
+        .collect();
+    matches.sort();
+    matches
+}
+
+/// Records that a detection method saw the installation, with `detail` as its evidence
+pub fn add_source(install: &mut TallyInfo, source: &str, detail: &str) {
+    if !install.detection_sources.iter().any(|s| s == source) {
+        install.detection_sources.push(source.to_string());
+    }
+    evidence::add_evidence(&mut install.evidence, source, detail);
+}
+
+/// Registry probes report missing values as "Unknown"
+fn known(value: String) -> Option<String> {
+    if value.is_empty() || value == "Unknown" {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Placeholder for a value the registry does not have
+fn unknown() -> String {
+    "Unknown".to_string()
+}
+
+/// Install paths compare case-insensitively and ignore separators style and trailing slashes
+fn path_key(path: &str) -> String {
+    path.replace('/', "\\").trim_end_matches('\\').to_ascii_lowercase()
+}
+
+/// Key of a host path after resolving symlinks, so a prefix reached through a
+/// link still matches
+fn host_path_key(path: &Path) -> String {
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    path_key(&path.to_string_lossy())
+}
@@ -0,0 +1,186 @@
+//! License details: serial number, license type, TSS expiry and activation
+
+use std::fs;
+use std::path::{Path, PathBuf};
+#[cfg(feature = "tally-xml")]
+use std::time::Duration;
+
+use super::ini;
+#[cfg(feature = "tally-xml")]
+use super::xml_api::{function_export_envelope, function_result, line_error};
+#[cfg(feature = "tally-xml")]
+use super::xml_client::{Charset, TallyHttpError, post_xml};
+use crate::models::{TallyInfo, TallyLicense};
+use crate::utils::{mask_identifier, parse_tally_date};
+
+/// TDL function that answers questions about the running license
+#[cfg(feature = "tally-xml")]
+const LICENSE_FUNCTION: &str = "$$LicenseInfo";
+
+/// Asks the running Tally instance about its license. Fails when the serial
+/// number cannot be read; the remaining details are best-effort.
+#[cfg(feature = "tally-xml")]
+pub fn fetch_license(endpoint: &str, timeout: Duration) -> Result<TallyLicense, TallyHttpError> {
+    let ask = |param: &str| -> Result<Option<String>, TallyHttpError> {
+        let envelope = function_export_envelope(LICENSE_FUNCTION, &[param]);
+        let response = post_xml(endpoint, &envelope, Charset::Utf8, timeout)?;
+
+        if let Some(error) = line_error(&response) {
+            return Err(TallyHttpError::Malformed(format!("Tally rejected the license query: {}", error)));
+        }
+        let result = function_result(&response)
+            .ok_or_else(|| TallyHttpError::Malformed("response carries no function result".to_string()))?;
+        Ok(Some(result).filter(|v| !v.is_empty()))
+    };
+    let flag = |param: &str| ask(param).ok().flatten().and_then(|v| ini::parse_bool(&v));
+
+    let serial_number = ask("SerialNumber")?;
+    let educational = flag("IsEducationalMode");
+    let license_type = if educational == Some(true) {
+        Some("Educational".to_string())
+    } else if flag("IsGold") == Some(true) {
+        Some("Gold".to_string())
+    } else if flag("IsSilver") == Some(true) {
+        Some("Silver".to_string())
+    } else {
+        None
+    };
+
+    Ok(TallyLicense {
+        serial_number,
+        multi_user: multi_user(license_type.as_deref()),
+        license_type,
+        tss_expiry: ask("TSSExpiryDate").ok().flatten().and_then(|d| parse_date(&d)),
+        activated: flag("IsLicensedMode").or(educational.map(|e| !e)),
+        source: "http".to_string(),
+        license_file: None,
+    })
+}
+
+/// Reads the first `*.lic` file in an install directory. Files written as
+/// `Key=Value` text are parsed; an encrypted file only shows that the
+/// installation was activated.
+pub fn read_license_file(install_dir: &Path) -> Option<TallyLicense> {
+    let path = find_license_file(install_dir)?;
+    let text = ini::decode(&fs::read(&path).ok()?);
+
+    // ANSI decoding accepts any byte, so binary content shows as control characters
+    let mut license = if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        TallyLicense {
+            activated: Some(true),
+            ..Default::default()
+        }
+    } else {
+        parse_license_text(&text)
+    };
+    license.source = "license_file".to_string();
+    license.license_file = Some(path.to_string_lossy().into_owned());
+    Some(license)
+}
+
+/// Parses `Key=Value` (or `Key: Value`) lines of a text license file.
+/// Keys are case-insensitive and ignore spaces, and "Licence" spells "License".
+pub fn parse_license_text(text: &str) -> TallyLicense {
+    let mut license = TallyLicense::default();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with(';') || line.starts_with('#') || line.starts_with('[') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=').or_else(|| line.split_once(':')) else {
+            continue;
+        };
+        let value = value.trim().trim_matches('"').trim();
+        if value.is_empty() {
+            continue;
+        }
+        let key: String = key
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_ascii_lowercase()
+            .replace("licence", "license");
+
+        match key.as_str() {
+            "serialnumber" | "serialno" | "serial" | "licenseserial" => license.serial_number = Some(value.to_string()),
+            "licensetype" | "edition" | "type" => license.license_type = Some(license_type(value)),
+            "tssexpiry" | "tssexpirydate" | "tssvalidtill" | "tssvalidupto" | "expirydate" | "validtill" => {
+                license.tss_expiry = parse_date(value);
+            }
+            "status" | "licensestatus" | "activated" | "activation" => license.activated = activation(value),
+            "users" | "usercount" | "numberofusers" => license.multi_user = value.parse::<u32>().ok().map(|n| n > 1),
+            _ => {}
+        }
+    }
+
+    if license.multi_user.is_none() {
+        license.multi_user = multi_user(license.license_type.as_deref());
+    }
+    license
+}
+
+/// Shortens every serial number like `os.product_id`
+pub fn mask_serials(installs: &mut [TallyInfo]) {
+    for license in installs.iter_mut().filter_map(|i| i.license.as_mut()) {
+        license.serial_number = license.serial_number.take().map(mask_identifier);
+    }
+}
+
+/// Silver licenses are single-user, Gold licenses multi-user
+fn multi_user(license_type: Option<&str>) -> Option<bool> {
+    match license_type? {
+        "Gold" => Some(true),
+        "Silver" => Some(false),
+        _ => None,
+    }
+}
+
+fn license_type(value: &str) -> String {
+    let lower = value.to_ascii_lowercase();
+    if lower.contains("gold") || lower.contains("multi") {
+        "Gold".to_string()
+    } else if lower.contains("silver") || lower.contains("single") {
+        "Silver".to_string()
+    } else if lower.contains("edu") {
+        "Educational".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn activation(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "active" | "activated" | "licensed" => Some(true),
+        "inactive" | "not activated" | "deactivated" | "unlicensed" | "educational" => Some(false),
+        other => ini::parse_bool(other),
+    }
+}
+
+/// Tally dates, or dates already in ISO 8601 form
+fn parse_date(value: &str) -> Option<String> {
+    let value = value.trim();
+    let iso = value.len() == 10
+        && value.bytes().enumerate().all(|(i, b)| if i == 4 || i == 7 { b == b'-' } else { b.is_ascii_digit() });
+    if iso {
+        Some(value.to_string())
+    } else {
+        parse_tally_date(value)
+    }
+}
+
+fn find_license_file(install_dir: &Path) -> Option<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(install_dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("lic"))
+        })
+        .collect();
+    files.sort();
+    files.into_iter().next()
+}
@@ -0,0 +1,139 @@
+#[cfg(feature = "tally-xml")]
+pub mod xml_client;
+#[cfg(feature = "tally-xml")]
+pub mod xml_api;
+#[cfg(feature = "tally-xml")]
+pub mod companies;
+pub mod query;
+pub mod backup;
+pub mod ini;
+pub mod data;
+pub mod installations;
+pub mod license;
+pub mod offline;
+pub mod tdl;
+#[cfg(feature = "tally-odbc")]
+pub mod odbc;
+#[cfg(not(windows))]
+pub mod wine;
+
+#[cfg(windows)]
+use crate::models::TallyInfo;
+#[cfg(any(windows, feature = "tally-xml"))]
+use std::time::Duration;
+#[cfg(windows)]
+use std::path::Path;
+use std::ops::RangeInclusive;
+#[cfg(windows)]
+use crate::collector::registry::LiveRegistry;
+#[cfg(windows)]
+use crate::diagnostics::Diagnostics;
+
+#[cfg(feature = "tally-xml")]
+use xml_client::{Charset, TallyHttpError, post_xml};
+
+/// Default Tally XML/ODBC gateway port
+pub const DEFAULT_TALLY_ENDPOINT: &str = "127.0.0.1:9000";
+
+/// Ports tried by gateway discovery when no range is given
+pub const DEFAULT_DISCOVERY_PORTS: &str = "9000-9010";
+
+/// How the XML gateway is found and what it is asked
+#[derive(Debug, Clone, Default)]
+pub struct GatewayOptions {
+    /// Probe only this endpoint; otherwise the port from tally.ini, else the default
+    pub endpoint: Option<String>,
+    /// Also try these ports and the ports Tally processes listen on (`--tally-discover`)
+    pub discovery: Option<RangeInclusive<u16>>,
+    /// Ask each loaded company for ledger and voucher statistics (`--tally-stats`)
+    pub company_stats: bool,
+    /// Connect to the ODBC server through this DSN only (`--tally-odbc-dsn`)
+    pub odbc_dsn: Option<String>,
+    /// Connect through this driver on the gateway port (`--tally-odbc-driver`)
+    pub odbc_driver: Option<String>,
+}
+
+/// Product details reported by the Tally XML gateway
+#[cfg(feature = "tally-xml")]
+#[derive(Debug, Default)]
+pub struct TallyHttpInfo {
+    pub variant: Option<String>,
+    pub version: Option<String>,
+    pub edition: Option<String>,
+}
+
+#[cfg(windows)]
+pub fn collect_tally_info(timeout: Duration, gateway: &GatewayOptions, diagnostics: &Diagnostics) -> Vec<TallyInfo> {
+    let mut installs = Vec::new();
+    
+    // Every method reports all the installations it sees; the same
+    // installation found twice is merged by install path
+    installations::detect_in_registry(&mut installs, &LiveRegistry::new(), &|path| path.to_string());
+    installations::detect_on_drive(&mut installs, Path::new("C:\\"), false);
+    
+    let as_is = |_: &TallyInfo, path: &str| std::path::PathBuf::from(path);
+    installations::attach_processes(&mut installs, crate::collector::process::tally_processes(), &as_is);
+    installations::enrich_installations(&mut installs, &as_is, gateway, timeout, diagnostics);
+    
+    installs
+}
+
+// Optional: Check Tally HTTP endpoint
+#[cfg(feature = "tally-xml")]
+pub fn check_tally_http(endpoint: &str, timeout: Duration) -> Result<TallyHttpInfo, TallyHttpError> {
+    use quick_xml::de::from_str;
+    use serde::Deserialize;
+    
+    #[derive(Debug, Deserialize)]
+    struct TallyResponse {
+        #[serde(rename = "PRODUCT")]
+        product: Option<String>,
+        
+        #[serde(rename = "VERSION")]
+        version: Option<String>,
+        
+        #[serde(rename = "EDITION")]
+        edition: Option<String>,
+    }
+    
+    // Set a shorter timeout for the HTTP request
+    let http_timeout = std::cmp::min(timeout, Duration::from_millis(200));
+    
+    // Simple XML request to get Tally product info
+    let request = "<ENVELOPE><HEADER><VERSION>1</VERSION><TALLYREQUEST>PRODUCT</TALLYREQUEST></HEADER></ENVELOPE>";
+    
+    let response = post_xml(endpoint, request, Charset::Utf8, http_timeout)?;
+    
+    // Parse XML response
+    let classify = |p: String| {
+        if p.contains("Prime") {
+            "TallyPrime".to_string()
+        } else if p.contains("ERP") || p.contains("ERP9") {
+            "TallyERP9".to_string()
+        } else {
+            p
+        }
+    };
+    
+    if let Ok(tally_response) = from_str::<TallyResponse>(&response) {
+        if tally_response.product.is_some() || tally_response.version.is_some() {
+            return Ok(TallyHttpInfo {
+                variant: tally_response.product.map(classify),
+                version: tally_response.version,
+                edition: tally_response.edition,
+            });
+        }
+    }
+    
+    // Gateways that do not understand the request still answer with a banner
+    // such as "<RESPONSE>TallyPrime Server is Running</RESPONSE>"
+    if let Some(banner) = response.find("Server is Running").map(|end| &response[..end]) {
+        let product = banner.rsplit('>').next().unwrap_or("").trim().to_string();
+        return Ok(TallyHttpInfo {
+            variant: Some(classify(product)).filter(|v| !v.is_empty()),
+            ..Default::default()
+        });
+    }
+    
+    Err(TallyHttpError::Malformed("response is not a Tally XML envelope".to_string()))
+}
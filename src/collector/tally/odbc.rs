@@ -0,0 +1,190 @@
+//! Tally's ODBC server, through the platform driver manager (ODBC Driver
+//! Manager on Windows, unixODBC elsewhere).
+//!
+//! Tally serves ODBC on the same port as the XML gateway. The driver is
+//! addressed by name, so any driver registered under a Tally driver name
+//! answers the probe; the tests use the SQLite driver that way.
+
+use std::time::Duration;
+
+use odbc_api::{ConnectionOptions, Cursor, Environment, ResultSetMetadata};
+
+use super::installations::{add_source, gateway_endpoint};
+use super::GatewayOptions;
+use crate::models::{OdbcDataSource, TallyCompany, TallyInfo, TallyOdbc};
+use crate::utils::parse_tally_date;
+
+/// Driver names the Tally installer registers, 64-bit first
+const TALLY_DRIVERS: [&str; 2] = ["Tally ODBC Driver64", "Tally ODBC Driver"];
+
+/// Tally exposes each collection as a table; Company lists the loaded companies
+const COMPANY_QUERY: &str = "SELECT * FROM Company";
+
+/// Connects to Tally's ODBC server for each installation and lists the
+/// loaded companies under the installation's `odbc`.
+///
+/// An explicit DSN is tried for the first installation with a live gateway
+/// (else the first one); an explicit driver is tried on every installation's
+/// gateway port. Without either, the Tally DSNs known to the driver manager
+/// are tried, then the installed Tally driver on each gateway port.
+pub fn probe_odbc(installs: &mut [TallyInfo], gateway: &GatewayOptions, timeout: Duration) {
+    if installs.is_empty() {
+        return;
+    }
+    // No driver manager, no ODBC
+    let Ok(environment) = Environment::new() else {
+        return;
+    };
+
+    let data_sources = tally_data_sources(&environment, gateway.odbc_dsn.as_deref());
+    let driver = gateway.odbc_driver.clone().or_else(|| installed_tally_driver(&environment));
+    let primary = installs.iter().position(|i| i.gateway.is_some()).unwrap_or(0);
+
+    for (index, install) in installs.iter_mut().enumerate() {
+        let mut attempts: Vec<String> = Vec::new();
+        if index == primary {
+            match &gateway.odbc_dsn {
+                Some(dsn) => attempts.push(format!("DSN={}", dsn)),
+                None if gateway.odbc_driver.is_none() => {
+                    attempts.extend(data_sources.iter().map(|source| format!("DSN={}", source.name)));
+                }
+                None => {}
+            }
+        }
+        if gateway.odbc_dsn.is_none() {
+            let endpoint = install.gateway.clone().or_else(|| gateway_endpoint(install, gateway.endpoint.as_deref()));
+            if let (Some(driver), Some(endpoint)) = (&driver, endpoint) {
+                attempts.push(driver_connection_string(driver, &endpoint));
+            }
+        }
+        if attempts.is_empty() {
+            continue;
+        }
+
+        let mut result = TallyOdbc::default();
+        for connection in attempts {
+            result = query_companies(&environment, &connection, timeout);
+            if result.connected {
+                break;
+            }
+        }
+        if index == primary {
+            result.data_sources = data_sources.clone();
+        }
+        if result.connected {
+            add_source(install, "odbc", &result.connection);
+        }
+        install.odbc = Some(result);
+    }
+}
+
+/// DSNs whose driver is a Tally driver, plus the one asked for by name
+fn tally_data_sources(environment: &Environment, explicit: Option<&str>) -> Vec<OdbcDataSource> {
+    let Ok(sources) = environment.data_sources() else {
+        return Vec::new();
+    };
+    sources
+        .into_iter()
+        .filter(|source| {
+            Some(source.server_name.as_str()) == explicit || source.driver.to_ascii_lowercase().contains("tally")
+        })
+        .map(|source| OdbcDataSource {
+            name: source.server_name,
+            driver: source.driver,
+        })
+        .collect()
+}
+
+/// The first Tally driver registered with the driver manager
+fn installed_tally_driver(environment: &Environment) -> Option<String> {
+    let drivers = environment.drivers().ok()?;
+    TALLY_DRIVERS
+        .iter()
+        .find(|name| drivers.iter().any(|driver| driver.description == **name))
+        .map(|name| name.to_string())
+}
+
+/// `DRIVER={Tally ODBC Driver64};SERVER=127.0.0.1;PORT=9000` for a gateway endpoint
+fn driver_connection_string(driver: &str, endpoint: &str) -> String {
+    let (server, port) = endpoint.rsplit_once(':').unwrap_or((endpoint, "9000"));
+    format!("DRIVER={{{}}};SERVER={};PORT={}", driver, server, port)
+}
+
+/// Connects with `connection` and reads the Company table
+fn query_companies(environment: &Environment, connection: &str, timeout: Duration) -> TallyOdbc {
+    let mut result = TallyOdbc {
+        connection: connection.to_string(),
+        ..Default::default()
+    };
+
+    let options = ConnectionOptions {
+        // Whole seconds are all ODBC offers
+        login_timeout_sec: Some(timeout.as_secs().max(1) as u32),
+    };
+    let handle = match environment.connect_with_connection_string(connection, options) {
+        Ok(handle) => handle,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    result.connected = true;
+
+    match read_companies(&handle) {
+        Ok(companies) => result.companies = companies,
+        Err(e) => result.error = Some(e.to_string()),
+    }
+    result
+}
+
+fn read_companies(connection: &odbc_api::Connection<'_>) -> Result<Vec<TallyCompany>, odbc_api::Error> {
+    let Some(mut cursor) = connection.execute(COMPANY_QUERY, ())? else {
+        return Ok(Vec::new());
+    };
+
+    // Columns are named after the TDL methods: $Name, $GUID, $BooksFrom, ...
+    let columns: Vec<String> = cursor
+        .column_names()?
+        .map(|name| name.map(|n| n.trim_start_matches('$').to_ascii_uppercase()))
+        .collect::<Result<_, _>>()?;
+
+    let mut companies = Vec::new();
+    let mut buffer = Vec::new();
+    while let Some(mut row) = cursor.next_row()? {
+        let mut company = TallyCompany::default();
+        for (index, column) in columns.iter().enumerate() {
+            if !row.get_text(index as u16 + 1, &mut buffer)? {
+                continue;
+            }
+            let value = String::from_utf8_lossy(&buffer).trim().to_string();
+            if value.is_empty() {
+                continue;
+            }
+            match column.as_str() {
+                "NAME" => company.name = value,
+                "GUID" => company.guid = Some(value),
+                "COMPANYNUMBER" => company.company_number = Some(value),
+                "BOOKSFROM" => company.books_from = parse_odbc_date(&value),
+                "LASTVOUCHERDATE" => company.last_voucher_date = parse_odbc_date(&value),
+                _ => {}
+            }
+        }
+        if !company.name.is_empty() {
+            companies.push(company);
+        }
+    }
+    Ok(companies)
+}
+
+/// ODBC hands dates over as `2023-04-01` (sometimes with a time); Tally's
+/// own text forms are accepted too
+fn parse_odbc_date(value: &str) -> Option<String> {
+    let date = value.get(..10).unwrap_or(value);
+    let iso = date.len() == 10
+        && date.bytes().enumerate().all(|(i, b)| if i == 4 || i == 7 { b == b'-' } else { b.is_ascii_digit() });
+    if iso {
+        Some(date.to_string())
+    } else {
+        parse_tally_date(value)
+    }
+}
@@ -0,0 +1,33 @@
+//! Tally detection on a Windows volume mounted on another system
+
+use std::path::{Path, PathBuf};
+
+use super::installations::{detect_in_registry, detect_on_drive, inspect_installations, resolve_case_insensitive};
+use crate::collector::evidence::score_installations;
+use crate::collector::registry::RegistrySource;
+use crate::models::TallyInfo;
+
+/// Detects Tally from the volume's registry and Program Files folders.
+/// Paths are reported as Windows sees them; files are read through the mount.
+pub fn collect_offline_tally_info(root: &Path, registry: &dyn RegistrySource) -> Vec<TallyInfo> {
+    let mut installs = Vec::new();
+    detect_in_registry(&mut installs, registry, &|path| path.to_string());
+
+    // Evidence points at the file that was read, on the mounted volume
+    detect_on_drive(&mut installs, root, false);
+
+    // Nothing runs on a mounted volume, so there is no gateway to probe
+    let on_volume = |_: &TallyInfo, path: &str| volume_path(root, path);
+    inspect_installations(&mut installs, &on_volume);
+    score_installations(&mut installs);
+    installs
+}
+
+/// Maps a `C:\` path onto the mounted volume; other drives are not available
+pub fn volume_path(root: &Path, windows_path: &str) -> PathBuf {
+    let path = windows_path.trim();
+    match path.get(..2) {
+        Some(drive) if drive.eq_ignore_ascii_case("c:") => resolve_case_insensitive(root, &path[2..]),
+        _ => PathBuf::from(path),
+    }
+}
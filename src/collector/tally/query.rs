@@ -0,0 +1,40 @@
+//! Report exports for `quicksys tally query`: the envelope support engineers
+//! would otherwise write by hand, sent through the same client as the probe
+
+#[cfg(feature = "tally-xml")]
+use std::time::Duration;
+
+#[cfg(feature = "tally-xml")]
+use super::xml_api::{line_error, report_export_envelope};
+#[cfg(feature = "tally-xml")]
+use super::xml_client::{Charset, TallyHttpError, post_xml};
+
+/// A report export request
+#[derive(Debug, Default)]
+pub struct ReportQuery {
+    /// Report name as Tally knows it, e.g. "Trial Balance" or "Day Book"
+    pub report: String,
+    pub company: Option<String>,
+    /// Period start, `YYYYMMDD`
+    pub from: Option<String>,
+    /// Period end, `YYYYMMDD`
+    pub to: Option<String>,
+}
+
+/// Exports the report and returns Tally's XML response. A response carrying
+/// a `<LINEERROR>` is an error.
+#[cfg(feature = "tally-xml")]
+pub fn run_report_query(endpoint: &str, query: &ReportQuery, timeout: Duration) -> Result<String, TallyHttpError> {
+    let envelope = report_export_envelope(
+        &query.report,
+        query.company.as_deref(),
+        query.from.as_deref(),
+        query.to.as_deref(),
+    );
+    let response = post_xml(endpoint, &envelope, Charset::Utf8, timeout)?;
+
+    if let Some(error) = line_error(&response) {
+        return Err(TallyHttpError::Malformed(format!("Tally rejected the report export: {}", error)));
+    }
+    Ok(response)
+}
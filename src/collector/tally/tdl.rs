@@ -0,0 +1,83 @@
+//! Inventory of TDL/TCP customisation files: the ones tally.ini loads at
+//! startup and the ones the running instance reports
+
+use std::fs;
+use std::path::Path;
+#[cfg(feature = "tally-xml")]
+use std::time::Duration;
+
+#[cfg(feature = "tally-xml")]
+use super::xml_api::{collection_export_envelope, line_error, parse_records};
+#[cfg(feature = "tally-xml")]
+use super::xml_client::{Charset, TallyHttpError, post_xml};
+use crate::models::{TallyConfig, TallyTdl};
+use crate::utils::{format_system_time, sha256_hex};
+
+/// Inline collection name; a private name avoids clashing with user TDLs
+#[cfg(feature = "tally-xml")]
+const TDL_COLLECTION: &str = "QuickSys TDL Files";
+
+/// Asks the running Tally instance which TDL files it has loaded. Paths are
+/// returned as Tally sees them.
+#[cfg(feature = "tally-xml")]
+pub fn fetch_loaded_tdls(endpoint: &str, timeout: Duration) -> Result<Vec<String>, TallyHttpError> {
+    let envelope = collection_export_envelope(TDL_COLLECTION, "TDL File", &["Name"], &[], None);
+    let response = post_xml(endpoint, &envelope, Charset::Utf8, timeout)?;
+
+    if let Some(error) = line_error(&response) {
+        return Err(TallyHttpError::Malformed(format!("Tally rejected the TDL export: {}", error)));
+    }
+
+    Ok(parse_records(&response, "TDLFILE")
+        .into_iter()
+        .filter_map(|mut record| record.remove("NAME").or_else(|| record.remove("@NAME")))
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+/// TDL files tally.ini loads at startup; none when `User TDL=No` turns them off
+pub fn configured_tdls(config: &TallyConfig) -> &[String] {
+    if config.user_tdl == Some(false) {
+        &[]
+    } else {
+        &config.tdl_files
+    }
+}
+
+/// Adds a TDL file seen by `source` ("tally_ini" or "http"), describing the
+/// file at `host_path` when it is first seen
+pub fn add_tdl(tdls: &mut Vec<TallyTdl>, path: &str, host_path: &Path, source: &str) {
+    let key = path.replace('/', "\\").to_ascii_lowercase();
+    let index = match tdls.iter().position(|t| t.path.replace('/', "\\").to_ascii_lowercase() == key) {
+        Some(index) => index,
+        None => {
+            tdls.push(describe_tdl(path, host_path));
+            tdls.len() - 1
+        }
+    };
+
+    let tdl = &mut tdls[index];
+    if !tdl.sources.iter().any(|s| s == source) {
+        tdl.sources.push(source.to_string());
+    }
+}
+
+/// Size, SHA-256 and modification time of a TDL file, or `exists: false`
+pub fn describe_tdl(path: &str, host_path: &Path) -> TallyTdl {
+    let mut tdl = TallyTdl {
+        path: path.to_string(),
+        ..Default::default()
+    };
+
+    let Ok(metadata) = fs::metadata(host_path) else {
+        return tdl;
+    };
+    if !metadata.is_file() {
+        return tdl;
+    }
+    tdl.exists = true;
+    tdl.size_bytes = Some(metadata.len());
+    tdl.last_modified = metadata.modified().ok().map(format_system_time);
+    tdl.sha256 = fs::read(host_path).ok().map(|data| sha256_hex(&data));
+    tdl
+}
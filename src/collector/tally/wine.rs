@@ -0,0 +1,240 @@
+//! Tally detection inside Wine prefixes on Linux hosts.
+//!
+//! Wine keeps the registry as text files in the prefix (`system.reg` for
+//! HKLM, `user.reg` for HKCU) and maps drive letters through `dosdevices`.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::GatewayOptions;
+use super::installations::{self, detect_in_registry, detect_on_drive, resolve_case_insensitive, subdirectories};
+use crate::collector::process;
+use crate::collector::registry::MemoryRegistry;
+use crate::diagnostics::Diagnostics;
+use crate::models::TallyInfo;
+
+/// Directories under the home directory whose children are prefixes
+const PREFIX_COLLECTIONS: [&str; 4] = [
+    ".local/share/bottles/bottles",
+    ".var/app/com.usebottles.bottles/data/bottles/bottles",
+    ".PlayOnLinux/wineprefix",
+    "Games",
+];
+
+/// Detects Tally in every Wine prefix of the current user
+pub fn collect_wine_tally_info(timeout: Duration, gateway: &GatewayOptions, diagnostics: &Diagnostics) -> Vec<TallyInfo> {
+    let mut installs: Vec<TallyInfo> = discover_prefixes().iter().flat_map(|prefix| detect_in_prefix(prefix)).collect();
+
+    // One pass over all prefixes, so a gateway is probed and credited only once
+    let in_prefix = |install: &TallyInfo, path: &str| match &install.wine_prefix {
+        Some(prefix) => host_path(Path::new(prefix), path),
+        None => PathBuf::from(path),
+    };
+    installations::attach_processes(&mut installs, process::tally_processes(), &in_prefix);
+    installations::enrich_installations(&mut installs, &in_prefix, gateway, timeout, diagnostics);
+    installs
+}
+
+/// Finds Wine prefixes: `$WINEPREFIX`, `~/.wine`, Bottles and PlayOnLinux
+/// prefixes, and prefixes named in Lutris game configurations
+pub fn discover_prefixes() -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+
+    if let Some(prefix) = env::var_os("WINEPREFIX") {
+        candidates.push(prefix.into());
+    }
+
+    if let Some(home) = env::var_os("HOME").map(PathBuf::from) {
+        candidates.push(home.join(".wine"));
+        for collection in PREFIX_COLLECTIONS {
+            candidates.extend(subdirectories(&home.join(collection)));
+        }
+        candidates.extend(lutris_prefixes(&home));
+    }
+
+    let mut prefixes: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        if !is_prefix(&candidate) {
+            continue;
+        }
+        let candidate = fs::canonicalize(&candidate).unwrap_or(candidate);
+        if !prefixes.contains(&candidate) {
+            prefixes.push(candidate);
+        }
+    }
+    prefixes
+}
+
+/// Detects Tally installations in one prefix from its registry files and drive_c
+pub fn detect_in_prefix(prefix: &Path) -> Vec<TallyInfo> {
+    let mut installs = Vec::new();
+
+    for registry in prefix_registries(prefix) {
+        detect_in_registry(&mut installs, &registry, &|path| host_path_string(prefix, path));
+    }
+
+    // Check drive_c/Program Files*/Tally* for Tally.exe
+    detect_on_drive(&mut installs, &prefix.join("drive_c"), true);
+
+    for install in &mut installs {
+        install.wine_prefix = Some(prefix.to_string_lossy().into_owned());
+    }
+    installs
+}
+
+/// The prefix's machine and user registries. Both files keep their keys
+/// below Software\, like HKLM does.
+pub fn prefix_registries(prefix: &Path) -> Vec<MemoryRegistry> {
+    ["system.reg", "user.reg"]
+        .iter()
+        .filter_map(|file| fs::read(prefix.join(file)).ok())
+        .map(|text| parse_registry(&String::from_utf8_lossy(&text)))
+        .collect()
+}
+
+/// Maps a Windows path inside a prefix to the host path, resolving the drive
+/// through `dosdevices` and each component case-insensitively like Wine does.
+/// Paths without a drive letter are already host paths.
+pub fn host_path(prefix: &Path, windows_path: &str) -> PathBuf {
+    let path = windows_path.trim();
+    let bytes = path.as_bytes();
+    if bytes.len() < 2 || !bytes[0].is_ascii_alphabetic() || bytes[1] != b':' {
+        return PathBuf::from(path);
+    }
+
+    let drive = bytes[0].to_ascii_lowercase() as char;
+    let mut drive_root = prefix.join("dosdevices").join(format!("{}:", drive));
+    if !drive_root.exists() && drive == 'c' {
+        drive_root = prefix.join("drive_c");
+    }
+
+    resolve_case_insensitive(&drive_root, &path[2..])
+}
+
+fn host_path_string(prefix: &Path, windows_path: &str) -> String {
+    host_path(prefix, windows_path).to_string_lossy().into_owned()
+}
+
+fn is_prefix(path: &Path) -> bool {
+    path.join("system.reg").is_file() && path.join("drive_c").is_dir()
+}
+
+/// Prefixes from `prefix:` lines in Lutris game configurations
+fn lutris_prefixes(home: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(home.join(".config/lutris/games")) else {
+        return Vec::new();
+    };
+
+    let mut prefixes = Vec::new();
+    for entry in entries.flatten() {
+        let Ok(text) = fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        for line in text.lines() {
+            let Some(value) = line.trim().strip_prefix("prefix:") else {
+                continue;
+            };
+            let value = value.trim().trim_matches(['"', '\'']);
+            match value.strip_prefix("~/") {
+                Some(relative) => prefixes.push(home.join(relative)),
+                None if !value.is_empty() => prefixes.push(PathBuf::from(value)),
+                None => {}
+            }
+        }
+    }
+    prefixes
+}
+
+/// Parses a Wine registry file (the `WINE REGISTRY Version 2` text format).
+/// String, expandable, multi-string and dword values are kept (dwords as
+/// decimal); binary values are skipped.
+pub fn parse_registry(text: &str) -> MemoryRegistry {
+    let mut registry = MemoryRegistry::default();
+    let mut current: Option<String> = None;
+
+    for line in text.lines() {
+        let line = line.trim_end();
+
+        if let Some(rest) = line.strip_prefix('[') {
+            // "[Software\\Tally Solutions] 1700000000": the timestamp follows the bracket
+            current = rest.rfind(']').map(|end| {
+                let path = unescape(&rest[..end]);
+                registry.add_key(&path);
+                path
+            });
+        } else if let Some(key) = &current {
+            if let Some((name, value)) = parse_value_line(line) {
+                registry.set_value(key, &name, value);
+            }
+        }
+    }
+
+    registry
+}
+
+/// Parses `"Name"=value` or `@=value`
+fn parse_value_line(line: &str) -> Option<(String, String)> {
+    let (name, rest) = match line.strip_prefix('@') {
+        Some(rest) => (String::new(), rest),
+        None => parse_quoted(line.strip_prefix('"')?)?,
+    };
+    let value = rest.trim_start().strip_prefix('=')?.trim_start();
+
+    let value = if let Some(quoted) = value.strip_prefix('"') {
+        parse_quoted(quoted)?.0
+    } else if let Some(typed) = value.strip_prefix("str(") {
+        // str(2) is REG_EXPAND_SZ, str(7) is REG_MULTI_SZ with NUL separators
+        let (_, quoted) = typed.split_once("):\"")?;
+        let text = parse_quoted(quoted)?.0;
+        text.split('\0').filter(|s| !s.is_empty()).collect::<Vec<_>>().join("\n")
+    } else if let Some(hex) = value.strip_prefix("dword:") {
+        u32::from_str_radix(hex.trim(), 16).ok()?.to_string()
+    } else {
+        return None;
+    };
+
+    Some((name, value))
+}
+
+/// Reads an escaped string up to its closing quote, returning it and the rest of the line
+fn parse_quoted(text: &str) -> Option<(String, &str)> {
+    let mut out = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((out, &text[i + 1..])),
+            '\\' => {
+                let (_, escaped) = chars.next()?;
+                match escaped {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    '0' => out.push('\0'),
+                    'x' => {
+                        // Up to four hex digits of a UTF-16 unit
+                        let digits: String = chars.clone().take(4).map(|(_, c)| c).take_while(char::is_ascii_hexdigit).collect();
+                        for _ in 0..digits.len() {
+                            chars.next();
+                        }
+                        let unit = u32::from_str_radix(&digits, 16).ok()?;
+                        out.push(char::from_u32(unit).unwrap_or(char::REPLACEMENT_CHARACTER));
+                    }
+                    other => out.push(other),
+                }
+            }
+            c => out.push(c),
+        }
+    }
+
+    None
+}
+
+/// Key names escape backslashes the same way as quoted strings
+fn unescape(text: &str) -> String {
+    let mut quoted = text.to_string();
+    quoted.push('"');
+    parse_quoted(&quoted).map(|(s, _)| s).unwrap_or_else(|| text.to_string())
+}
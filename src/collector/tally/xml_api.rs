@@ -0,0 +1,300 @@
+//! Request envelopes and response parsing for Tally's XML API
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// One object from an export response: child element texts keyed by
+/// uppercase tag name, plus the object's `NAME` attribute under `@NAME`
+pub type XmlRecord = BTreeMap<String, String>;
+
+/// Builds an export request for an inline TDL collection of `object_type`
+/// objects, fetching `fetch` methods and evaluating `compute` formulae
+/// (`Name : Formula`) for each object
+pub fn collection_export_envelope(
+    collection: &str,
+    object_type: &str,
+    fetch: &[&str],
+    compute: &[&str],
+    company: Option<&str>,
+) -> String {
+    let computes: String = compute
+        .iter()
+        .map(|formula| format!("<COMPUTE>{}</COMPUTE>", escape(formula)))
+        .collect();
+
+    let company_variable = company
+        .map(|name| format!("<SVCURRENTCOMPANY>{}</SVCURRENTCOMPANY>", escape(name)))
+        .unwrap_or_default();
+
+    format!(
+        "<ENVELOPE>\
+           <HEADER><VERSION>1</VERSION><TALLYREQUEST>Export</TALLYREQUEST><TYPE>Collection</TYPE><ID>{name}</ID></HEADER>\
+           <BODY><DESC>\
+             <STATICVARIABLES><SVEXPORTFORMAT>$$SysName:XML</SVEXPORTFORMAT>{company}</STATICVARIABLES>\
+             <TDL><TDLMESSAGE>\
+               <COLLECTION NAME=\"{name}\" ISMODIFY=\"No\"><TYPE>{object_type}</TYPE><FETCH>{fetch}</FETCH>{computes}</COLLECTION>\
+             </TDLMESSAGE></TDL>\
+           </DESC></BODY>\
+         </ENVELOPE>",
+        name = escape(collection),
+        company = company_variable,
+        object_type = escape(object_type),
+        fetch = escape(&fetch.join(", ")),
+        computes = computes,
+    )
+}
+
+/// Builds an export request that summarises `object_type` objects inside
+/// Tally: one object per distinct `by` key (`Name : Formula`), carrying the
+/// `aggregates` (`Name : SUM|MAX|MIN : Formula`) over the objects in the group
+pub fn aggregate_export_envelope(
+    collection: &str,
+    object_type: &str,
+    by: &[&str],
+    aggregates: &[&str],
+    company: Option<&str>,
+) -> String {
+    let method_name = |definition: &&str| definition.split(':').next().unwrap_or("").trim().to_string();
+    let fetch: Vec<String> = by.iter().chain(aggregates).map(method_name).collect();
+
+    let clauses: String = by
+        .iter()
+        .map(|key| format!("<BY>{}</BY>", escape(key)))
+        .chain(aggregates.iter().map(|aggregate| format!("<AGGRCOMPUTE>{}</AGGRCOMPUTE>", escape(aggregate))))
+        .collect();
+
+    let company_variable = company
+        .map(|name| format!("<SVCURRENTCOMPANY>{}</SVCURRENTCOMPANY>", escape(name)))
+        .unwrap_or_default();
+
+    format!(
+        "<ENVELOPE>\
+           <HEADER><VERSION>1</VERSION><TALLYREQUEST>Export</TALLYREQUEST><TYPE>Collection</TYPE><ID>{name}</ID></HEADER>\
+           <BODY><DESC>\
+             <STATICVARIABLES><SVEXPORTFORMAT>$$SysName:XML</SVEXPORTFORMAT>{company}</STATICVARIABLES>\
+             <TDL><TDLMESSAGE>\
+               <COLLECTION NAME=\"{name}\" ISMODIFY=\"No\"><TYPE>{object_type}</TYPE>{clauses}<FETCH>{fetch}</FETCH></COLLECTION>\
+             </TDLMESSAGE></TDL>\
+           </DESC></BODY>\
+         </ENVELOPE>",
+        name = escape(collection),
+        company = company_variable,
+        object_type = escape(object_type),
+        clauses = clauses,
+        fetch = escape(&fetch.join(", ")),
+    )
+}
+
+/// Builds an export request for a report such as "Trial Balance", optionally
+/// for one company and a period (dates as `YYYYMMDD`)
+pub fn report_export_envelope(report: &str, company: Option<&str>, from: Option<&str>, to: Option<&str>) -> String {
+    let variables: String = [("SVCURRENTCOMPANY", company), ("SVFROMDATE", from), ("SVTODATE", to)]
+        .iter()
+        .filter_map(|(name, value)| value.map(|value| format!("<{0}>{1}</{0}>", name, escape(value))))
+        .collect();
+
+    format!(
+        "<ENVELOPE>\
+           <HEADER><VERSION>1</VERSION><TALLYREQUEST>Export</TALLYREQUEST><TYPE>Data</TYPE><ID>{report}</ID></HEADER>\
+           <BODY><DESC>\
+             <STATICVARIABLES><SVEXPORTFORMAT>$$SysName:XML</SVEXPORTFORMAT>{variables}</STATICVARIABLES>\
+           </DESC></BODY>\
+         </ENVELOPE>",
+        report = escape(report),
+        variables = variables,
+    )
+}
+
+/// Builds a request that evaluates a TDL function such as `$$LicenseInfo`
+/// with the given parameters
+pub fn function_export_envelope(function: &str, params: &[&str]) -> String {
+    let params: String = params
+        .iter()
+        .map(|param| format!("<PARAM>{}</PARAM>", escape(param)))
+        .collect();
+
+    format!(
+        "<ENVELOPE>\
+           <HEADER><VERSION>1</VERSION><TALLYREQUEST>Export</TALLYREQUEST><TYPE>Function</TYPE><ID>{function}</ID></HEADER>\
+           <BODY><DESC><FUNCPARAMLIST>{params}</FUNCPARAMLIST></DESC></BODY>\
+         </ENVELOPE>",
+        function = escape(function),
+        params = params,
+    )
+}
+
+/// Returns the `<RESULT>` text of a function export response (empty for an
+/// empty result), or None when the response carries no result at all
+pub fn function_result(xml: &str) -> Option<String> {
+    if !xml.contains("<RESULT") {
+        return None;
+    }
+    let result = parse_records(xml, "DATA").into_iter().next().and_then(|mut data| data.remove("RESULT"));
+    Some(result.unwrap_or_default())
+}
+
+/// Extracts every `<record_tag>` element from an export response.
+/// Nested elements are flattened; the innermost text wins.
+pub fn parse_records(xml: &str, record_tag: &str) -> Vec<XmlRecord> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut records = Vec::new();
+    let mut current: Option<XmlRecord> = None;
+    let mut open_tag: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let tag = String::from_utf8_lossy(e.name().as_ref()).to_ascii_uppercase();
+
+                if tag == record_tag && current.is_none() {
+                    let mut record = XmlRecord::new();
+                    for attr in e.attributes().flatten() {
+                        if attr.key.as_ref().eq_ignore_ascii_case(b"NAME") {
+                            if let Ok(value) = attr.unescape_value() {
+                                record.insert("@NAME".to_string(), value.into_owned());
+                            }
+                        }
+                    }
+                    current = Some(record);
+                } else if current.is_some() {
+                    open_tag = Some(tag);
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let (Some(record), Some(tag)) = (current.as_mut(), open_tag.as_ref()) {
+                    if let Ok(text) = t.unescape() {
+                        record.insert(tag.clone(), text.trim().to_string());
+                    }
+                }
+            }
+            Ok(Event::End(e)) => {
+                let tag = String::from_utf8_lossy(e.name().as_ref()).to_ascii_uppercase();
+                if tag == record_tag {
+                    if let Some(record) = current.take() {
+                        records.push(record);
+                    }
+                }
+                open_tag = None;
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+
+    records
+}
+
+/// Converts an XML document to JSON: an element becomes an object keyed by
+/// child tag, attributes become `@name` keys and text `#text` (or a plain
+/// string for elements with text only). Repeated tags become arrays.
+pub fn xml_to_json(xml: &str) -> Result<Value, String> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    // Open elements: tag name, children and attributes so far, text so far
+    let mut stack: Vec<(String, Map<String, Value>, String)> = vec![(String::new(), Map::new(), String::new())];
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                stack.push((name, attributes(&e), String::new()));
+            }
+            Ok(Event::Empty(e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                let value = element_value(attributes(&e), String::new());
+                if let Some((_, parent, _)) = stack.last_mut() {
+                    append_child(parent, name, value);
+                }
+            }
+            Ok(Event::Text(t)) => {
+                // Tally writes control characters as character references XML does not allow
+                let text = t.unescape().map(|text| text.into_owned()).unwrap_or_else(|_| String::from_utf8_lossy(&t).into_owned());
+                if let Some((_, _, buffer)) = stack.last_mut() {
+                    buffer.push_str(&text);
+                }
+            }
+            Ok(Event::CData(c)) => {
+                if let Some((_, _, buffer)) = stack.last_mut() {
+                    buffer.push_str(&String::from_utf8_lossy(&c));
+                }
+            }
+            Ok(Event::End(_)) => {
+                if stack.len() < 2 {
+                    return Err("unbalanced closing tag".to_string());
+                }
+                let Some((name, children, text)) = stack.pop() else {
+                    break;
+                };
+                if let Some((_, parent, _)) = stack.last_mut() {
+                    append_child(parent, name, element_value(children, text));
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("malformed XML at byte {}: {}", reader.buffer_position(), e)),
+            _ => {}
+        }
+    }
+
+    match stack.pop() {
+        Some((_, document, _)) if stack.is_empty() && !document.is_empty() => Ok(Value::Object(document)),
+        Some(_) if !stack.is_empty() => Err("document ends inside an element".to_string()),
+        _ => Err("document has no elements".to_string()),
+    }
+}
+
+fn attributes(element: &BytesStart) -> Map<String, Value> {
+    element
+        .attributes()
+        .flatten()
+        .map(|attr| {
+            let key = format!("@{}", String::from_utf8_lossy(attr.key.as_ref()));
+            let value = attr
+                .unescape_value()
+                .map(|v| v.into_owned())
+                .unwrap_or_else(|_| String::from_utf8_lossy(&attr.value).into_owned());
+            (key, Value::String(value))
+        })
+        .collect()
+}
+
+fn element_value(mut children: Map<String, Value>, text: String) -> Value {
+    if children.is_empty() {
+        return Value::String(text);
+    }
+    if !text.is_empty() {
+        children.insert("#text".to_string(), Value::String(text));
+    }
+    Value::Object(children)
+}
+
+fn append_child(parent: &mut Map<String, Value>, name: String, value: Value) {
+    match parent.get_mut(&name) {
+        Some(Value::Array(items)) => items.push(value),
+        Some(existing) => {
+            let first = existing.take();
+            *existing = Value::Array(vec![first, value]);
+        }
+        None => {
+            parent.insert(name, value);
+        }
+    }
+}
+
+/// Returns the `<LINEERROR>` message of a failed request, if any
+pub fn line_error(xml: &str) -> Option<String> {
+    let start = xml.find("<LINEERROR>")? + "<LINEERROR>".len();
+    let end = xml[start..].find("</LINEERROR>")? + start;
+    Some(xml[start..end].trim().to_string())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
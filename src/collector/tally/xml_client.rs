@@ -0,0 +1,317 @@
+//! Minimal HTTP/1.1 client for Tally's XML-over-HTTP gateway.
+//!
+//! Tally only understands POST requests carrying an XML envelope. Responses may
+//! be UTF-8 or UTF-16 (with or without a BOM) and may be chunked.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// Largest response body accepted; report exports of big companies stay well
+/// below it, and a broken gateway cannot make us allocate more
+const MAX_RESPONSE_BYTES: usize = 64 * 1024 * 1024;
+
+/// Longest status, header or chunk-size line accepted
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// Character set of a request envelope or response body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    /// Little-endian, which is what Tally means by `charset=utf-16`
+    Utf16,
+    Utf16Be,
+}
+
+#[derive(Debug)]
+pub enum TallyHttpError {
+    /// The endpoint could not be resolved to a socket address
+    InvalidEndpoint(String),
+    /// Connecting, writing or reading failed (including timeouts)
+    Io(io::Error),
+    /// The gateway answered with a non-2xx status
+    Status(u16),
+    /// The response was not valid HTTP
+    Malformed(String),
+    /// The response body is larger than `MAX_RESPONSE_BYTES`
+    TooLarge,
+}
+
+impl fmt::Display for TallyHttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TallyHttpError::InvalidEndpoint(endpoint) => write!(f, "invalid endpoint: {}", endpoint),
+            TallyHttpError::Io(e) => write!(f, "I/O error: {}", e),
+            TallyHttpError::Status(code) => write!(f, "unexpected HTTP status {}", code),
+            TallyHttpError::Malformed(reason) => write!(f, "malformed response: {}", reason),
+            TallyHttpError::TooLarge => write!(f, "response larger than {} bytes", MAX_RESPONSE_BYTES),
+        }
+    }
+}
+
+impl From<io::Error> for TallyHttpError {
+    fn from(e: io::Error) -> Self {
+        TallyHttpError::Io(e)
+    }
+}
+
+/// POSTs an XML envelope to `endpoint` (host:port) and returns the decoded response body.
+/// The whole exchange, including connect, is bounded by `timeout`.
+pub fn post_xml(endpoint: &str, envelope: &str, charset: Charset, timeout: Duration) -> Result<String, TallyHttpError> {
+    let deadline = Instant::now() + timeout;
+    let addr = resolve(endpoint)?;
+
+    let stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_write_timeout(Some(remaining(deadline)?))?;
+
+    let (body, content_type) = match charset {
+        Charset::Utf8 => (envelope.as_bytes().to_vec(), "text/xml; charset=utf-8"),
+        Charset::Utf16 => (encode_utf16(envelope, false), "text/xml; charset=utf-16"),
+        Charset::Utf16Be => (encode_utf16(envelope, true), "text/xml; charset=utf-16be"),
+    };
+
+    let head = format!(
+        "POST / HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccept: text/xml\r\nConnection: close\r\n\r\n",
+        endpoint,
+        content_type,
+        body.len()
+    );
+
+    let mut writer = &stream;
+    writer.write_all(head.as_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+
+    let mut reader = BufReader::new(&stream);
+
+    // Status line
+    let status_line = read_line(&mut reader, &stream, deadline)?;
+    let status = parse_status(&status_line)?;
+
+    // Headers
+    let mut content_length = None;
+    let mut chunked = false;
+    let mut response_charset = None;
+    loop {
+        let line = read_line(&mut reader, &stream, deadline)?;
+        if line.is_empty() {
+            break;
+        }
+
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(TallyHttpError::Malformed(format!("bad header line: {}", line)));
+        };
+        let value = value.trim();
+
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => {
+                content_length = Some(value.parse::<usize>()
+                    .map_err(|_| TallyHttpError::Malformed(format!("bad Content-Length: {}", value)))?);
+            }
+            "transfer-encoding" => chunked = value.to_ascii_lowercase().contains("chunked"),
+            "content-type" => response_charset = charset_from_content_type(value),
+            _ => {}
+        }
+    }
+
+    // Body
+    let raw = if chunked {
+        read_chunked(&mut reader, &stream, deadline)?
+    } else if let Some(length) = content_length {
+        if length > MAX_RESPONSE_BYTES {
+            return Err(TallyHttpError::TooLarge);
+        }
+        // Read as the data arrives rather than trusting the header with an allocation
+        let mut raw = Vec::new();
+        if read_body(&mut reader, &stream, deadline, length, &mut raw)? < length {
+            return Err(TallyHttpError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "body shorter than Content-Length")));
+        }
+        raw
+    } else {
+        let mut raw = Vec::new();
+        read_body(&mut reader, &stream, deadline, MAX_RESPONSE_BYTES + 1, &mut raw)?;
+        if raw.len() > MAX_RESPONSE_BYTES {
+            return Err(TallyHttpError::TooLarge);
+        }
+        raw
+    };
+
+    if !(200..300).contains(&status) {
+        return Err(TallyHttpError::Status(status));
+    }
+
+    Ok(decode_body(&raw, response_charset))
+}
+
+fn resolve(endpoint: &str) -> Result<SocketAddr, TallyHttpError> {
+    endpoint
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.next())
+        .ok_or_else(|| TallyHttpError::InvalidEndpoint(endpoint.to_string()))
+}
+
+/// Time left before `deadline`, or a timeout error once it has passed
+fn remaining(deadline: Instant) -> Result<Duration, TallyHttpError> {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+        return Err(timed_out());
+    }
+    Ok(left)
+}
+
+fn timed_out() -> TallyHttpError {
+    TallyHttpError::Io(io::Error::new(io::ErrorKind::TimedOut, "Tally request timed out"))
+}
+
+/// Waits until the reader has data buffered, re-arming the socket's read timeout with the
+/// time left before `deadline` so that a gateway trickling bytes cannot outlast it. An empty
+/// slice means the gateway closed the connection.
+fn fill_buf<'r>(reader: &'r mut impl BufRead, stream: &TcpStream, deadline: Instant) -> Result<&'r [u8], TallyHttpError> {
+    loop {
+        stream.set_read_timeout(Some(remaining(deadline)?))?;
+        match reader.fill_buf() {
+            Ok(_) => break,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            // Unix reports an expired read timeout as WouldBlock
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Err(timed_out()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    // The data is buffered now, so this does not touch the socket again
+    Ok(reader.fill_buf()?)
+}
+
+/// Appends up to `limit` bytes to `body` and returns how many were read, fewer only when the
+/// gateway closed the connection early
+fn read_body(reader: &mut impl BufRead, stream: &TcpStream, deadline: Instant, limit: usize, body: &mut Vec<u8>) -> Result<usize, TallyHttpError> {
+    let mut read = 0;
+    while read < limit {
+        let available = fill_buf(reader, stream, deadline)?;
+        if available.is_empty() {
+            break;
+        }
+        let taken = available.len().min(limit - read);
+        body.extend_from_slice(&available[..taken]);
+        reader.consume(taken);
+        read += taken;
+    }
+    Ok(read)
+}
+
+/// Reads one CRLF-terminated line, without the terminator
+fn read_line(reader: &mut impl BufRead, stream: &TcpStream, deadline: Instant) -> Result<String, TallyHttpError> {
+    let mut line = Vec::new();
+    loop {
+        let available = fill_buf(reader, stream, deadline)?;
+        if available.is_empty() {
+            break;
+        }
+        let end = available.iter().position(|&b| b == b'\n');
+        let taken = end.map_or(available.len(), |i| i + 1);
+        line.extend_from_slice(&available[..taken]);
+        reader.consume(taken);
+
+        if line.len() > MAX_LINE_BYTES {
+            return Err(TallyHttpError::Malformed("line too long".to_string()));
+        }
+        if end.is_some() {
+            break;
+        }
+    }
+
+    if line.is_empty() {
+        return Err(TallyHttpError::Malformed("connection closed before end of headers".to_string()));
+    }
+    let line = String::from_utf8(line).map_err(|_| TallyHttpError::Malformed("line is not UTF-8".to_string()))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+fn parse_status(status_line: &str) -> Result<u16, TallyHttpError> {
+    let mut parts = status_line.split_whitespace();
+
+    match (parts.next(), parts.next()) {
+        (Some(version), Some(code)) if version.starts_with("HTTP/1.") => code
+            .parse()
+            .map_err(|_| TallyHttpError::Malformed(format!("bad status line: {}", status_line))),
+        _ => Err(TallyHttpError::Malformed(format!("bad status line: {}", status_line))),
+    }
+}
+
+/// Reads a `Transfer-Encoding: chunked` body (RFC 9112 section 7.1)
+fn read_chunked(reader: &mut impl BufRead, stream: &TcpStream, deadline: Instant) -> Result<Vec<u8>, TallyHttpError> {
+    let mut body = Vec::new();
+
+    loop {
+        let size_line = read_line(reader, stream, deadline)?;
+        // Chunk extensions follow a ';' and are ignored
+        let size_hex = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_hex, 16)
+            .map_err(|_| TallyHttpError::Malformed(format!("bad chunk size: {}", size_line)))?;
+
+        if size == 0 {
+            // Skip optional trailers up to the terminating empty line
+            while !read_line(reader, stream, deadline)?.is_empty() {}
+            return Ok(body);
+        }
+
+        if size > MAX_RESPONSE_BYTES - body.len() {
+            return Err(TallyHttpError::TooLarge);
+        }
+        if read_body(reader, stream, deadline, size, &mut body)? < size {
+            return Err(TallyHttpError::Io(io::Error::new(io::ErrorKind::UnexpectedEof, "chunk shorter than its size")));
+        }
+
+        // Each chunk is followed by CRLF
+        if !read_line(reader, stream, deadline)?.is_empty() {
+            return Err(TallyHttpError::Malformed("missing CRLF after chunk".to_string()));
+        }
+    }
+}
+
+fn charset_from_content_type(content_type: &str) -> Option<Charset> {
+    let lower = content_type.to_ascii_lowercase();
+    let charset = lower.split(';').find_map(|part| part.trim().strip_prefix("charset="))?;
+
+    match charset.trim_matches('"') {
+        "utf-16" | "utf-16le" | "unicode" => Some(Charset::Utf16),
+        "utf-16be" => Some(Charset::Utf16Be),
+        _ => Some(Charset::Utf8),
+    }
+}
+
+/// Decodes a response body, preferring a BOM over the declared charset
+fn decode_body(raw: &[u8], declared: Option<Charset>) -> String {
+    match raw {
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, false),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, true),
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        // Without a BOM, UTF-16 XML starts with '<' next to a NUL byte
+        _ if declared == Some(Charset::Utf16Be) || raw.starts_with(b"\0<") => decode_utf16(raw, true),
+        _ if declared == Some(Charset::Utf16) || raw.starts_with(b"<\0") => decode_utf16(raw, false),
+        _ => String::from_utf8_lossy(raw).into_owned(),
+    }
+}
+
+fn decode_utf16(raw: &[u8], big_endian: bool) -> String {
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|pair| if big_endian {
+            u16::from_be_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_le_bytes([pair[0], pair[1]])
+        })
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Encodes as UTF-16 with a BOM; Tally expects little-endian for `charset=utf-16`
+fn encode_utf16(text: &str, big_endian: bool) -> Vec<u8> {
+    let mut bytes = if big_endian { vec![0xFE, 0xFF] } else { vec![0xFF, 0xFE] };
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() });
+    }
+    bytes
+}
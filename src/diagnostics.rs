@@ -0,0 +1,172 @@
+//! Self-diagnostics recorded by the collector: probe timings, recent errors and uptime.
+//! Only the HTTP server reports them, so builds without it keep just the timings.
+
+#[cfg(feature = "http")]
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+#[cfg(feature = "http")]
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+#[cfg(feature = "http")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Number of recent errors kept for `/api/diagnostics`
+#[cfg(feature = "http")]
+const MAX_RECENT_ERRORS: usize = 20;
+
+#[derive(Debug, Default, Clone)]
+struct ProbeStats {
+    runs: u64,
+    last_duration_ms: u64,
+    max_duration_ms: u64,
+    #[cfg(feature = "http")]
+    last_error: Option<String>,
+}
+
+#[cfg(feature = "http")]
+#[derive(Debug, Clone)]
+struct ErrorRecord {
+    probe: String,
+    message: String,
+    at_unix_ms: u64,
+}
+
+pub struct Diagnostics {
+    #[cfg(feature = "http")]
+    started_at: Instant,
+    collections: AtomicU64,
+    probes: Mutex<BTreeMap<String, ProbeStats>>,
+    #[cfg(feature = "http")]
+    errors: Mutex<VecDeque<ErrorRecord>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            #[cfg(feature = "http")]
+            started_at: Instant::now(),
+            collections: AtomicU64::new(0),
+            probes: Mutex::new(BTreeMap::new()),
+            #[cfg(feature = "http")]
+            errors: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Runs a probe and records how long it took
+    pub fn time_probe<T>(&self, probe: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        let duration_ms = start.elapsed().as_millis() as u64;
+
+        if let Ok(mut probes) = self.probes.lock() {
+            let stats = probes.entry(probe.to_string()).or_default();
+            stats.runs += 1;
+            stats.last_duration_ms = duration_ms;
+            stats.max_duration_ms = stats.max_duration_ms.max(duration_ms);
+        }
+
+        result
+    }
+
+    /// Records a probe failure; the most recent errors are kept for diagnostics
+    #[cfg(feature = "http")]
+    pub fn record_error(&self, probe: &str, message: impl Into<String>) {
+        let message = message.into();
+
+        if let Ok(mut probes) = self.probes.lock() {
+            probes.entry(probe.to_string()).or_default().last_error = Some(message.clone());
+        }
+
+        if let Ok(mut errors) = self.errors.lock() {
+            if errors.len() == MAX_RECENT_ERRORS {
+                errors.pop_front();
+            }
+            errors.push_back(ErrorRecord {
+                probe: probe.to_string(),
+                message,
+                at_unix_ms: unix_ms(SystemTime::now()),
+            });
+        }
+    }
+
+    /// Without the HTTP server nobody reads the errors, so they are dropped
+    #[cfg(not(feature = "http"))]
+    pub fn record_error(&self, _probe: &str, _message: impl Into<String>) {}
+
+    /// Marks a full collection as finished
+    pub fn finish_collection(&self) {
+        self.collections.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of completed collections since startup
+    #[cfg(feature = "http")]
+    pub fn collections(&self) -> u64 {
+        self.collections.load(Ordering::Relaxed)
+    }
+
+    #[cfg(feature = "http")]
+    pub fn uptime_ms(&self) -> u64 {
+        self.started_at.elapsed().as_millis() as u64
+    }
+
+    /// Renders the recorded state as JSON
+    #[cfg(feature = "http")]
+    pub fn to_json(&self) -> Value {
+        let probes: BTreeMap<String, Value> = self.probes.lock()
+            .map(|probes| {
+                probes.iter()
+                    .map(|(name, stats)| {
+                        (name.clone(), json!({
+                            "runs": stats.runs,
+                            "last_duration_ms": stats.last_duration_ms,
+                            "max_duration_ms": stats.max_duration_ms,
+                            "last_error": stats.last_error,
+                        }))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let errors: Vec<Value> = self.errors.lock()
+            .map(|errors| {
+                errors.iter()
+                    .map(|e| json!({"probe": e.probe, "message": e.message, "at_unix_ms": e.at_unix_ms}))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "schema_version": crate::schema::SCHEMA_VERSION,
+            "platform": std::env::consts::OS,
+            "features": enabled_features(),
+            "uptime_ms": self.uptime_ms(),
+            "collections": self.collections(),
+            "probes": probes,
+            "errors": errors,
+        })
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Cargo features compiled into this binary
+#[cfg(feature = "http")]
+pub fn enabled_features() -> Value {
+    json!({
+        "http": cfg!(feature = "http"),
+        "tally-xml": cfg!(feature = "tally-xml"),
+        "tally-odbc": cfg!(feature = "tally-odbc"),
+    })
+}
+
+#[cfg(feature = "http")]
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0)
+}
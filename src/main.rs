@@ -1,24 +1,19 @@
 use std::time::Instant;
 use clap::Parser;
-use serde_json::Value;
 
 mod models;
 mod cli;
 mod utils;
-
-#[cfg(windows)]
+mod schema;
+mod diagnostics;
 mod collector;
 
 #[cfg(not(windows))]
 mod mock_collector;
 
-use cli::Cli;
-
-#[cfg(windows)]
+use cli::{Cli, Command, TallyCommand};
 use collector::Collector;
-
-#[cfg(not(windows))]
-use mock_collector::Collector;
+use collector::tally::query::ReportQuery;
 
 fn main() {
     let start_time = Instant::now();
@@ -26,8 +21,66 @@ fn main() {
     // Parse command line arguments
     let cli = Cli::parse();
     
+    // Subcommands run instead of a collection
+    if let Some(command) = cli.command {
+        match command {
+            Command::Schema { openapi } => {
+                let document = if openapi {
+                    schema::openapi_document()
+                } else {
+                    schema::system_info_schema()
+                };
+                print_json(&document, cli.pretty);
+            }
+            Command::FileVersion { path } => {
+                match collector::pe::read_version_info(&path) {
+                    Ok(info) => print_json(&serde_json::json!(info), cli.pretty),
+                    Err(e) => {
+                        print_json(&serde_json::json!({ "error": e.to_string() }), cli.pretty);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            Command::Tally { command: TallyCommand::Probe { endpoint } } => {
+                let (report, ok) = probe_tally_endpoint(&endpoint, utils::timeout_duration(cli.timeout_ms), cli.mask_serials);
+                print_json(&report, cli.pretty);
+                if !ok {
+                    std::process::exit(1);
+                }
+            }
+            Command::Tally { command: TallyCommand::Query { endpoint, report, company, from, to, raw } } => {
+                let query = ReportQuery { report, company, from, to };
+                if !query_tally_report(&endpoint, query, raw, utils::timeout_duration(cli.timeout_ms), cli.pretty) {
+                    std::process::exit(1);
+                }
+            }
+            Command::Tally { command: TallyCommand::Data { path } } => {
+                let folders = collector::tally::data::scan_data_directory(&path);
+                print_json(&serde_json::json!(folders), cli.pretty);
+            }
+            Command::Tally { command: TallyCommand::Registry { path } } => {
+                match collector::registry::load_snapshot(&path) {
+                    Ok(registry) => {
+                        // Paths in a snapshot belong to another machine; report them as listed
+                        let mut installs = Vec::new();
+                        collector::tally::installations::detect_in_registry(&mut installs, registry.as_ref(), &|p| p.to_string());
+                        collector::evidence::score_installations(&mut installs);
+                        print_json(&serde_json::json!(installs), cli.pretty);
+                    }
+                    Err(e) => {
+                        print_json(&serde_json::json!({ "error": e.to_string() }), cli.pretty);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        return;
+    }
+    
     #[cfg(not(windows))]
-    println!("Note: Running in cross-platform compatibility mode. Full functionality only available on Windows.");
+    if cli.offline_windows.is_none() {
+        eprintln!("Note: Running in cross-platform compatibility mode. Full functionality only available on Windows.");
+    }
     
     // Initialize collector with CLI options
     let mut collector = Collector::new(cli.timeout_ms);
@@ -41,8 +94,73 @@ fn main() {
         collector.set_tally_http_endpoint(host_port);
     }
     
-    // Collect system information
-    let mut result = collector.collect();
+    if cli.tally_discover {
+        collector.enable_tally_port_discovery(cli.tally_ports);
+    }
+    
+    if cli.tally_stats {
+        collector.enable_tally_company_stats();
+    }
+    
+    if let Some(dsn) = cli.tally_odbc_dsn {
+        collector.set_tally_odbc_dsn(dsn);
+    }
+    
+    if let Some(driver) = cli.tally_odbc_driver {
+        collector.set_tally_odbc_driver(driver);
+    }
+    
+    if cli.mask_serials {
+        collector.mask_license_serials();
+    }
+    
+    collector.set_backup_max_age_days(cli.backup_max_age_days);
+    
+    // Rule files are checked before anything is collected
+    let mut app_rules = collector::apps::rules::builtin_rules().to_vec();
+    for path in &cli.app_rules {
+        match collector::apps::rules::load_rules(path) {
+            Ok(rules) => app_rules = collector::apps::rules::merge_rules(app_rules, rules),
+            Err(e) => {
+                print_json(&serde_json::json!({ "error": e.to_string() }), cli.pretty);
+                std::process::exit(1);
+            }
+        }
+    }
+    collector.set_app_rules(app_rules.clone());
+    let collector = std::sync::Arc::new(collector);
+    
+    // The server answers from the start and reports itself ready once the
+    // first collection below is published
+    #[cfg(feature = "http")]
+    let server = match cli.http {
+        Some(http_port) => match collector::http::spawn_server(http_port, collector.clone(), cli.schema_version) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                print_json(&serde_json::json!({ "error": e }), cli.pretty);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    
+    // Collect system information, from a mounted volume's hives when asked to
+    let mut result = match &cli.offline_windows {
+        Some(root) => match collector::offline::collect_offline(root, !cli.no_tally, cli.mask_serials, cli.backup_max_age_days, &app_rules) {
+            Ok(system_info) => serde_json::to_value(system_info).unwrap_or_else(|_| serde_json::json!({})),
+            Err(e) => {
+                print_json(&serde_json::json!({ "error": e }), cli.pretty);
+                std::process::exit(1);
+            }
+        },
+        None => collector.collect(),
+    };
+    
+    // Warnings count for --check even when --select leaves them out
+    let warnings: Vec<String> = result["warnings"]
+        .as_array()
+        .map(|warnings| warnings.iter().filter_map(|w| w["message"].as_str().map(str::to_string)).collect())
+        .unwrap_or_default();
     
     // Filter fields if --select is specified
     if let Some(fields) = cli.select {
@@ -53,32 +171,149 @@ fn main() {
     let duration_ms = start_time.elapsed().as_millis() as u64;
     collector.add_metadata(&mut result, duration_ms);
     
+    // Down-convert for consumers pinned to an older layout
+    if cli.schema_version != schema::SCHEMA_VERSION {
+        result = schema::convert_to_version(result, cli.schema_version);
+    }
+    
     // Output the result
-    if cli.pretty {
-        println!("{}", serde_json::to_string_pretty(&result).unwrap());
-    } else {
-        println!("{}", serde_json::to_string(&result).unwrap());
+    print_json(&result, cli.pretty);
+    
+    if cli.check && !warnings.is_empty() {
+        for warning in &warnings {
+            eprintln!("warning: {}", warning);
+        }
+        std::process::exit(2);
     }
     
-    // Start HTTP server if requested
+    // Keep serving if requested
     if let Some(http_port) = cli.http {
         #[cfg(feature = "http")]
-        {
-            #[cfg(windows)]
-            {
-                println!("Starting HTTP server on port {}...", http_port);
-                collector::http::start_server(http_port, result);
-            }
-            
-            #[cfg(not(windows))]
-            {
-                println!("HTTP server not available in cross-platform mode.");
+        if let Some(server) = server {
+            println!("Serving HTTP on port {}...", http_port);
+            server.publish(result);
+            if server.wait().is_err() {
+                std::process::exit(1);
             }
         }
         
         #[cfg(not(feature = "http"))]
         {
+            let _ = http_port;
             eprintln!("HTTP server feature not enabled. Recompile with --features http");
         }
     }
 }
+
+/// Writes a JSON document to stdout, optionally pretty-printed
+fn print_json(value: &serde_json::Value, pretty: bool) {
+    if pretty {
+        println!("{}", serde_json::to_string_pretty(value).unwrap());
+    } else {
+        println!("{}", serde_json::to_string(value).unwrap());
+    }
+}
+
+/// Runs the Tally XML product probe against one endpoint for `quicksys tally probe`
+#[cfg(feature = "tally-xml")]
+fn probe_tally_endpoint(endpoint: &str, timeout: std::time::Duration, mask_serials: bool) -> (serde_json::Value, bool) {
+    use collector::tally::companies::fetch_companies;
+    use collector::tally::license::fetch_license;
+    use collector::tally::tdl::{add_tdl, fetch_loaded_tdls};
+    
+    match collector::tally::check_tally_http(endpoint, timeout) {
+        Ok(info) => {
+            // Company listing is best-effort; report why it failed instead of failing the probe
+            let companies = match fetch_companies(endpoint, timeout) {
+                Ok(companies) => serde_json::json!(companies),
+                Err(e) => serde_json::json!({"error": e.to_string()}),
+            };
+            let license = match fetch_license(endpoint, timeout) {
+                Ok(mut license) => {
+                    if mask_serials {
+                        license.serial_number = license.serial_number.map(utils::mask_identifier);
+                    }
+                    serde_json::json!(license)
+                }
+                Err(e) => serde_json::json!({"error": e.to_string()}),
+            };
+            // The gateway is usually local, so the files can be described too
+            let tdls = match fetch_loaded_tdls(endpoint, timeout) {
+                Ok(paths) => {
+                    let mut tdls = Vec::new();
+                    for path in paths {
+                        add_tdl(&mut tdls, &path, std::path::Path::new(&path), "http");
+                    }
+                    serde_json::json!(tdls)
+                }
+                Err(e) => serde_json::json!({"error": e.to_string()}),
+            };
+            
+            (serde_json::json!({
+                "endpoint": endpoint,
+                "reachable": true,
+                "variant": info.variant,
+                "version": info.version,
+                "edition": info.edition,
+                "companies": companies,
+                "license": license,
+                "tdls": tdls,
+            }), true)
+        }
+        Err(e) => (serde_json::json!({
+            "endpoint": endpoint,
+            "reachable": false,
+            "error": e.to_string(),
+        }), false),
+    }
+}
+
+#[cfg(not(feature = "tally-xml"))]
+fn probe_tally_endpoint(endpoint: &str, _timeout: std::time::Duration, _mask_serials: bool) -> (serde_json::Value, bool) {
+    (serde_json::json!({
+        "endpoint": endpoint,
+        "reachable": false,
+        "error": "Tally XML support not enabled. Recompile with --features tally-xml",
+    }), false)
+}
+
+/// Exports a report for `quicksys tally query` and prints it as JSON, or as
+/// Tally's XML with `raw`; returns false when the export failed
+#[cfg(feature = "tally-xml")]
+fn query_tally_report(endpoint: &str, query: ReportQuery, raw: bool, timeout: std::time::Duration, pretty: bool) -> bool {
+    use collector::tally::query::run_report_query;
+    use collector::tally::xml_api::xml_to_json;
+    
+    let fail = |error: String| {
+        print_json(&serde_json::json!({ "endpoint": endpoint, "report": query.report, "error": error }), pretty);
+        false
+    };
+    
+    let xml = match run_report_query(endpoint, &query, timeout) {
+        Ok(xml) => xml,
+        Err(e) => return fail(e.to_string()),
+    };
+    if raw {
+        println!("{}", xml);
+        return true;
+    }
+    match xml_to_json(&xml) {
+        Ok(document) => {
+            print_json(&document, pretty);
+            true
+        }
+        Err(e) => fail(format!("response is not XML: {}", e)),
+    }
+}
+
+#[cfg(not(feature = "tally-xml"))]
+fn query_tally_report(endpoint: &str, query: ReportQuery, _raw: bool, _timeout: std::time::Duration, pretty: bool) -> bool {
+    // Only the export envelope reads the company and period
+    let _ = (query.company, query.from, query.to);
+    print_json(&serde_json::json!({
+        "endpoint": endpoint,
+        "report": query.report,
+        "error": "Tally XML support not enabled. Recompile with --features tally-xml",
+    }), pretty);
+    false
+}
@@ -1,100 +1,48 @@
-//! Mock collector implementation for non-Windows platforms
-//! Provides stub implementations that return placeholder data
+//! Placeholder hardware data for non-Windows platforms, where the OS,
+//! device, CPU, memory, disk and network probes are not implemented
 
-use serde_json::{Value, json};
-use std::time::Duration;
+use crate::models::{CpuInfo, DeviceInfo, DiskInfo, MemoryInfo, NetworkInfo, OsInfo, SystemInfo};
 
-pub struct Collector {
-    timeout: Duration,
-    tally_enabled: bool,
-    tally_http_endpoint: Option<String>,
-}
-
-impl Collector {
-    pub fn new(timeout_ms: u64) -> Self {
-        Self {
-            timeout: Duration::from_millis(timeout_ms),
-            tally_enabled: true,
-            tally_http_endpoint: Some("127.0.0.1:9000".to_string()),
-        }
-    }
-    
-    pub fn disable_tally_detection(&mut self) {
-        self.tally_enabled = false;
-    }
-    
-    pub fn set_tally_http_endpoint(&mut self, endpoint: String) {
-        self.tally_http_endpoint = Some(endpoint);
+/// Mock system information, built from the same models as the Windows
+/// collector so both stay in sync
+pub fn placeholder_system_info() -> SystemInfo {
+    SystemInfo {
+        os: Some(OsInfo {
+            family: "Mock OS".to_string(),
+            edition: Some("Development".to_string()),
+            version: Some("1.0".to_string()),
+            build: Some("dev".to_string()),
+            arch: Some("x86_64".to_string()),
+            product_id: None,
+        }),
+        device: Some(DeviceInfo {
+            hostname: Some("mock-device".to_string()),
+            manufacturer: Some("Mock Manufacturer".to_string()),
+            model: Some("Development Model".to_string()),
+            uuid: Some("00000000-0000-0000-0000-000000000000".to_string()),
+        }),
+        cpu: Some(CpuInfo {
+            name: Some("Mock CPU".to_string()),
+            physical_cores: Some(4),
+            logical_cores: Some(8),
+            max_frequency_mhz: Some(3000),
+        }),
+        memory: Some(MemoryInfo {
+            installed_mb: Some(16384),
+            available_mb: Some(8192),
+        }),
+        disks: Some(vec![DiskInfo {
+            drive_letter: Some("C:".to_string()),
+            fs_type: Some("NTFS".to_string()),
+            total_gb: Some(500.0),
+            free_gb: Some(250.0),
+        }]),
+        network: Some(vec![NetworkInfo {
+            interface_name: Some("Mock Ethernet".to_string()),
+            mac_address: Some("00:00:00:00:00:00".to_string()),
+            ipv4_addresses: Some(vec!["192.168.1.100".to_string()]),
+            ipv6_addresses: Some(vec!["fe80::0000:0000:0000:0000".to_string()]),
+        }]),
+        ..Default::default()
     }
-    
-    pub fn collect(&self) -> Value {
-        // Return mock data for non-Windows platforms
-        json!({
-            "os": {
-                "family": "Mock OS",
-                "edition": "Development",
-                "version": "1.0",
-                "build": "dev",
-                "arch": "x86_64"
-            },
-            "device": {
-                "hostname": "mock-device",
-                "manufacturer": "Mock Manufacturer",
-                "model": "Development Model",
-                "uuid": "00000000-0000-0000-0000-000000000000"
-            },
-            "cpu": {
-                "name": "Mock CPU",
-                "physical_cores": 4,
-                "logical_cores": 8,
-                "max_frequency_mhz": 3000
-            },
-            "memory": {
-                "installed_mb": 16384,
-                "available_mb": 8192
-            },
-            "disks": [
-                {
-                    "drive_letter": "C:",
-                    "fs_type": "NTFS",
-                    "total_gb": 500.0,
-                    "free_gb": 250.0
-                }
-            ],
-            "network": [
-                {
-                    "interface_name": "Mock Ethernet",
-                    "mac_address": "00:00:00:00:00:00",
-                    "ipv4_addresses": ["192.168.1.100"],
-                    "ipv6_addresses": ["fe80::0000:0000:0000:0000"]
-                }
-            ],
-            "apps": {
-                "tally": {
-                    "installed": self.tally_enabled,
-                    "variant": "TallyPrime",
-                    "version": "3.0.1",
-                    "detection_source": "mock"
-                }
-            }
-        })
-    }
-    
-    pub fn filter_fields(&self, value: Value, selector: String) -> Value {
-        // Use the real implementation from utils
-        let fields = crate::utils::parse_field_selector(&selector);
-        crate::utils::filter_json_fields(value, fields)
-    }
-    
-    pub fn add_metadata(&self, value: &mut Value, duration_ms: u64) {
-        if let Value::Object(obj) = value {
-            let collector_info = json!({
-                "name": "QuickSys",
-                "version": env!("CARGO_PKG_VERSION"),
-                "duration_ms": duration_ms
-            });
-            
-            obj.insert("collector".to_string(), collector_info);
-        }
-    }
-}
\ No newline at end of file
+}
@@ -1,6 +1,9 @@
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
+use schemars::JsonSchema;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Top-level document returned by the CLI and `/api/info`
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct SystemInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub collector: Option<CollectorInfo>,
@@ -25,17 +28,37 @@ pub struct SystemInfo {
     
     #[serde(skip_serializing_if = "Option::is_none")]
     pub apps: Option<AppsInfo>,
+    
+    /// Conditions that need attention; `--check` exits non-zero when any are present
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub warnings: Vec<Warning>,
+}
+
+/// A condition that needs attention, such as a stale backup
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct Warning {
+    /// Stable identifier, e.g. "tally_backup_stale"
+    pub code: String,
+    pub message: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Metadata about the collector run that produced the document
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct CollectorInfo {
+    /// Collector name, always "QuickSys"
     pub name: String,
+    /// Collector version
     pub version: String,
+    /// Layout version of this document; see `quicksys --schema-version`
+    pub schema_version: u32,
+    /// Wall-clock time spent collecting, in milliseconds
     pub duration_ms: u64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Operating system details
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct OsInfo {
+    /// OS family, e.g. "Windows"
     pub family: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub edition: Option<String>,
@@ -45,11 +68,13 @@ pub struct OsInfo {
     pub build: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub arch: Option<String>,
+    /// Truncated product ID; only the first characters are reported
     #[serde(skip_serializing_if = "Option::is_none")]
     pub product_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Machine identity
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct DeviceInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub hostname: Option<String>,
@@ -61,7 +86,8 @@ pub struct DeviceInfo {
     pub uuid: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Processor details
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct CpuInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -69,11 +95,13 @@ pub struct CpuInfo {
     pub physical_cores: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub logical_cores: Option<u32>,
+    /// Maximum clock speed in MHz
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_frequency_mhz: Option<u32>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Physical memory, in megabytes
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct MemoryInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub installed_mb: Option<u64>,
@@ -81,22 +109,28 @@ pub struct MemoryInfo {
     pub available_mb: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// A fixed disk volume
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct DiskInfo {
+    /// Drive letter including the colon, e.g. "C:"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub drive_letter: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fs_type: Option<String>,
+    /// Volume size in gigabytes
     #[serde(skip_serializing_if = "Option::is_none")]
     pub total_gb: Option<f64>,
+    /// Space available to the current user in gigabytes
     #[serde(skip_serializing_if = "Option::is_none")]
     pub free_gb: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// An active, non-loopback network interface
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct NetworkInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub interface_name: Option<String>,
+    /// Colon-separated uppercase hex, e.g. "00:1A:2B:3C:4D:5E"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mac_address: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -105,15 +139,68 @@ pub struct NetworkInfo {
     pub ipv6_addresses: Option<Vec<String>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Detected applications
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct AppsInfo {
+    /// One entry per Tally installation, deduplicated by install path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tally: Option<Vec<TallyInfo>>,
+    /// Other applications found by the detection rules, one entry per rule
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub detected: Vec<DetectedApp>,
+    /// Software inventory from the package managers, Flatpak and Snap (Linux)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub packages: Vec<InstalledPackage>,
+}
+
+/// An application recognised by a detection rule
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct DetectedApp {
+    /// Rule identifier, e.g. "busy" or "hp-printer"
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    /// "accounting", "antivirus", "printer", ... as the rule files it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// First version any matcher found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// As the application sees it, e.g. `C:\BusyWin`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub install_path: Option<String>,
+    /// Every matcher that fired: "registry_uninstall", "registry_vendor", "file", "file_version", "desktop_file" or "package"
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub detection_sources: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub evidence: Vec<Evidence>,
+    /// 0-100, weighted like Tally's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<u8>,
+}
+
+/// A package installed on the host
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+pub struct InstalledPackage {
+    pub name: String,
+    /// As the package manager writes it, with epoch and release, e.g. "1:2.39.2-1" or "3.1.2-3.fc39"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// e.g. "amd64", "x86_64", "noarch" or "all"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub architecture: Option<String>,
+    /// "dpkg", "rpm", "apk", "flatpak" or "snap"
+    pub source: String,
+    /// When it was installed, where the package manager keeps track
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tally: Option<TallyInfo>,
+    pub install_date: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// A Tally installation
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
 pub struct TallyInfo {
-    pub installed: bool,
+    /// "TallyPrime", "TallyERP9" or "Tally"
     #[serde(skip_serializing_if = "Option::is_none")]
     pub variant: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -122,6 +209,267 @@ pub struct TallyInfo {
     pub edition: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub install_path: Option<String>,
+    /// Wine prefix the installation lives in, when found on a Linux host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wine_prefix: Option<String>,
+    /// Every method that saw this installation: "registry_uninstall", "registry_vendor", "file_version", "process", "http" or "odbc"
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub detection_sources: Vec<String>,
+    /// Every signal behind the detection: registry keys, files, processes and live answers
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub evidence: Vec<Evidence>,
+    /// 0-100, from the evidence: registry entries alone stay low, files on
+    /// disk raise it and a running instance raises it most
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<u8>,
+    /// XML/ODBC gateway (host:port) that answered the probe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    /// Companies loaded in the running instance, from the XML API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub companies: Option<Vec<TallyCompany>>,
+    /// What Tally's ODBC server answered, when built with ODBC support
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub odbc: Option<TallyOdbc>,
+    /// Settings read from tally.ini in the install directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config: Option<TallyConfig>,
+    /// Company folders found in the data directory, whether or not Tally is running
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_folders: Option<Vec<TallyDataFolder>>,
+    /// License details, from the XML API or a license file in the install directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<TallyLicense>,
+    /// Newest backup archive of each company in `data_folders`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backups: Option<Vec<TallyBackup>>,
+    /// TDL/TCP customisation files loaded at startup or by the running instance
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tdls: Option<Vec<TallyTdl>>,
+    /// Tally processes started from this installation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub running: Option<Vec<TallyProcess>>,
+}
+
+/// One signal that an installation exists
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct Evidence {
+    /// A detection source, or for Tally "executable" or "tally_ini" for files found in the install directory
+    pub source: String,
+    /// Registry key, file path, desktop file, package, process, gateway answer or ODBC connection string
+    pub detail: String,
+}
+
+/// Result of connecting to Tally's ODBC server
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyOdbc {
+    /// Connection string tried last, e.g. "DSN=TallyODBC64_9000" or
+    /// "DRIVER={Tally ODBC Driver64};SERVER=127.0.0.1;PORT=9000"
+    pub connection: String,
+    pub connected: bool,
+    /// Companies from the ODBC Company table
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub companies: Vec<TallyCompany>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Tally DSNs known to the ODBC driver manager
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub data_sources: Vec<OdbcDataSource>,
+}
+
+/// An ODBC data source name and the driver behind it
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
+pub struct OdbcDataSource {
+    pub name: String,
+    pub driver: String,
+}
+
+/// The newest backup of a company
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyBackup {
+    /// "unknown" for the newest archive outside any company folder when the
+    /// installation has several companies
+    pub company_number: String,
+    /// Backup archive; absent when no backup of the company was found
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// ISO 8601 UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// Whole days since the archive was written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_days: Option<u64>,
+    /// Older than `--backup-max-age-days`, or missing
+    pub stale: bool,
+}
+
+/// A TDL/TCP customisation file
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyTdl {
+    /// Path as Tally sees it
+    pub path: String,
+    pub exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_bytes: Option<u64>,
+    /// SHA-256 of the file contents, lowercase hex
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// ISO 8601 UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+    /// "tally_ini" (loaded at startup) and "http" (loaded in the running instance)
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sources: Vec<String>,
+}
+
+/// A running Tally process
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyProcess {
+    pub pid: u32,
+    /// ISO 8601 UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_time: Option<String>,
+    /// Resident set size (working set on Windows)
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub detection_source: Option<String>,
-}
\ No newline at end of file
+    pub memory_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command_line: Option<String>,
+    /// TCP ports the process listens on, such as the XML/ODBC gateway
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub listening_ports: Vec<u16>,
+}
+
+/// License of a Tally installation
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyLicense {
+    /// Shortened like `os.product_id` when `--mask-serials` is given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<String>,
+    /// "Silver" (single user), "Gold" (multi-user) or "Educational"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub multi_user: Option<bool>,
+    /// End of the Tally Software Services (TSS) subscription, ISO 8601 date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tss_expiry: Option<String>,
+    /// Whether the installation runs licensed rather than in educational mode
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub activated: Option<bool>,
+    /// "http" or "license_file"
+    pub source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_file: Option<String>,
+}
+
+/// Settings from tally.ini
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ini_path: Option<String>,
+    /// Directory holding the company data folders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_directory: Option<String>,
+    /// Port of the XML/ODBC server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub odbc_server: Option<bool>,
+    /// "Server", "Client", "Both" or "None"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_server: Option<String>,
+    /// Whether the companies listed in `load_companies` open at startup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_companies: Option<bool>,
+    /// Destination of Tally's Backup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backup_directory: Option<String>,
+    /// Company numbers or paths loaded at startup
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub load_companies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_tdl: Option<bool>,
+    /// TDL/TCP files loaded at startup
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub tdl_files: Vec<String>,
+}
+
+/// A company data folder found on disk
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyDataFolder {
+    /// Folder name, e.g. "10000"
+    pub company_number: String,
+    pub path: String,
+    /// "TallyPrime" for .1800 data files, "TallyERP9" for .900
+    pub data_format: String,
+    pub company_file_size_bytes: u64,
+    /// Number of Tally data files in the folder
+    pub file_count: u64,
+    pub total_size_bytes: u64,
+    /// Newest modification time among the data files, ISO 8601 UTC
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+/// A company reported by the Tally XML API
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyCompany {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
+    /// Numeric folder name of the company under the data directory, e.g. "10000"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_number: Option<String>,
+    /// Start of the books, ISO 8601 date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub books_from: Option<String>,
+    /// Date of the most recent voucher, ISO 8601 date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_voucher_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_path: Option<String>,
+    /// Master and voucher counts, only collected with `--tally-stats`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<TallyCompanyStats>,
+}
+
+/// Size of a company's books, counted by the running Tally instance
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct TallyCompanyStats {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ledgers: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stock_items: Option<u64>,
+    /// Vouchers of all types
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vouchers: Option<u64>,
+    /// Voucher count per voucher type, e.g. "Sales" or "Payment"
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub vouchers_by_type: BTreeMap<String, u64>,
+    /// Date of the most recent voucher, ISO 8601 date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_voucher_date: Option<String>,
+}
+
+/// Version resource of a PE executable
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default)]
+pub struct FileVersionInfo {
+    /// Numeric file version from VS_FIXEDFILEINFO, as major.minor.build.revision
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_file_version: Option<String>,
+    /// Numeric product version from VS_FIXEDFILEINFO
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fixed_product_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub company_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_description: Option<String>,
+}
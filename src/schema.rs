@@ -0,0 +1,266 @@
+//! JSON Schema and OpenAPI descriptions of the collector output
+
+use schemars::gen::SchemaSettings;
+use schemars::schema_for;
+use serde_json::{Map, Value, json};
+
+use crate::models::SystemInfo;
+
+/// Layout version of the output document reported in `collector.schema_version`.
+/// Adding optional fields keeps the version; renaming, removing or retyping a
+/// field bumps it and needs a down-converter in `convert_to_version`.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Oldest layout that can still be requested with `--schema-version`
+pub const MIN_SCHEMA_VERSION: u32 = 1;
+
+/// Returns the JSON Schema (draft 7) for the top-level output document
+pub fn system_info_schema() -> Value {
+    serde_json::to_value(schema_for!(SystemInfo)).unwrap_or_else(|_| json!({}))
+}
+
+/// Returns an OpenAPI 3.0 document describing every HTTP server route
+pub fn openapi_document() -> Value {
+    // Generate the model schemas with references pointing into `components`
+    let mut generator = SchemaSettings::openapi3().into_generator();
+    let system_info_ref = generator.subschema_for::<SystemInfo>();
+    let components = generator.take_definitions();
+
+    let stream_parameters = json!([
+        {
+            "name": "select",
+            "in": "query",
+            "description": "Sections to include, same syntax as --select (e.g. memory,disks)",
+            "schema": {"type": "string"}
+        },
+        {
+            "name": "interval_ms",
+            "in": "query",
+            "description": "Push interval in milliseconds (raised to 250 when shorter, refused when over an hour)",
+            "schema": {"type": "integer", "default": 1000, "maximum": 3600000}
+        },
+        {
+            "name": "mode",
+            "in": "query",
+            "description": "`patch` sends JSON Patch deltas after the first snapshot, `snapshot` sends the full document every interval",
+            "schema": {"type": "string", "enum": ["patch", "snapshot"], "default": "patch"}
+        }
+    ]);
+
+    // Stream routes refuse intervals they would never tick at
+    let interval_too_long = json!({
+        "description": "interval_ms is longer than an hour",
+        "content": {"text/plain": {"schema": {"type": "string"}}}
+    });
+
+    // Snapshot routes honour If-None-Match / If-Modified-Since
+    let not_modified = json!({
+        "description": "The snapshot matches the ETag in If-None-Match, or has not changed since If-Modified-Since"
+    });
+    // ...and have nothing to serve before the first collection is published
+    let collecting = json!({
+        "description": "The first collection is still running"
+    });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "QuickSys API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Local HTTP API exposing the system information collected by QuickSys"
+        },
+        "paths": {
+            "/": {
+                "get": {
+                    "summary": "Server banner",
+                    "responses": {
+                        "200": {
+                            "description": "Plain-text banner",
+                            "content": {"text/plain": {"schema": {"type": "string"}}}
+                        }
+                    }
+                }
+            },
+            "/api/info": {
+                "get": {
+                    "summary": "Full system information document",
+                    "responses": {
+                        "200": {
+                            "description": "Snapshot taken when the server started",
+                            "content": {"application/json": {"schema": system_info_ref}}
+                        },
+                        "304": not_modified,
+                        "503": collecting
+                    }
+                }
+            },
+            "/api/info/{path}": {
+                "get": {
+                    "summary": "Subtree of the system information document",
+                    "parameters": [
+                        {
+                            "name": "path",
+                            "in": "path",
+                            "required": true,
+                            "description": "Dot-separated path into the document (e.g. apps.tally)",
+                            "schema": {"type": "string"}
+                        }
+                    ],
+                    "responses": {
+                        "200": {
+                            "description": "Value found at the path, or an empty object when the path does not exist",
+                            "content": {"application/json": {"schema": {}}}
+                        },
+                        "304": not_modified,
+                        "503": collecting
+                    }
+                }
+            },
+            "/api/stream": {
+                "get": {
+                    "summary": "Server-Sent Events stream of re-collected data",
+                    "parameters": stream_parameters,
+                    "responses": {
+                        "200": {
+                            "description": "`snapshot` events carry a full document, `patch` events carry an RFC 6902 JSON Patch array",
+                            "content": {"text/event-stream": {"schema": {"type": "string"}}}
+                        },
+                        "400": interval_too_long
+                    }
+                }
+            },
+            "/api/ws": {
+                "get": {
+                    "summary": "WebSocket stream of re-collected data",
+                    "parameters": stream_parameters,
+                    "responses": {
+                        "101": {
+                            "description": "Switching protocols; messages are {\"type\": \"snapshot\", \"data\": ...} or {\"type\": \"patch\", \"ops\": [...]}"
+                        },
+                        "400": interval_too_long
+                    }
+                }
+            },
+            "/healthz": {
+                "get": {
+                    "summary": "Liveness probe",
+                    "responses": {
+                        "200": {
+                            "description": "The server is running",
+                            "content": {"text/plain": {"schema": {"type": "string"}}}
+                        }
+                    }
+                }
+            },
+            "/readyz": {
+                "get": {
+                    "summary": "Readiness probe",
+                    "responses": {
+                        "200": {
+                            "description": "The first collection has finished",
+                            "content": {"text/plain": {"schema": {"type": "string"}}}
+                        },
+                        "503": collecting
+                    }
+                }
+            },
+            "/api/diagnostics": {
+                "get": {
+                    "summary": "Self-diagnostics",
+                    "responses": {
+                        "200": {
+                            "description": "Build features, probe timings, recent errors, uptime and per-route request counters",
+                            "content": {"application/json": {"schema": {"type": "object"}}}
+                        }
+                    }
+                }
+            },
+            "/api/openapi.json": {
+                "get": {
+                    "summary": "This document",
+                    "responses": {
+                        "200": {
+                            "description": "OpenAPI 3.0 document",
+                            "content": {"application/json": {"schema": {"type": "object"}}}
+                        }
+                    }
+                }
+            }
+        },
+        "components": {
+            "schemas": components
+        }
+    })
+}
+
+/// Rewrites a current-layout document into an older published layout.
+/// Each step converts one version down, so converters only ever need to know
+/// about the change that introduced their version.
+pub fn convert_to_version(mut value: Value, target: u32) -> Value {
+    let mut version = SCHEMA_VERSION;
+
+    while version > target.max(MIN_SCHEMA_VERSION) {
+        match version {
+            3 => downgrade_v3_to_v2(&mut value),
+            2 => downgrade_v2_to_v1(&mut value),
+            _ => unreachable!("missing down-converter for schema version {}", version),
+        }
+        version -= 1;
+    }
+
+    // Layouts from v2 onwards report the version they were converted to
+    if target >= 2 {
+        if let Some(collector) = value.get_mut("collector").and_then(Value::as_object_mut) {
+            collector.insert("schema_version".to_string(), json!(target));
+        }
+    }
+
+    value
+}
+
+/// The fields of v2's single Tally object
+const V2_TALLY_FIELDS: &[&str] = &["installed", "variant", "version", "edition", "install_path", "detection_source"];
+
+/// v2 reported a single Tally installation with `installed` and one
+/// `detection_source`; the first installation in the list stands in for it,
+/// without the details only v3 reports
+fn downgrade_v3_to_v2(value: &mut Value) {
+    let Some(tally) = value.pointer_mut("/apps/tally") else {
+        return;
+    };
+    let Some(installs) = tally.as_array_mut().map(std::mem::take) else {
+        return;
+    };
+
+    let installed = !installs.is_empty();
+    let mut primary = match installs.into_iter().next() {
+        Some(Value::Object(install)) => install,
+        _ => Map::new(),
+    };
+
+    // v2 kept the method that last refined the values, and live probes ran last
+    let sources: Vec<Value> = match primary.remove("detection_sources") {
+        Some(Value::Array(sources)) => sources,
+        _ => Vec::new(),
+    };
+    let source = ["odbc", "http"]
+        .iter()
+        .map(|live| json!(live))
+        .find(|live| sources.contains(live))
+        .or_else(|| sources.first().cloned());
+
+    primary.insert("installed".to_string(), json!(installed));
+    if let Some(source) = source {
+        primary.insert("detection_source".to_string(), source);
+    }
+    primary.retain(|key, _| V2_TALLY_FIELDS.contains(&key.as_str()));
+
+    *tally = Value::Object(primary);
+}
+
+/// v1 (QuickSys 1.0.0) predates `collector.schema_version`
+fn downgrade_v2_to_v1(value: &mut Value) {
+    if let Some(collector) = value.get_mut("collector").and_then(Value::as_object_mut) {
+        collector.remove("schema_version");
+    }
+}
@@ -1,5 +1,6 @@
 use std::time::Duration;
 use serde_json::Value;
+use sha2::{Digest, Sha256};
 
 /// Converts a string with comma-separated field names into a vector of field paths
 pub fn parse_field_selector(selector: &str) -> Vec<Vec<String>> {
@@ -65,9 +66,8 @@ fn insert_at_path(root: &mut Value, path: &[String], value: Value) {
     let mut current = root;
     
     // Navigate to the parent of the final key
-    for i in 0..path.len() - 1 {
-        let key = &path[i];
-        
+    for key in &path[..path.len() - 1] {
+
         // Ensure the current path exists and is an object
         if !current.is_object() {
             *current = Value::Object(serde_json::Map::new());
@@ -90,4 +90,238 @@ fn insert_at_path(root: &mut Value, path: &[String], value: Value) {
         
         current.as_object_mut().unwrap().insert(last_key.clone(), value);
     }
-}
\ No newline at end of file
+}
+
+/// Computes an RFC 6902 JSON Patch that turns `old` into `new`
+#[cfg(feature = "http")]
+pub fn json_patch_diff(old: &Value, new: &Value) -> Vec<Value> {
+    let mut ops = Vec::new();
+    diff_values(old, new, String::new(), &mut ops);
+    ops
+}
+
+/// Helper function to walk two JSON values and record the operations between them
+#[cfg(feature = "http")]
+fn diff_values(old: &Value, new: &Value, pointer: String, ops: &mut Vec<Value>) {
+    match (old, new) {
+        (Value::Object(old_obj), Value::Object(new_obj)) => {
+            for (key, old_val) in old_obj {
+                let child = format!("{}/{}", pointer, escape_pointer_token(key));
+                match new_obj.get(key) {
+                    Some(new_val) => diff_values(old_val, new_val, child, ops),
+                    None => ops.push(serde_json::json!({"op": "remove", "path": child})),
+                }
+            }
+            
+            for (key, new_val) in new_obj {
+                if !old_obj.contains_key(key) {
+                    let child = format!("{}/{}", pointer, escape_pointer_token(key));
+                    ops.push(serde_json::json!({"op": "add", "path": child, "value": new_val}));
+                }
+            }
+        }
+        _ if old != new => {
+            // Arrays and scalars are replaced wholesale; element-level diffs
+            // are not worth the extra bytes for lists this small
+            ops.push(serde_json::json!({"op": "replace", "path": pointer, "value": new}));
+        }
+        _ => {}
+    }
+}
+
+/// Escapes a key for use as a JSON Pointer reference token (RFC 6901)
+#[cfg(feature = "http")]
+fn escape_pointer_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Returns the lowercase hex SHA-256 digest of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Formats seconds since the Unix epoch as an ISO 8601 UTC timestamp
+pub fn format_unix_time(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let seconds_of_day = secs % 86_400;
+
+    // Civil-from-days conversion (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        (seconds_of_day % 3600) / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Formats a `SystemTime` as an ISO 8601 UTC timestamp
+pub fn format_system_time(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format_unix_time(secs)
+}
+
+/// Converts Tally dates (`20240401`, `1-Apr-2024` or `1-Apr-24`) to ISO 8601,
+/// rejecting days the month does not have
+pub fn parse_tally_date(value: &str) -> Option<String> {
+    let value = value.trim();
+
+    let (year, month, day) = if value.len() == 8 && value.bytes().all(|b| b.is_ascii_digit()) {
+        (value[0..4].parse().ok()?, value[4..6].parse().ok()?, value[6..8].parse().ok()?)
+    } else {
+        let mut parts = value.split('-');
+        let day: u32 = parts.next()?.trim().parse().ok()?;
+        let month = match parts.next()?.trim().to_ascii_lowercase().as_str() {
+            "jan" => 1, "feb" => 2, "mar" => 3, "apr" => 4, "may" => 5, "jun" => 6,
+            "jul" => 7, "aug" => 8, "sep" => 9, "oct" => 10, "nov" => 11, "dec" => 12,
+            _ => return None,
+        };
+        let year: u32 = parts.next()?.trim().parse().ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        // Two-digit years are always this century in Tally's date format
+        (if year < 100 { 2000 + year } else { year }, month, day)
+    };
+
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
+}
+
+/// Days in `month` (1-12) of `year` in the Gregorian calendar; 0 for any other month
+fn days_in_month(year: u32, month: u32) -> u32 {
+    // Every fourth year, except centuries not divisible by 400
+    let leap = matches!((year % 4, year % 100, year % 400), (0, 1.., _) | (_, _, 0));
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+/// Matches `text` against a pattern where `*` stands for any run of
+/// characters and `?` for one character
+pub fn wildcard_match(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    let fold = |s: &str| -> Vec<char> {
+        if ignore_case {
+            s.to_lowercase().chars().collect()
+        } else {
+            s.chars().collect()
+        }
+    };
+    let (pattern, text) = (fold(pattern), fold(text));
+
+    // Greedy match that backtracks to the last `*` on a mismatch
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Shortens a product ID or license serial to its first characters; the full
+/// value identifies the license
+pub fn mask_identifier(id: String) -> String {
+    if id.len() > 8 {
+        format!("{}...", id.chars().take(5).collect::<String>())
+    } else {
+        id
+    }
+}
+
+#[cfg(all(test, feature = "http"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn identical_documents_need_no_patch() {
+        let doc = json!({"os": {"family": "Windows"}, "disks": [{"total_gb": 500.0}]});
+        assert!(json_patch_diff(&doc, &doc.clone()).is_empty());
+    }
+
+    #[test]
+    fn patch_removes_replaces_then_adds() {
+        let old = json!({"memory": {"available_mb": 8192, "installed_mb": 16384}, "os": {"build": "19045"}});
+        let new = json!({"memory": {"available_mb": 6144, "installed_mb": 16384}, "cpu": {"logical_cores": 8}});
+
+        assert_eq!(
+            json_patch_diff(&old, &new),
+            vec![
+                json!({"op": "replace", "path": "/memory/available_mb", "value": 6144}),
+                json!({"op": "remove", "path": "/os"}),
+                json!({"op": "add", "path": "/cpu", "value": {"logical_cores": 8}}),
+            ]
+        );
+    }
+
+    #[test]
+    fn arrays_and_type_changes_are_replaced_whole() {
+        let old = json!({"disks": [{"free_gb": 250.0}, {"free_gb": 10.0}], "apps": null});
+        let new = json!({"disks": [{"free_gb": 249.5}, {"free_gb": 10.0}], "apps": {"tally": []}});
+
+        assert_eq!(
+            json_patch_diff(&old, &new),
+            vec![
+                json!({"op": "replace", "path": "/apps", "value": {"tally": []}}),
+                json!({"op": "replace", "path": "/disks", "value": [{"free_gb": 249.5}, {"free_gb": 10.0}]}),
+            ]
+        );
+        // A changed root is replaced through the empty pointer
+        assert_eq!(json_patch_diff(&json!(1), &json!(2)), vec![json!({"op": "replace", "path": "", "value": 2})]);
+    }
+
+    #[test]
+    fn pointer_tokens_are_escaped() {
+        assert_eq!(escape_pointer_token("C:/Tally"), "C:~1Tally");
+        assert_eq!(escape_pointer_token("a~b"), "a~0b");
+        // `~` is escaped first so an escaped `/` is not escaped again
+        assert_eq!(escape_pointer_token("~1/"), "~01~1");
+
+        let old = json!({"data/folders": {"m~n": 1}});
+        let new = json!({"data/folders": {"m~n": 2}, "x": {"a/b~": true}});
+        assert_eq!(
+            json_patch_diff(&old, &new),
+            vec![
+                json!({"op": "replace", "path": "/data~1folders/m~0n", "value": 2}),
+                json!({"op": "add", "path": "/x", "value": {"a/b~": true}}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Rule-driven application detection against a fixture host root (dpkg
+//! status, desktop entries, files) and a fixture Wine prefix
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use support::{detect_apps, fixture, run_quicksys_with_env};
+
+fn empty_home() -> PathBuf {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("apps-empty-home");
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn detect(home: &Path, extra_args: &[&str]) -> Vec<Value> {
+    let mut args = vec!["--no-tally"];
+    args.extend_from_slice(extra_args);
+
+    let apps = detect_apps(&args, &[("HOME", home), ("HOST_ROOT", &fixture("apps/root"))]);
+    apps["detected"].as_array().cloned().unwrap_or_default()
+}
+
+fn app<'a>(apps: &'a [Value], id: &str) -> &'a Value {
+    apps.iter().find(|app| app["id"] == id).unwrap_or_else(|| panic!("{} not detected: {:#?}", id, apps))
+}
+
+#[test]
+fn built_in_rules_match_packages_and_desktop_entries() {
+    let apps = detect(&empty_home(), &[]);
+    assert_eq!(apps.len(), 2, "{:#?}", apps);
+
+    let hp = app(&apps, "hp-printer");
+    assert_eq!(hp["name"], "HP printer software");
+    assert_eq!(hp["category"], "printer");
+    assert_eq!(hp["version"], "3.22.10+dfsg0-2");
+    assert_eq!(hp["detection_sources"], json!(["desktop_file", "package"]));
+    // The hidden entry counts as removed
+    let evidence = hp["evidence"].as_array().unwrap();
+    assert_eq!(evidence.len(), 2);
+    assert!(evidence[0]["detail"].as_str().unwrap().ends_with("usr/share/applications/hplip.desktop"));
+    assert_eq!(evidence[1]["detail"], "dpkg: hplip 3.22.10+dfsg0-2");
+    assert_eq!(hp["confidence"], 60);
+
+    // Only configuration files are left of printer-driver-escpr
+    let epson = app(&apps, "epson-printer");
+    assert_eq!(epson["evidence"], json!([{ "source": "package", "detail": "dpkg: epson-inkjet-printer-escpr2 1.2.4-1" }]));
+}
+
+#[test]
+fn finds_windows_applications_in_wine_prefixes() {
+    let apps = detect(&fixture("apps/home"), &[]);
+
+    let busy = app(&apps, "busy");
+    assert_eq!(busy["vendor"], "Busy Infotech");
+    assert_eq!(busy["version"], "21.4.3");
+    assert_eq!(busy["install_path"], "C:\\BusyWin");
+    assert_eq!(busy["detection_sources"], json!(["registry_uninstall", "file_version"]));
+    assert_eq!(busy["evidence"][0]["detail"], "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\BUSY 21");
+    assert!(busy["evidence"][1]["detail"].as_str().unwrap().ends_with("/drive_c/BusyWin/busy21.exe"));
+    assert_eq!(busy["confidence"], 50);
+
+    // Vendor keys are looked up in the 32-bit view too
+    let k7 = app(&apps, "k7");
+    assert_eq!(k7["version"], Value::Null);
+    assert_eq!(k7["evidence"][0]["detail"], "HKLM\\SOFTWARE\\WOW6432Node\\K7 Computing\\K7TotalSecurity");
+    assert_eq!(k7["confidence"], 10);
+}
+
+#[test]
+fn rule_files_add_and_replace_apps() {
+    let rules = fixture("apps/rules");
+    let apps = detect(&empty_home(), &["--app-rules", rules.to_str().unwrap()]);
+
+    let acme = app(&apps, "acme-billing");
+    assert_eq!(acme["install_path"], "/opt/acme/bin");
+    assert_eq!(acme["detection_sources"], json!(["file"]));
+    assert_eq!(acme["confidence"], 35);
+
+    // The replacement knows nothing about desktop entries
+    let hp = app(&apps, "hp-printer");
+    assert_eq!(hp["name"], "HP LaserJet tools");
+    assert_eq!(hp["detection_sources"], json!(["package"]));
+}
+
+#[test]
+fn invalid_rule_files_are_rejected() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("app-rules-invalid");
+    fs::create_dir_all(&dir).unwrap();
+
+    let cases = [
+        ("unknown-key.toml", "[[app]]\nid = \"x\"\nname = \"X\"\nregistery = []\n", "unknown field"),
+        ("tally.toml", "[[app]]\nid = \"tally\"\nname = \"My Tally\"\n", "reserved"),
+        ("missing.toml", "", "cannot read"),
+    ];
+    for (name, text, message) in cases {
+        let path = dir.join(name);
+        if !text.is_empty() {
+            fs::write(&path, text).unwrap();
+        }
+        let (report, output) = run_quicksys_with_env(&["--app-rules", path.to_str().unwrap()], &[]);
+        assert_eq!(output.status.code(), Some(1), "{}", name);
+        let error = report["error"].as_str().unwrap_or_default();
+        assert!(error.contains(message), "{}: {}", name, error);
+    }
+}
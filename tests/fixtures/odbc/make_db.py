@@ -0,0 +1,32 @@
+#!/usr/bin/env python3
+"""Regenerates the SQLite stand-in for Tally's ODBC server used by tests/tally_odbc.rs.
+
+Tally exposes each collection as a table whose columns are named after the
+TDL methods ($Name, $GUID, ...); the Company table here is shaped the same
+way, so the SQLite ODBC driver can be registered under a Tally driver name.
+
+    python3 tests/fixtures/odbc/make_db.py
+"""
+
+import os
+import sqlite3
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+PATH = os.path.join(HERE, "tally.db")
+
+COMPANIES = [
+    ("Sharma Traders & Sons", "7f3c2a10-4b9e-4d61-9a2f-0c1d2e3f4a5b", "10000", "2023-04-01", "2024-03-15"),
+    ("Mehta Hardware", "0b6d5c4e-1f2a-4e3b-8c9d-7a6b5c4d3e2f", "10001", "2021-04-01", None),
+]
+
+if os.path.exists(PATH):
+    os.remove(PATH)
+
+db = sqlite3.connect(PATH)
+db.execute(
+    'CREATE TABLE Company ("$Name" TEXT, "$GUID" TEXT, "$CompanyNumber" TEXT, '
+    '"$BooksFrom" DATE, "$LastVoucherDate" DATE)'
+)
+db.executemany("INSERT INTO Company VALUES (?, ?, ?, ?, ?)", COMPANIES)
+db.commit()
+db.close()
@@ -0,0 +1,211 @@
+#!/usr/bin/env python3
+"""Regenerates the registry hives of the mounted-volume fixture used by
+tests/offline_windows.rs.
+
+Writes regf files with the layout Windows uses: a 4 KiB base block with its
+checksum, 4 KiB-aligned hbins, and nk/vk/lh/li/ri cells.
+
+    python3 tests/fixtures/offline/make_hives.py
+"""
+
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+CONFIG = os.path.join(HERE, "volume", "Windows", "System32", "config")
+
+REG_SZ, REG_EXPAND_SZ, REG_DWORD, REG_MULTI_SZ, REG_QWORD = 1, 2, 4, 7, 11
+FILETIME = 133_550_000_000_000_000  # 2024-03-15
+
+
+class Key:
+    def __init__(self, name, values=(), subkeys=(), utf16_name=False, index_root=False):
+        self.name = name
+        self.values = list(values)
+        self.subkeys = list(subkeys)
+        self.utf16_name = utf16_name
+        self.index_root = index_root
+
+
+class HiveWriter:
+    def __init__(self):
+        self.cells = bytearray()
+
+    def alloc(self, data):
+        """Appends an allocated cell and returns its offset relative to the first hbin"""
+        size = len(data) + 4
+        size += -size % 8
+        offset = self._place(size)
+        self.cells[offset:offset + size] = struct.pack("<i", -size) + data + b"\0" * (size - 4 - len(data))
+        return offset
+
+    def _place(self, size):
+        # Cells never cross an hbin boundary; pad the current bin with a free cell
+        position = len(self.cells)
+        bin_start = position - position % 4096
+        if position == bin_start or position + size > bin_start + 4096:
+            if position != bin_start:
+                free = bin_start + 4096 - position
+                self.cells += struct.pack("<i", free) + b"\0" * (free - 4)
+                bin_start += 4096
+            self.cells += b"hbin" + struct.pack("<II", bin_start, 4096) + b"\0" * 8 + struct.pack("<QI", FILETIME, 0)
+        offset = len(self.cells)
+        self.cells += b"\0" * size
+        return offset
+
+    def write_key(self, key, parent):
+        name = key.name.encode("utf-16le") if key.utf16_name else key.name.encode("latin-1")
+        nk = bytearray(0x4C) + name
+        nk[0:2] = b"nk"
+        flags = (0x0004 if parent == -1 else 0) | (0 if key.utf16_name else 0x0020)
+        struct.pack_into("<HQ", nk, 2, flags, FILETIME)
+        struct.pack_into("<I", nk, 0x10, parent & 0xFFFFFFFF)
+        struct.pack_into("<I", nk, 0x2C, 0xFFFFFFFF)  # no security cell in fixtures
+        struct.pack_into("<I", nk, 0x30, 0xFFFFFFFF)
+        struct.pack_into("<HH", nk, 0x48, len(name), 0)
+        offset = self.alloc(bytes(nk))
+
+        children = sorted(key.subkeys, key=lambda k: k.name.upper())
+        child_offsets = [self.write_key(child, offset) for child in children]
+        list_offset = 0xFFFFFFFF
+        if child_offsets:
+            if key.index_root:
+                # Split the children over two li lists under an ri index root
+                half = len(child_offsets) // 2
+                lists = [child_offsets[:half], child_offsets[half:]]
+                li = [self.alloc(b"li" + struct.pack("<H", len(l)) + b"".join(struct.pack("<I", o) for o in l)) for l in lists]
+                list_offset = self.alloc(b"ri" + struct.pack("<H", len(li)) + b"".join(struct.pack("<I", o) for o in li))
+            else:
+                entries = b"".join(struct.pack("<II", o, lh_hash(c.name)) for o, c in zip(child_offsets, children))
+                list_offset = self.alloc(b"lh" + struct.pack("<H", len(children)) + entries)
+
+        value_offsets = [self.write_value(*value) for value in key.values]
+        values_offset = 0xFFFFFFFF
+        if value_offsets:
+            values_offset = self.alloc(b"".join(struct.pack("<I", o) for o in value_offsets))
+
+        struct.pack_into("<I", self.cells, offset + 4 + 0x14, len(children))
+        struct.pack_into("<I", self.cells, offset + 4 + 0x1C, list_offset)
+        struct.pack_into("<I", self.cells, offset + 4 + 0x20, 0xFFFFFFFF)
+        struct.pack_into("<I", self.cells, offset + 4 + 0x24, len(value_offsets))
+        struct.pack_into("<I", self.cells, offset + 4 + 0x28, values_offset)
+        return offset
+
+    def write_value(self, name, value_type, value):
+        if value_type in (REG_SZ, REG_EXPAND_SZ):
+            data = (value + "\0").encode("utf-16le")
+        elif value_type == REG_MULTI_SZ:
+            data = "".join(v + "\0" for v in value).encode("utf-16le") + b"\0\0"
+        elif value_type == REG_DWORD:
+            data = struct.pack("<I", value)
+        else:
+            data = struct.pack("<Q", value)
+
+        encoded = name.encode("latin-1")
+        if len(data) <= 4:
+            size, data_offset = len(data) | 0x80000000, struct.unpack("<I", data.ljust(4, b"\0"))[0]
+        else:
+            size, data_offset = len(data), self.alloc(data)
+        vk = b"vk" + struct.pack("<HIIIHH", len(encoded), size, data_offset, value_type, 0x0001, 0) + encoded
+        return self.alloc(vk)
+
+
+def lh_hash(name):
+    h = 0
+    for c in name.upper():
+        h = (h * 37 + ord(c)) & 0xFFFFFFFF
+    return h
+
+
+def write_hive(path, root, file_name):
+    writer = HiveWriter()
+    root_offset = writer.write_key(root, -1)
+    cells = writer.cells + b"\0" * (-len(writer.cells) % 4096)
+    if len(writer.cells) % 4096:
+        # Close the last bin with a free cell
+        tail = len(writer.cells)
+        struct.pack_into("<i", cells, tail, len(cells) - tail)
+
+    base = bytearray(4096)
+    base[0:4] = b"regf"
+    struct.pack_into("<IIQIIIII", base, 4, 1, 1, FILETIME, 1, 5, 0, 1, root_offset)
+    struct.pack_into("<II", base, 0x28, len(cells), 1)
+    name = file_name.encode("utf-16le")[:64]
+    base[0x30:0x30 + len(name)] = name
+    checksum = 0
+    for (dword,) in struct.iter_unpack("<I", bytes(base[:0x1FC])):
+        checksum ^= dword
+    struct.pack_into("<I", base, 0x1FC, checksum)
+
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    with open(path, "wb") as f:
+        f.write(bytes(base) + bytes(cells))
+
+
+def uninstall_entry(key_name, display_name, version, location):
+    return Key(key_name, [
+        ("DisplayName", REG_SZ, display_name),
+        ("DisplayVersion", REG_SZ, version),
+        ("InstallLocation", REG_SZ, location),
+        ("EstimatedSize", REG_DWORD, 120000),
+    ])
+
+
+software = Key("ROOT", subkeys=[
+    Key("Microsoft", subkeys=[
+        Key("Windows", subkeys=[
+            Key("CurrentVersion", subkeys=[
+                # More entries than fit one list on real systems; use an index root
+                Key("Uninstall", index_root=True, subkeys=[
+                    uninstall_entry("7-Zip", "7-Zip 23.01 (x64)", "23.01", "C:\\Program Files\\7-Zip\\"),
+                    uninstall_entry("{A1B2C3D4-TALLYPRIME}", "TallyPrime", "4.1", "C:\\Program Files\\TallyPrime\\"),
+                    uninstall_entry("Git_is1", "Git", "2.44.0", "C:\\Program Files\\Git\\"),
+                    uninstall_entry("Mozilla Firefox", "Mozilla Firefox (x64 en-US)", "124.0", "C:\\Program Files\\Mozilla Firefox"),
+                ]),
+            ]),
+        ]),
+        Key("Windows NT", subkeys=[
+            Key("CurrentVersion", [
+                ("ProductName", REG_SZ, "Windows 10 Pro"),
+                ("EditionID", REG_SZ, "Professional"),
+                ("DisplayVersion", REG_SZ, "22H2"),
+                ("CurrentBuild", REG_SZ, "19045"),
+                ("CurrentBuildNumber", REG_SZ, "19045"),
+                ("UBR", REG_DWORD, 4170),
+                ("ProductId", REG_SZ, "00330-80000-00000-AA123"),
+                ("InstallTime", REG_QWORD, FILETIME),
+                ("RegisteredOwner", REG_EXPAND_SZ, "%USERNAME%"),
+            ]),
+        ]),
+    ]),
+    Key("WOW6432Node", subkeys=[
+        Key("Tally Solutions", subkeys=[
+            # Names outside Latin-1 are stored as UTF-16; cover that encoding too
+            Key("Tally.ERP 9", [
+                ("InstallDir", REG_SZ, "c:\\program files (x86)\\tally\\erp9"),
+                ("Version", REG_SZ, "6.6.3"),
+                ("Modules", REG_MULTI_SZ, ["Accounting", "Inventory"]),
+            ], utf16_name=True),
+        ]),
+    ]),
+])
+
+system = Key("ROOT", subkeys=[
+    Key("Select", [("Current", REG_DWORD, 2), ("Default", REG_DWORD, 2)]),
+    Key("ControlSet001", subkeys=[
+        Key("Control", subkeys=[
+            Key("ComputerName", subkeys=[Key("ComputerName", [("ComputerName", REG_SZ, "STALE-NAME")])]),
+        ]),
+    ]),
+    Key("ControlSet002", subkeys=[
+        Key("Control", subkeys=[
+            Key("ComputerName", subkeys=[Key("ComputerName", [("ComputerName", REG_SZ, "ACCOUNTS-PC")])]),
+            Key("Session Manager", subkeys=[
+                Key("Environment", [("PROCESSOR_ARCHITECTURE", REG_SZ, "AMD64")]),
+            ]),
+        ]),
+    ]),
+])
+
+write_hive(os.path.join(CONFIG, "SOFTWARE"), software, "\\SystemRoot\\System32\\Config\\SOFTWARE")
+write_hive(os.path.join(CONFIG, "SYSTEM"), system, "\\SystemRoot\\System32\\Config\\SYSTEM")
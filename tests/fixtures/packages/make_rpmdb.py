@@ -0,0 +1,82 @@
+#!/usr/bin/env python3
+"""Regenerates the rpm databases used by tests/packages.rs.
+
+Both layouts hold the same headers:
+
+  root/usr/lib/sysimage/rpm/rpmdb.sqlite   SQLite, rpm 4.16 and later
+  bdb-root/var/lib/rpm/Packages            Berkeley DB hash, older rpm
+
+The Berkeley DB file is written through dbm.ndbm, so run this with a Python
+whose ndbm module is built on Berkeley DB (Debian's /usr/bin/python3 is):
+
+    /usr/bin/python3 tests/fixtures/packages/make_rpmdb.py
+"""
+
+import dbm.ndbm
+import os
+import sqlite3
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+
+NAME, VERSION, RELEASE, EPOCH, SUMMARY, DESCRIPTION, INSTALLTIME, ARCH = 1000, 1001, 1002, 1003, 1004, 1005, 1008, 1022
+INT32, STRING, I18NSTRING = 4, 6, 9
+
+# 2024-03-15T08:00:00Z, then a day apart
+INSTALLED = 1710489600
+
+PACKAGES = [
+    {NAME: "bash", VERSION: "5.2.26", RELEASE: "3.fc40", ARCH: "x86_64", INSTALLTIME: INSTALLED},
+    {NAME: "openssl-libs", EPOCH: 1, VERSION: "3.1.4", RELEASE: "3.fc40", ARCH: "x86_64", INSTALLTIME: INSTALLED + 86400},
+    # Imported signing keys are stored as packages too
+    {NAME: "gpg-pubkey", VERSION: "a15b79cc", RELEASE: "63d04c2c", INSTALLTIME: INSTALLED},
+    # Larger than a page, so it is stored in overflow pages
+    {NAME: "tzdata", VERSION: "2024a", RELEASE: "5.fc40", ARCH: "noarch", INSTALLTIME: INSTALLED + 2 * 86400,
+     SUMMARY: "Timezone data", DESCRIPTION: "This package contains data files with rules for various timezones. " * 150},
+]
+
+
+def header(tags):
+    """A header as rpm stores it in the database: counts, index, data, big-endian"""
+    index, data = [], b""
+    for tag in sorted(tags):
+        value = tags[tag]
+        if isinstance(value, int):
+            data += b"\0" * (-len(data) % 4)
+            index.append((tag, INT32, len(data), 1))
+            data += struct.pack(">I", value)
+        else:
+            kind = I18NSTRING if tag in (SUMMARY, DESCRIPTION) else STRING
+            index.append((tag, kind, len(data), 1))
+            data += value.encode() + b"\0"
+    return struct.pack(">II", len(index), len(data)) + b"".join(struct.pack(">IIII", *entry) for entry in index) + data
+
+
+def write_sqlite(path):
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    if os.path.exists(path):
+        os.remove(path)
+    db = sqlite3.connect(path)
+    db.execute("CREATE TABLE Packages (hnum INTEGER PRIMARY KEY AUTOINCREMENT, blob BLOB NOT NULL)")
+    db.executemany("INSERT INTO Packages (blob) VALUES (?)", [(header(p),) for p in PACKAGES])
+    db.commit()
+    db.close()
+
+
+def write_bdb(path):
+    os.makedirs(os.path.dirname(path), exist_ok=True)
+    for stale in (path, path + ".db"):
+        if os.path.exists(stale):
+            os.remove(stale)
+    db = dbm.ndbm.open(path, "n")
+    # Key 0 holds the next instance number; packages are keyed by instance
+    db[struct.pack("<I", 0)] = struct.pack("<I", len(PACKAGES) + 1)
+    for instance, package in enumerate(PACKAGES, start=1):
+        db[struct.pack("<I", instance)] = header(package)
+    db.close()
+    os.rename(path + ".db", path)
+
+
+assert dbm.ndbm.library == "Berkeley DB", "needs a Python whose ndbm module uses Berkeley DB"
+write_sqlite(os.path.join(HERE, "root/usr/lib/sysimage/rpm/rpmdb.sqlite"))
+write_bdb(os.path.join(HERE, "bdb-root/var/lib/rpm/Packages"))
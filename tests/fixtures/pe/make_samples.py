@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+"""Regenerates the sample PE images used by tests/pe_version.rs.
+
+The images are minimal but valid: one .rsrc section holding a VS_VERSIONINFO
+resource laid out as the Microsoft resource compiler does.
+
+    python3 tests/fixtures/pe/make_samples.py
+"""
+
+import os
+import struct
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+SECTION_RVA = 0x1000
+FILE_ALIGNMENT = 0x200
+
+
+def pad4(data):
+    return data + b"\0" * (-len(data) % 4)
+
+
+def block(key, value=b"", value_length=0, text=False, children=()):
+    body = pad4(b"\0" * 6 + (key + "\0").encode("utf-16le"))
+    body = pad4(body + value) if value else body
+    body += b"".join(pad4(child) for child in children)
+    return struct.pack("<HHH", len(body), value_length, 1 if text else 0) + body[6:]
+
+
+def string(key, text):
+    value = (text + "\0").encode("utf-16le")
+    return block(key, value, len(text) + 1, text=True)
+
+
+def version_resource(fixed, strings, table):
+    def split(version):
+        a, b, c, d = version
+        return (a << 16) | b, (c << 16) | d
+
+    file_ms, file_ls = split(fixed)
+    fixed_info = struct.pack(
+        "<13I", 0xFEEF04BD, 0x00010000, file_ms, file_ls, file_ms, file_ls, 0x3F, 0, 0x40004, 1, 0, 0, 0
+    )
+    string_table = block(table, children=[string(k, v) for k, v in strings])
+    translation = block("Translation", struct.pack("<HH", int(table[:4], 16), int(table[4:], 16)), 4)
+    return block(
+        "VS_VERSION_INFO",
+        fixed_info,
+        len(fixed_info),
+        children=[block("StringFileInfo", children=[string_table]), block("VarFileInfo", children=[translation])],
+    )
+
+
+def resource_section(version):
+    # Type (RT_VERSION) -> name (1) -> language (0x409) -> data entry
+    directory = lambda entry_id, target: struct.pack("<IIHHHH", 0, 0, 0, 0, 0, 1) + struct.pack("<II", entry_id, target)
+    tree = directory(16, 0x80000000 | 24) + directory(1, 0x80000000 | 48) + directory(0x409, 72)
+    data_entry = struct.pack("<IIII", SECTION_RVA + 88, len(version), 0, 0)
+    return tree + data_entry + version
+
+
+def pe_image(section, pe32_plus):
+    raw_size = len(section) + (-len(section) % FILE_ALIGNMENT)
+    optional_size = 240 if pe32_plus else 224
+
+    dos = bytearray(64)
+    dos[0:2] = b"MZ"
+    struct.pack_into("<I", dos, 0x3C, 64)
+
+    coff = struct.pack("<HHIIIHH", 0x8664 if pe32_plus else 0x14C, 1, 0, 0, 0, optional_size, 0x22)
+
+    optional = bytearray(optional_size)
+    struct.pack_into("<H", optional, 0, 0x20B if pe32_plus else 0x10B)
+    struct.pack_into("<II", optional, 32, 0x1000, FILE_ALIGNMENT)
+    struct.pack_into("<II", optional, 56, SECTION_RVA + 0x1000, FILE_ALIGNMENT)
+    struct.pack_into("<H", optional, 68, 2)
+    directories = 112 if pe32_plus else 96
+    struct.pack_into("<I", optional, directories - 4, 16)
+    if section:
+        struct.pack_into("<II", optional, directories + 2 * 8, SECTION_RVA, len(section))
+
+    section_header = struct.pack(
+        "<8sIIIIIIHHI", b".rsrc", len(section), SECTION_RVA, raw_size, FILE_ALIGNMENT, 0, 0, 0, 0, 0x40000040
+    )
+
+    headers = bytes(dos) + b"PE\0\0" + coff + bytes(optional) + section_header
+    headers += b"\0" * (FILE_ALIGNMENT - len(headers))
+    return headers + section + b"\0" * (raw_size - len(section))
+
+
+def write(name, data):
+    with open(os.path.join(HERE, name), "wb") as f:
+        f.write(data)
+
+
+write("tally_prime.exe", pe_image(resource_section(version_resource(
+    (4, 1, 0, 0),
+    [
+        ("CompanyName", "Tally Solutions Pvt. Ltd."),
+        ("FileDescription", "TallyPrime"),
+        ("FileVersion", "4.1.0.0"),
+        ("ProductName", "TallyPrime"),
+        ("ProductVersion", "4.1"),
+    ],
+    "040904b0",
+)), pe32_plus=True))
+
+# ERP 9 shipped as a 32-bit image with a language-neutral string table
+write("tally_erp9.exe", pe_image(resource_section(version_resource(
+    (6, 6, 3, 0),
+    [
+        ("CompanyName", "Tally Solutions Pvt. Ltd."),
+        ("FileVersion", "6.6.3"),
+        ("ProductName", "Tally.ERP 9"),
+        ("ProductVersion", "Release 6.6.3"),
+    ],
+    "000004b0",
+)), pe32_plus=False))
+
+write("no_version.exe", pe_image(b"", pe32_plus=True))
@@ -0,0 +1,197 @@
+//! The `--http` server: readiness, diagnostics, conditional requests and
+//! streaming endpoints
+
+#![cfg(feature = "http")]
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::thread;
+use std::time::Duration;
+#[cfg(unix)]
+use std::process::Command;
+
+use serde_json::Value;
+use support::{fixture, QuicksysServer};
+
+fn empty_home() -> PathBuf {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("http-empty-home");
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn start_server() -> QuicksysServer {
+    QuicksysServer::start(&["--no-tally"], &[("HOME", &empty_home())])
+}
+
+#[test]
+fn stream_clients_share_one_collection_per_tick() {
+    let server = start_server();
+
+    // Both first ticks fall within the same interval, so the second client
+    // gets the collection the first one triggered
+    let first = server.first_event("/api/stream?interval_ms=60000&select=cpu");
+    let second = server.first_event("/api/stream?interval_ms=60000&select=memory");
+    assert!(first.starts_with("event:snapshot\ndata:{\"collector\""), "{}", first);
+    assert!(first.contains("\"cpu\":") && !first.contains("\"memory\":"), "{}", first);
+    assert!(second.contains("\"memory\":") && !second.contains("\"cpu\":"), "{}", second);
+
+    // The startup collection plus one for both streams
+    let diagnostics = server.get("/api/diagnostics", &[]).json();
+    assert_eq!(diagnostics["collections"], 2, "{:#}", diagnostics);
+}
+
+#[cfg(unix)]
+#[test]
+fn unchanged_collections_push_no_patches() {
+    // Every collection waits for dpkg's status a little longer than the last, so no two take
+    // the same time while the packages they read stay the same
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("http-slowing-root");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("var/lib/dpkg")).unwrap();
+    let status = root.join("var/lib/dpkg/status");
+    assert!(Command::new("mkfifo").arg(&status).status().unwrap().success());
+    let writer = status.clone();
+    thread::spawn(move || {
+        for delay in (1..).map(|n| Duration::from_millis(20 * n)) {
+            thread::sleep(delay);
+            fs::write(&writer, "Package: bash\nStatus: install ok installed\nVersion: 5.2.15-2\n").unwrap();
+        }
+    });
+
+    let server = QuicksysServer::start(&["--no-tally"], &[("HOME", &empty_home()), ("HOST_ROOT", &root)]);
+    server.wait_until_ready();
+    let events = server.event_names_within("/api/stream?interval_ms=250&select=apps", Duration::from_millis(1500));
+    assert_eq!(events, ["snapshot"]);
+
+    // Every tick re-collected all the same
+    let diagnostics = server.get("/api/diagnostics", &[]).json();
+    assert!(diagnostics["collections"].as_u64().unwrap() >= 4, "{:#}", diagnostics);
+}
+
+#[test]
+fn refuses_push_intervals_over_an_hour() {
+    let server = start_server();
+
+    for interval in ["3600001", &u64::MAX.to_string()] {
+        let response = server.get(&format!("/api/stream?interval_ms={}", interval), &[]);
+        assert_eq!(response.status, 400, "interval_ms={}", interval);
+        assert_eq!(String::from_utf8_lossy(&response.body), "interval_ms must not exceed 3600000");
+    }
+    // The longest allowed interval still streams
+    assert!(server.first_event("/api/stream?interval_ms=3600000").starts_with("event:snapshot"));
+}
+
+#[test]
+fn snapshot_carries_weak_validators() {
+    let server = start_server();
+
+    let response = server.get("/api/info", &[]);
+    assert_eq!(response.status, 200);
+    let etag = response.header("etag").unwrap();
+    assert!(etag.starts_with("W/\"") && etag.ends_with('"') && etag.len() == 36, "{}", etag);
+    assert!(response.header("last-modified").unwrap().ends_with(" GMT"));
+    assert_eq!(response.header("cache-control"), Some("no-cache"));
+    assert!(response.json()["cpu"].is_object());
+
+    // Every encoding is the same content, so it gets the same weak tag
+    let gzip = server.get("/api/info", &[("Accept-Encoding", "gzip")]);
+    assert_eq!(gzip.header("content-encoding"), Some("gzip"));
+    assert_eq!(gzip.header("etag"), Some(etag));
+}
+
+#[test]
+fn if_none_match_answers_not_modified() {
+    let server = start_server();
+    let etag = server.get("/api/info", &[]).header("etag").unwrap().to_string();
+    let strong_form = etag.trim_start_matches("W/");
+
+    for tag in [etag.as_str(), strong_form, "*", &format!("\"other\", {}", etag)] {
+        let response = server.get("/api/info", &[("If-None-Match", tag)]);
+        assert_eq!(response.status, 304, "If-None-Match: {}", tag);
+        assert!(response.body.is_empty());
+        assert_eq!(response.header("etag"), Some(etag.as_str()));
+    }
+    // Sections share the snapshot's validators
+    assert_eq!(server.get("/api/info/cpu", &[("If-None-Match", &etag)]).status, 304);
+
+    let changed = server.get("/api/info", &[("If-None-Match", "W/\"0123456789abcdef0123456789abcdef\"")]);
+    assert_eq!(changed.status, 200);
+    assert!(changed.json()["os"].is_object());
+}
+
+#[test]
+fn if_modified_since_answers_not_modified() {
+    let server = start_server();
+    let last_modified = server.get("/api/info", &[]).header("last-modified").unwrap().to_string();
+
+    let response = server.get("/api/info", &[("If-Modified-Since", &last_modified)]);
+    assert_eq!(response.status, 304);
+    assert!(response.body.is_empty());
+
+    let older = server.get("/api/info", &[("If-Modified-Since", "Sat, 01 Jan 2000 00:00:00 GMT")]);
+    assert_eq!(older.status, 200);
+
+    // An entity tag that does not match wins over the date
+    let mismatch = server.get("/api/info", &[("If-None-Match", "W/\"stale\""), ("If-Modified-Since", &last_modified)]);
+    assert_eq!(mismatch.status, 200);
+}
+
+#[cfg(unix)]
+#[test]
+fn not_ready_until_the_first_collection_is_published() {
+    // The first collection blocks reading dpkg's status until the test writes it
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("http-blocked-root");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("var/lib/dpkg")).unwrap();
+    let status = root.join("var/lib/dpkg/status");
+    assert!(Command::new("mkfifo").arg(&status).status().unwrap().success());
+
+    let server = QuicksysServer::start(&["--no-tally"], &[("HOME", &empty_home()), ("HOST_ROOT", &root)]);
+    assert_eq!(server.get("/healthz", &[]).status, 200);
+    let readyz = server.get("/readyz", &[]);
+    assert_eq!(readyz.status, 503);
+    assert_eq!(readyz.body, b"collecting");
+    assert_eq!(server.get("/api/info", &[]).status, 503);
+    assert_eq!(server.get("/api/info/cpu", &[]).status, 503);
+    assert_eq!(server.get("/api/diagnostics", &[]).json()["ready"], false);
+
+    fs::write(&status, "Package: bash\nStatus: install ok installed\nVersion: 5.2.15-2\n").unwrap();
+    server.wait_until_ready();
+
+    assert_eq!(server.get("/readyz", &[]).body, b"ready");
+    let info = server.get("/api/info", &[]).json();
+    assert_eq!(info["apps"]["packages"][0]["name"], "bash");
+    assert_eq!(server.get("/api/diagnostics", &[]).json()["ready"], true);
+}
+
+#[test]
+fn diagnostics_report_probes_and_gateway_failures() {
+    // Nothing listens on port 1, so the gateway of the prefix's installation fails
+    let server = QuicksysServer::start(&["--tally-http", "127.0.0.1:1"], &[("HOME", &fixture("wine/home"))]);
+    server.wait_until_ready();
+    server.get("/api/info", &[]);
+    server.get("/api/info/nowhere", &[]);
+
+    let diagnostics = server.get("/api/diagnostics", &[]).json();
+    assert_eq!(diagnostics["ready"], true);
+    assert_eq!(diagnostics["features"]["http"], true);
+    assert_eq!(diagnostics["collections"], 1);
+    for probe in ["mock", "tally", "packages", "apps"] {
+        assert_eq!(diagnostics["probes"][probe]["runs"], 1, "{}", probe);
+    }
+
+    let errors = diagnostics["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1, "{:#?}", errors);
+    assert_eq!(errors[0]["probe"], "tally");
+    let message = errors[0]["message"].as_str().unwrap();
+    assert!(message.starts_with("gateway 127.0.0.1:1: "), "{}", message);
+    assert!(errors[0]["at_unix_ms"].as_u64().unwrap() > 0);
+    assert_eq!(diagnostics["probes"]["tally"]["last_error"], message);
+    assert_eq!(diagnostics["probes"]["mock"]["last_error"], Value::Null);
+
+    assert_eq!(diagnostics["requests"]["/api/info"]["2xx"], 1);
+    assert_eq!(diagnostics["requests"]["/api/info/:path"]["total"], 1);
+}
@@ -0,0 +1,127 @@
+//! Inventory of the mounted-volume fixture in tests/fixtures/offline/volume
+//! (regenerate its hives with tests/fixtures/offline/make_hives.py)
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+use support::{fixture, run_quicksys};
+
+fn inventory(root: &std::path::Path) -> (Value, bool) {
+    let (report, output) = run_quicksys(&["--offline-windows", root.to_str().unwrap()]);
+    (report, output.status.success())
+}
+
+#[test]
+fn reads_os_and_device_from_hives() {
+    let (report, ok) = inventory(&fixture("offline/volume"));
+
+    assert!(ok);
+    assert_eq!(
+        report["os"],
+        json!({
+            "family": "Windows",
+            "edition": "Professional",
+            "version": "22H2",
+            "build": "19045",
+            "arch": "x86_64",
+            "product_id": "00330..."
+        })
+    );
+    // Select\Current points at ControlSet002, not the stale ControlSet001
+    assert_eq!(report["device"]["hostname"], "ACCOUNTS-PC");
+    assert!(report.get("cpu").is_none());
+}
+
+#[test]
+fn detects_tally_on_the_volume() {
+    let (report, ok) = inventory(&fixture("offline/volume"));
+    assert!(ok);
+
+    let installs = report["apps"]["tally"].as_array().unwrap();
+    assert_eq!(installs.len(), 2, "{:#?}", installs);
+
+    // Listed under an index root among unrelated uninstall entries
+    let prime = &installs[0];
+    assert_eq!(prime["variant"], "TallyPrime");
+    assert_eq!(prime["version"], "4.1");
+    assert_eq!(prime["install_path"], "C:\\Program Files\\TallyPrime\\");
+    assert_eq!(prime["config"]["port"], 9100);
+    assert_eq!(prime["data_folders"][0]["company_number"], "10000");
+    assert!(prime.get("wine_prefix").is_none());
+
+    // The vendor key (UTF-16 name) and the folder on disk are one installation
+    let erp9 = &installs[1];
+    assert_eq!(erp9["variant"], "TallyERP9");
+    assert_eq!(erp9["version"], "6.6.3");
+    assert_eq!(erp9["detection_sources"], json!(["registry_vendor", "file_version"]));
+    assert!(erp9["install_path"].as_str().unwrap().eq_ignore_ascii_case("C:\\Program Files (x86)\\Tally\\ERP9"));
+    assert_eq!(erp9["config"]["odbc_server"], false);
+}
+
+#[test]
+fn skips_tally_when_disabled() {
+    let root = fixture("offline/volume");
+    let (report, output) = run_quicksys(&["--offline-windows", root.to_str().unwrap(), "--no-tally"]);
+
+    assert!(output.status.success());
+    assert!(report.get("apps").is_none());
+    assert_eq!(report["os"]["edition"], "Professional");
+}
+
+#[test]
+fn missing_or_corrupt_software_hive_is_an_error() {
+    let (report, ok) = inventory(&fixture("pe"));
+    assert!(!ok);
+    assert!(report["error"].as_str().unwrap().contains("SOFTWARE"), "{}", report);
+
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("offline-corrupt");
+    let config = root.join("Windows/System32/config");
+    fs::create_dir_all(&config).unwrap();
+    fs::write(config.join("SOFTWARE"), b"not a hive").unwrap();
+
+    let (report, ok) = inventory(&root);
+    assert!(!ok);
+    assert!(report["error"].as_str().unwrap().ends_with("not a registry hive (missing regf signature)"), "{}", report);
+
+    // A truncated hive must not panic either
+    let hive = fs::read(fixture("offline/volume/Windows/System32/config/SOFTWARE")).unwrap();
+    fs::write(config.join("SOFTWARE"), &hive[..5000]).unwrap();
+    let (_, output) = run_quicksys(&["--offline-windows", root.to_str().unwrap()]);
+    assert!(matches!(output.status.code(), Some(0) | Some(1)), "{:?}", output.status);
+}
+
+#[test]
+fn value_counts_are_bounded_by_the_value_list() {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("offline-value-counts");
+    let config = root.join("Windows/System32/config");
+    fs::create_dir_all(&config).unwrap();
+
+    // Every key claims u32::MAX values; only its value list says how many there are
+    let mut hive = fs::read(fixture("offline/volume/Windows/System32/config/SOFTWARE")).unwrap();
+    let mut bin = 0x1000;
+    while hive.get(bin..bin + 4) == Some(b"hbin") {
+        let bin_size = u32::from_le_bytes(hive[bin + 8..bin + 12].try_into().unwrap()) as usize;
+        let mut cell = bin + 0x20;
+        while cell + 8 <= bin + bin_size {
+            let size = i32::from_le_bytes(hive[cell..cell + 4].try_into().unwrap());
+            if size < 0 && &hive[cell + 4..cell + 6] == b"nk" {
+                hive[cell + 4 + 0x24..cell + 4 + 0x28].copy_from_slice(&u32::MAX.to_le_bytes());
+            }
+            cell += (size.unsigned_abs() as usize).max(8);
+        }
+        bin += bin_size;
+    }
+    // A lookup that misses walks the whole list; CurrentVersion is the last key holding this name
+    let name = hive.windows(14).rposition(|w| w == b"DisplayVersion").unwrap();
+    hive[name + 13] = b'X';
+    fs::write(config.join("SOFTWARE"), hive).unwrap();
+
+    let (report, ok) = inventory(&root);
+    assert!(ok);
+    assert_eq!(report["os"]["edition"], "Professional");
+    assert_eq!(report["os"]["build"], "19045");
+    assert!(report["os"].get("version").is_none(), "{}", report["os"]);
+}
@@ -0,0 +1,173 @@
+//! Package inventory read from fixture databases: a dpkg status file, rpm's
+//! sqlite and Berkeley DB layouts, apk's installed file, a Flatpak
+//! deployment and snaps
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+use support::{detect_apps, fixture};
+
+/// 2024-03-15T10:30:00Z
+const INSTALLED: u64 = 1_710_498_600;
+
+/// Git does not keep modification times, so the dates read from them are set here
+fn set_modified(path: &Path) {
+    File::open(path)
+        .unwrap()
+        .set_modified(UNIX_EPOCH + Duration::from_secs(INSTALLED))
+        .unwrap();
+}
+
+fn empty_home() -> PathBuf {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("packages-empty-home");
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+fn packages(root: &Path) -> Vec<Value> {
+    let apps = detect_apps(&["--no-tally"], &[("HOME", &empty_home()), ("HOST_ROOT", root)]);
+    apps["packages"].as_array().cloned().unwrap_or_default()
+}
+
+fn from<'a>(packages: &'a [Value], source: &str) -> Vec<&'a Value> {
+    packages.iter().filter(|package| package["source"] == source).collect()
+}
+
+fn names(packages: &[&Value]) -> Vec<String> {
+    packages.iter().map(|package| package["name"].as_str().unwrap().to_string()).collect()
+}
+
+#[test]
+fn reads_every_package_source() {
+    let root = fixture("packages/root");
+    for list in ["libc6:amd64.list", "git.list", "tzdata.list"] {
+        set_modified(&root.join("var/lib/dpkg/info").join(list));
+    }
+    set_modified(&root.join("var/lib/flatpak/app/org.gnome.Calculator/x86_64/stable/active/"));
+    set_modified(&root.join("var/lib/snapd/snaps/firefox_4173.snap"));
+    set_modified(&root.join("var/lib/snapd/snaps/core22_1033.snap"));
+
+    let packages = packages(&root);
+    let sources: Vec<&str> = packages.iter().map(|package| package["source"].as_str().unwrap()).collect();
+    let mut grouped = sources.clone();
+    grouped.dedup();
+    assert_eq!(grouped, ["dpkg", "rpm", "apk", "flatpak", "snap"], "{:#?}", packages);
+
+    // nano was removed and only left its configuration behind
+    let dpkg = from(&packages, "dpkg");
+    assert_eq!(names(&dpkg), ["git", "libc6", "tzdata"]);
+    assert_eq!(
+        *dpkg[1],
+        json!({
+            "name": "libc6",
+            "version": "2.36-9+deb12u4",
+            "architecture": "amd64",
+            "source": "dpkg",
+            "install_date": "2024-03-15T10:30:00Z",
+        })
+    );
+    assert_eq!(dpkg[0]["version"], "1:2.39.2-1.1");
+    assert_eq!(dpkg[0]["install_date"], "2024-03-15T10:30:00Z");
+
+    let apk = from(&packages, "apk");
+    assert_eq!(names(&apk), ["busybox", "musl"]);
+    assert_eq!(*apk[1], json!({ "name": "musl", "version": "1.2.4-r2", "architecture": "x86_64", "source": "apk" }));
+
+    let flatpak = from(&packages, "flatpak");
+    assert_eq!(
+        flatpak,
+        [&json!({
+            "name": "org.gnome.Calculator",
+            "version": "45.0.2",
+            "architecture": "x86_64",
+            "source": "flatpak",
+            "install_date": "2024-03-15T10:30:00Z",
+        })]
+    );
+
+    // Flow and block lists of architectures
+    let snap = from(&packages, "snap");
+    assert_eq!(names(&snap), ["core22", "firefox"]);
+    assert_eq!(snap[0]["version"], "20231123");
+    assert_eq!(snap[0]["architecture"], "amd64");
+    assert_eq!(
+        *snap[1],
+        json!({
+            "name": "firefox",
+            "version": "121.0-1",
+            "architecture": "amd64",
+            "source": "snap",
+            "install_date": "2024-03-15T10:30:00Z",
+        })
+    );
+}
+
+/// The rpm fixtures hold the same headers in both database formats
+fn assert_rpm_packages(packages: &[&Value]) {
+    // gpg-pubkey entries are signing keys, not packages
+    assert_eq!(names(packages), ["bash", "openssl-libs", "tzdata"]);
+    assert_eq!(
+        *packages[0],
+        json!({
+            "name": "bash",
+            "version": "5.2.26-3.fc40",
+            "architecture": "x86_64",
+            "source": "rpm",
+            "install_date": "2024-03-15T08:00:00Z",
+        })
+    );
+    assert_eq!(packages[1]["version"], "1:3.1.4-3.fc40");
+    assert_eq!(packages[1]["install_date"], "2024-03-16T08:00:00Z");
+    // Its header spills onto overflow pages
+    assert_eq!(packages[2]["version"], "2024a-5.fc40");
+    assert_eq!(packages[2]["architecture"], "noarch");
+    assert_eq!(packages[2]["install_date"], "2024-03-17T08:00:00Z");
+}
+
+#[test]
+fn reads_the_sqlite_rpm_database() {
+    let packages = packages(&fixture("packages/root"));
+    assert_rpm_packages(&from(&packages, "rpm"));
+}
+
+#[test]
+fn reads_the_berkeley_db_rpm_database() {
+    let packages = packages(&fixture("packages/bdb-root"));
+    assert_eq!(packages.len(), 3, "{:#?}", packages);
+    assert_rpm_packages(&from(&packages, "rpm"));
+}
+
+#[test]
+fn no_package_databases_leave_apps_out() {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("packages-empty-root");
+    fs::create_dir_all(&root).unwrap();
+
+    let apps = detect_apps(&["--no-tally"], &[("HOME", &empty_home()), ("HOST_ROOT", &root)]);
+    assert_eq!(apps, Value::Null, "{:#}", apps);
+}
+
+#[test]
+fn skips_corrupt_berkeley_db_items() {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("packages-bdb-corrupt-root");
+    fs::create_dir_all(root.join("var/lib/rpm")).unwrap();
+    let mut db = fs::read(fixture("packages/bdb-root/var/lib/rpm/Packages")).unwrap();
+
+    // On the first hash page, openssl-libs' header starts past the page end
+    // and tzdata's, stored on overflow pages, claims to be 4 GiB long
+    let page = 4096;
+    let item_offsets = page + 26;
+    let overflow = page + u16::from_le_bytes([db[item_offsets + 10], db[item_offsets + 11]]) as usize;
+    db[overflow + 8..overflow + 12].copy_from_slice(&u32::MAX.to_le_bytes());
+    db[item_offsets + 6..item_offsets + 8].copy_from_slice(&0xFFFFu16.to_le_bytes());
+    fs::write(root.join("var/lib/rpm/Packages"), db).unwrap();
+
+    // bash, on the second page, is still listed
+    let packages = packages(&root);
+    assert_eq!(names(&from(&packages, "rpm")), ["bash"], "{:#?}", packages);
+}
@@ -0,0 +1,69 @@
+//! Version resources read from the sample images in tests/fixtures/pe
+//! (regenerate them with tests/fixtures/pe/make_samples.py)
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::json;
+use support::{fixture, run_quicksys};
+
+fn file_version(path: &std::path::Path) -> (serde_json::Value, bool) {
+    let (value, output) = run_quicksys(&["file-version", path.to_str().unwrap()]);
+    (value, output.status.success())
+}
+
+#[test]
+fn reads_pe32_plus_version_resource() {
+    let (info, ok) = file_version(&fixture("pe/tally_prime.exe"));
+
+    assert!(ok);
+    assert_eq!(
+        info,
+        json!({
+            "fixed_file_version": "4.1.0.0",
+            "fixed_product_version": "4.1.0.0",
+            "file_version": "4.1.0.0",
+            "product_version": "4.1",
+            "product_name": "TallyPrime",
+            "company_name": "Tally Solutions Pvt. Ltd.",
+            "file_description": "TallyPrime"
+        })
+    );
+}
+
+#[test]
+fn reads_pe32_image_with_language_neutral_string_table() {
+    let (info, ok) = file_version(&fixture("pe/tally_erp9.exe"));
+
+    assert!(ok);
+    assert_eq!(info["product_name"], "Tally.ERP 9");
+    assert_eq!(info["product_version"], "Release 6.6.3");
+    assert_eq!(info["fixed_file_version"], "6.6.3.0");
+    assert!(info.get("file_description").is_none());
+}
+
+#[test]
+fn image_without_resources_is_an_error() {
+    let (report, ok) = file_version(&fixture("pe/no_version.exe"));
+
+    assert!(!ok);
+    assert_eq!(report["error"], "no version resource");
+}
+
+#[test]
+fn rejects_non_pe_and_truncated_files() {
+    let (report, ok) = file_version(&fixture("tally/companies_prime.xml"));
+    assert!(!ok);
+    assert!(report["error"].as_str().unwrap().starts_with("not a PE image"));
+
+    // Cut inside the resource section: must fail cleanly rather than panic
+    let image = fs::read(fixture("pe/tally_prime.exe")).unwrap();
+    let truncated = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("truncated.exe");
+    fs::write(&truncated, &image[..600]).unwrap();
+
+    let (report, ok) = file_version(&truncated);
+    assert!(!ok);
+    assert!(report["error"].as_str().unwrap().starts_with("truncated"), "{}", report);
+}
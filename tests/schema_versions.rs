@@ -0,0 +1,86 @@
+//! Golden tests locking every published output layout.
+//!
+//! Run with `UPDATE_GOLDEN=1 cargo test` to rewrite the golden files after an
+//! intentional change, then review the diff before committing.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+use serde_json::Value;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name)
+}
+
+fn run_quicksys(args: &[&str]) -> Value {
+    // Tally is detected in the fixture Wine prefixes and nothing else is installed
+    // below the fixture root, so the output does not depend on the host
+    let home = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/wine/home");
+    let output = Command::new(env!("CARGO_BIN_EXE_quicksys"))
+        .args(args)
+        .env("HOME", &home)
+        .env("HOST_ROOT", &home)
+        .env_remove("WINEPREFIX")
+        .env_remove("XDG_DATA_HOME")
+        .env_remove("XDG_DATA_DIRS")
+        .output()
+        .expect("failed to run quicksys");
+    assert!(output.status.success(), "quicksys {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    let stdout = stdout.replace(home.to_str().unwrap(), "$HOME");
+    let mut value: Value = serde_json::from_str(&stdout).expect("stdout is not JSON");
+
+    // Timing is the only field that varies between runs
+    if let Some(collector) = value.get_mut("collector").and_then(Value::as_object_mut) {
+        collector.insert("duration_ms".to_string(), Value::from(0));
+    }
+
+    value
+}
+
+fn assert_golden(name: &str, actual: &Value) {
+    let path = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, serde_json::to_string_pretty(actual).unwrap() + "\n").unwrap();
+        return;
+    }
+
+    let expected: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(&expected, actual, "{} does not match; rerun with UPDATE_GOLDEN=1 if the change is intentional", name);
+}
+
+#[test]
+fn json_schema_matches_golden() {
+    assert_golden("schema.json", &run_quicksys(&["schema"]));
+}
+
+#[test]
+#[cfg(not(windows))]
+fn output_v1_matches_golden() {
+    assert_golden("output_v1.json", &run_quicksys(&["--schema-version", "1"]));
+}
+
+#[test]
+#[cfg(not(windows))]
+fn output_v2_matches_golden() {
+    assert_golden("output_v2.json", &run_quicksys(&["--schema-version", "2"]));
+}
+
+#[test]
+#[cfg(not(windows))]
+fn output_v3_matches_golden() {
+    assert_golden("output_v3.json", &run_quicksys(&["--schema-version", "3"]));
+}
+
+#[test]
+fn rejects_unpublished_schema_version() {
+    let status = Command::new(env!("CARGO_BIN_EXE_quicksys"))
+        .args(["--schema-version", "99"])
+        .output()
+        .expect("failed to run quicksys")
+        .status;
+    assert!(!status.success());
+}
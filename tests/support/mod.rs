@@ -0,0 +1,481 @@
+//! Shared helpers for integration tests: running the binary and its HTTP
+//! server, an in-process stand-in for Tally's XML-over-HTTP gateway and fake
+//! proc trees.
+
+#![allow(dead_code)]
+
+#[cfg(unix)]
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// Runs quicksys with `args` and returns the parsed stdout plus the raw output
+pub fn run_quicksys(args: &[&str]) -> (Value, Output) {
+    run_quicksys_with_env(args, &[])
+}
+
+/// Like `run_quicksys`, with extra environment variables for the child
+pub fn run_quicksys_with_env(args: &[&str], env: &[(&str, &Path)]) -> (Value, Output) {
+    let output = quicksys_command(args, env).output().expect("failed to run quicksys");
+    let value = serde_json::from_slice(&output.stdout).unwrap_or(Value::Null);
+    (value, output)
+}
+
+/// Runs `quicksys --select apps` with `extra_args`, asserts that it succeeds
+/// and returns the `apps` section (null when nothing was found)
+pub fn detect_apps(extra_args: &[&str], env: &[(&str, &Path)]) -> Value {
+    let mut args = vec!["--select", "apps"];
+    args.extend_from_slice(extra_args);
+
+    let (report, output) = run_quicksys_with_env(&args, env);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    report["apps"].clone()
+}
+
+/// The Tally installations `detect_apps` reports
+pub fn detect_tally(extra_args: &[&str], env: &[(&str, &Path)]) -> Vec<Value> {
+    detect_apps(extra_args, env)["tally"].as_array().cloned().unwrap_or_default()
+}
+
+/// Whether a JSON string ends with `suffix`, for host paths below a temporary directory
+pub fn ends_with(value: &Value, suffix: &str) -> bool {
+    value.as_str().is_some_and(|s| s.ends_with(suffix))
+}
+
+/// quicksys with `args`, isolated from the developer's machine
+fn quicksys_command(args: &[&str], env: &[(&str, &Path)]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_quicksys"));
+    command.args(args);
+    // Keep the developer's own Wine setup out of the results
+    command.env_remove("WINEPREFIX");
+    // ...and any Tally the developer has running; tests point HOST_PROC at a fake tree
+    command.env("HOST_PROC", Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-proc"));
+    // ...and the applications and packages installed on the developer's machine
+    command.env("HOST_ROOT", Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-root"));
+    command.env("XDG_DATA_HOME", Path::new(env!("CARGO_TARGET_TMPDIR")).join("no-data-home"));
+    command.env_remove("XDG_DATA_DIRS");
+    for (key, value) in env {
+        command.env(key, value);
+    }
+    command
+}
+
+/// quicksys serving `--http` on a free port; the process is killed on drop
+pub struct QuicksysServer {
+    pub port: u16,
+    child: Child,
+}
+
+/// A response read from `QuicksysServer`, with the body as sent (compressed
+/// bodies are left as they are)
+#[derive(Debug)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_slice(&self.body).unwrap_or_else(|e| panic!("{}: {}", e, String::from_utf8_lossy(&self.body)))
+    }
+}
+
+impl QuicksysServer {
+    /// Starts quicksys with `--http` added to `args` and waits until it accepts connections
+    pub fn start(args: &[&str], env: &[(&str, &Path)]) -> Self {
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let port_arg = port.to_string();
+        let mut args = args.to_vec();
+        args.extend_from_slice(&["--http", &port_arg]);
+
+        let child = quicksys_command(&args, env)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start quicksys");
+        let mut server = Self { port, child };
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(server.child.try_wait().unwrap().is_none(), "quicksys exited before serving");
+            assert!(Instant::now() < deadline, "quicksys did not start serving on port {}", port);
+            thread::sleep(Duration::from_millis(20));
+        }
+        server
+    }
+
+    /// Sends a GET request with extra headers and reads the whole response
+    pub fn get(&self, path: &str, headers: &[(&str, &str)]) -> HttpResponse {
+        let mut stream = self.request(path, headers);
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw).unwrap();
+
+        let split = raw.windows(4).position(|w| w == b"\r\n\r\n").expect("no end of headers");
+        let head = String::from_utf8_lossy(&raw[..split]).into_owned();
+        let mut lines = head.lines();
+        let status = lines.next().and_then(|line| line.split(' ').nth(1)).and_then(|s| s.parse().ok()).unwrap();
+        let headers: Vec<(String, String)> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+            .collect();
+
+        let mut response = HttpResponse { status, headers, body: raw[split + 4..].to_vec() };
+        if response.header("transfer-encoding").is_some_and(|te| te.eq_ignore_ascii_case("chunked")) {
+            response.body = dechunk(&response.body);
+        }
+        response
+    }
+
+    /// Waits until `/readyz` reports the first collection as published
+    pub fn wait_until_ready(&self) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.get("/readyz", &[]).status != 200 {
+            assert!(Instant::now() < deadline, "quicksys did not become ready");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    /// Opens an event stream and returns its first event once it arrives;
+    /// the connection is closed afterwards
+    pub fn first_event(&self, path: &str) -> String {
+        let mut stream = self.request(path, &[]);
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+
+        let mut raw = Vec::new();
+        let mut buffer = [0u8; 4096];
+        loop {
+            let read = stream.read(&mut buffer).expect("no event before the timeout");
+            assert!(read > 0, "stream closed before the first event");
+            raw.extend_from_slice(&buffer[..read]);
+
+            let text = String::from_utf8_lossy(&raw);
+            if let Some(start) = text.find("event:") {
+                if let Some(end) = text[start..].find("\n\n") {
+                    return text[start..start + end].to_string();
+                }
+            }
+        }
+    }
+
+    /// Opens an event stream and returns the names of the events it sends within `window`;
+    /// the connection is closed afterwards
+    pub fn event_names_within(&self, path: &str, window: Duration) -> Vec<String> {
+        let mut stream = self.request(path, &[]);
+        let deadline = Instant::now() + window;
+
+        let mut raw = Vec::new();
+        let mut buffer = [0u8; 4096];
+        while let Some(left) = deadline.checked_duration_since(Instant::now()).filter(|left| !left.is_zero()) {
+            stream.set_read_timeout(Some(left)).unwrap();
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => raw.extend_from_slice(&buffer[..read]),
+            }
+        }
+
+        String::from_utf8_lossy(&raw)
+            .lines()
+            .filter_map(|line| line.strip_prefix("event:"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn request(&self, path: &str, headers: &[(&str, &str)]) -> TcpStream {
+        let mut stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        let mut request = format!("GET {} HTTP/1.1\r\nHost: 127.0.0.1\r\nConnection: close\r\n", path);
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).unwrap();
+        stream
+    }
+}
+
+impl Drop for QuicksysServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Joins the chunks of a chunked body
+fn dechunk(mut body: &[u8]) -> Vec<u8> {
+    let mut joined = Vec::new();
+    while let Some(line_end) = body.windows(2).position(|w| w == b"\r\n") {
+        let size_line = String::from_utf8_lossy(&body[..line_end]).into_owned();
+        let size = usize::from_str_radix(size_line.split(';').next().unwrap().trim(), 16).unwrap();
+        if size == 0 {
+            break;
+        }
+        let start = line_end + 2;
+        joined.extend_from_slice(&body[start..start + size]);
+        body = &body[start + size + 2..];
+    }
+    joined
+}
+
+/// Path to a checked-in fixture
+pub fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(path)
+}
+
+/// An HTTP request as seen by the fake Tally server
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub request_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The body decoded as UTF-8, or UTF-16LE when it starts with a BOM
+    pub fn body_text(&self) -> String {
+        match self.body.as_slice() {
+            [0xFF, 0xFE, rest @ ..] => {
+                let units: Vec<u16> = rest.chunks_exact(2).map(|p| u16::from_le_bytes([p[0], p[1]])).collect();
+                String::from_utf16_lossy(&units)
+            }
+            body => String::from_utf8_lossy(body).into_owned(),
+        }
+    }
+}
+
+/// How the fake server frames its reply
+pub enum Reply {
+    /// UTF-8 body with Content-Length
+    Xml(String),
+    /// UTF-16LE body with BOM and `charset=utf-16`
+    Utf16(String),
+    /// UTF-8 body sent with `Transfer-Encoding: chunked` in pieces of the given size
+    Chunked(String, usize),
+    /// Raw bytes written verbatim, for malformed responses
+    Raw(Vec<u8>),
+    /// UTF-8 body with Content-Length, written one byte at a time with the given pause after each
+    Drip(String, Duration),
+}
+
+/// A stand-in Tally XML gateway listening on an ephemeral localhost port
+pub struct FakeTallyServer {
+    pub endpoint: String,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+}
+
+impl FakeTallyServer {
+    /// Starts a server that answers every request with `respond(request)`
+    pub fn start(respond: impl Fn(&RecordedRequest) -> Reply + Send + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if let Some(request) = read_request(&stream) {
+                    let reply = respond(&request);
+                    recorded.lock().unwrap().push(request);
+                    write_reply(stream, reply);
+                }
+            }
+        });
+
+        Self { endpoint, requests }
+    }
+
+    /// Starts a server that answers every request with the same reply
+    pub fn with_fixed_reply(body: &'static str) -> Self {
+        Self::start(move |_| Reply::Xml(body.to_string()))
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).ok()?;
+
+    Some(RecordedRequest {
+        request_line: request_line.trim_end().to_string(),
+        headers,
+        body,
+    })
+}
+
+fn write_reply(mut stream: TcpStream, reply: Reply) {
+    let bytes = match reply {
+        Reply::Xml(body) => {
+            let mut bytes = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/xml; charset=utf-8\r\nContent-Length: {}\r\n\r\n",
+                body.len()
+            ).into_bytes();
+            bytes.extend_from_slice(body.as_bytes());
+            bytes
+        }
+        Reply::Utf16(body) => {
+            let mut encoded = vec![0xFF, 0xFE];
+            for unit in body.encode_utf16() {
+                encoded.extend_from_slice(&unit.to_le_bytes());
+            }
+            let mut bytes = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/xml; charset=utf-16\r\nContent-Length: {}\r\n\r\n",
+                encoded.len()
+            ).into_bytes();
+            bytes.extend_from_slice(&encoded);
+            bytes
+        }
+        Reply::Chunked(body, size) => {
+            let mut bytes = b"HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec();
+            for chunk in body.as_bytes().chunks(size) {
+                bytes.extend_from_slice(format!("{:x};ext=1\r\n", chunk.len()).as_bytes());
+                bytes.extend_from_slice(chunk);
+                bytes.extend_from_slice(b"\r\n");
+            }
+            bytes.extend_from_slice(b"0\r\n\r\n");
+            bytes
+        }
+        Reply::Raw(bytes) => bytes,
+        Reply::Drip(body, pause) => {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: text/xml; charset=utf-8\r\nContent-Length: {}\r\n\r\n", body.len());
+            let _ = stream.write_all(head.as_bytes());
+            for byte in body.bytes() {
+                if stream.write_all(&[byte]).is_err() {
+                    return;
+                }
+                thread::sleep(pause);
+            }
+            return;
+        }
+    };
+
+    let _ = stream.write_all(&bytes);
+}
+
+/// A home directory below the test target directory holding a Wine prefix
+/// (`.wine`) whose registry lists TallyPrime in `C:\Program Files\TallyPrime`,
+/// with `ini` as its tally.ini. Anything left from an earlier run is removed.
+pub fn tally_prefix_home(name: &str, ini: impl AsRef<[u8]>) -> PathBuf {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&home);
+    let install = tally_install_dir(&home);
+    std::fs::create_dir_all(&install).unwrap();
+
+    std::fs::write(
+        home.join(".wine/system.reg"),
+        "WINE REGISTRY Version 2\n\n[Software\\\\Tally Solutions\\\\TallyPrime] 1710498600\n\"InstallDir\"=\"C:\\\\Program Files\\\\TallyPrime\"\n",
+    )
+    .unwrap();
+    std::fs::write(install.join("tally.ini"), ini).unwrap();
+    home
+}
+
+/// The TallyPrime install directory of a `tally_prefix_home`
+pub fn tally_install_dir(home: &Path) -> PathBuf {
+    home.join(".wine/drive_c/Program Files/TallyPrime")
+}
+
+/// Boot time in the fake /proc/stat: 2024-03-15T05:20:00Z
+#[cfg(unix)]
+pub const BOOT_TIME: u64 = 1_710_480_000;
+
+#[cfg(unix)]
+const TCP_HEADER: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode\n";
+
+/// One process in the fake proc tree
+#[cfg(unix)]
+pub struct FakeProcess<'a> {
+    pub pid: u32,
+    pub comm: &'a str,
+    pub args: &'a [&'a str],
+    pub environ: &'a [String],
+    pub start_ticks: Option<u64>,
+    pub rss_kb: Option<u64>,
+    pub sockets: &'a [u64],
+}
+
+/// Builds a proc tree for `HOST_PROC` below the test target directory; `tcp`
+/// and `tcp6` are the socket rows of `net/tcp` and `net/tcp6`
+#[cfg(unix)]
+pub fn proc_tree(name: &str, processes: &[FakeProcess], tcp: &str, tcp6: &str) -> PathBuf {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("net")).unwrap();
+    fs::write(root.join("stat"), format!("cpu  1 2 3 4\nbtime {}\nprocesses 9000\n", BOOT_TIME)).unwrap();
+    fs::write(root.join("net/tcp"), format!("{}{}", TCP_HEADER, tcp)).unwrap();
+    fs::write(root.join("net/tcp6"), format!("{}{}", TCP_HEADER, tcp6)).unwrap();
+
+    for process in processes {
+        let dir = root.join(process.pid.to_string());
+        fs::create_dir_all(dir.join("fd")).unwrap();
+        fs::write(dir.join("comm"), format!("{}\n", process.comm)).unwrap();
+        fs::write(dir.join("cmdline"), process.args.iter().map(|a| format!("{}\0", a)).collect::<String>()).unwrap();
+        fs::write(dir.join("environ"), process.environ.iter().map(|e| format!("{}\0", e)).collect::<String>()).unwrap();
+        if let Some(ticks) = process.start_ticks {
+            // Fields 4 to 21 are irrelevant here; field 22 is the start time
+            let fields = vec!["0"; 18].join(" ");
+            fs::write(dir.join("stat"), format!("{} ({}) S {} {} 0 0\n", process.pid, process.comm, fields, ticks)).unwrap();
+        }
+        if let Some(rss) = process.rss_kb {
+            fs::write(dir.join("status"), format!("Name:\t{}\nVmRSS:\t  {} kB\nThreads:\t12\n", process.comm, rss)).unwrap();
+        }
+        std::os::unix::fs::symlink("/dev/null", dir.join("fd/0")).unwrap();
+        for (fd, inode) in process.sockets.iter().enumerate() {
+            std::os::unix::fs::symlink(format!("socket:[{}]", inode), dir.join(format!("fd/{}", fd + 3))).unwrap();
+        }
+    }
+    root
+}
+
+/// A `net/tcp` row for a socket listening on 0.0.0.0:`port`
+pub fn tcp_listen_row(port: u16, inode: u64) -> String {
+    format!(
+        "   0: 00000000:{:04X} 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 {} 1 0000000000000000 100 0 0 10 0\n",
+        port, inode
+    )
+}
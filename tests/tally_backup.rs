@@ -0,0 +1,151 @@
+//! Backup freshness: newest archive per company, staleness warnings and `--check`
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process::Output;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::{Value, json};
+use support::{run_quicksys_with_env, tally_install_dir, tally_prefix_home};
+
+const DAY: u64 = 86_400;
+
+/// A home directory with a Wine prefix holding TallyPrime and companies 10000 and 10001
+fn prefix_home(name: &str, ini: &str) -> PathBuf {
+    prefix_home_with(name, ini, &["10000", "10001"])
+}
+
+fn prefix_home_with(name: &str, ini: &str, companies: &[&str]) -> PathBuf {
+    let home = tally_prefix_home(name, ini);
+    for company in companies {
+        let folder = home.join(".wine/drive_c/Users/Public/TallyPrime/data").join(company);
+        fs::create_dir_all(&folder).unwrap();
+        fs::write(folder.join("Company.1800"), company).unwrap();
+    }
+    home
+}
+
+/// Writes a backup archive last modified `age_days` and an hour ago
+fn archive(path: &Path, age_days: u64, size: usize) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, vec![0u8; size]).unwrap();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(UNIX_EPOCH + Duration::from_secs(now - age_days * DAY - 3600))
+        .unwrap();
+}
+
+fn run(home: &Path, extra_args: &[&str]) -> (Value, Output) {
+    let mut args = vec!["--select", "apps,warnings"];
+    args.extend_from_slice(extra_args);
+    run_quicksys_with_env(&args, &[("HOME", home)])
+}
+
+#[test]
+fn reports_newest_backup_per_company() {
+    let home = prefix_home("backup-fresh-home", "[Tally]\nClient Server=None\nBackup Path=C:\\Backups\n");
+    let drive_c = home.join(".wine/drive_c");
+    archive(&drive_c.join("Backups/10000/TBK1800.001"), 2, 2048);
+    archive(&drive_c.join("Backups/10001/TBK1800.001"), 1, 1024);
+    archive(&drive_c.join("TallyBackup/10001/tbk900.002"), 30, 512);
+    fs::write(drive_c.join("Backups/notes.txt"), "not a backup").unwrap();
+
+    let (report, output) = run(&home, &["--check"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(report.get("warnings").is_none());
+
+    let backups = &report["apps"]["tally"][0]["backups"];
+    assert_eq!(backups[0]["company_number"], "10000");
+    assert_eq!(backups[0]["path"], drive_c.join("Backups/10000/TBK1800.001").to_string_lossy().as_ref());
+    assert_eq!(backups[0]["size_bytes"], 2048);
+    assert_eq!(backups[0]["age_days"], 2);
+    assert_eq!(backups[0]["stale"], false);
+    assert!(backups[0]["last_modified"].as_str().is_some_and(|t| t.ends_with('Z')));
+
+    // The newest of the company's archives across the backup directories
+    assert_eq!(backups[1]["company_number"], "10001");
+    assert_eq!(backups[1]["path"], drive_c.join("Backups/10001/TBK1800.001").to_string_lossy().as_ref());
+    assert_eq!(backups[1]["age_days"], 1);
+    assert_eq!(backups.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn credits_a_shared_archive_to_the_only_company() {
+    let home = prefix_home_with("backup-single-home", "[Tally]\nClient Server=None\n", &["10000"]);
+    let archive_path = tally_install_dir(&home).join("Backup/TBK1800.003");
+    archive(&archive_path, 1, 100);
+
+    let (report, output) = run(&home, &["--check"]);
+    assert!(output.status.success(), "{:#}", report);
+    let backups = report["apps"]["tally"][0]["backups"].as_array().unwrap();
+    assert_eq!(backups.len(), 1, "{:#?}", backups);
+    assert_eq!(backups[0]["company_number"], "10000");
+    assert_eq!(backups[0]["path"], archive_path.to_string_lossy().as_ref());
+    assert_eq!(backups[0]["age_days"], 1);
+}
+
+#[test]
+fn shared_archive_of_several_companies_belongs_to_none() {
+    let home = prefix_home("backup-shared-home", "[Tally]\nClient Server=None\n");
+    let shared = tally_install_dir(&home).join("Backup/TBK1800.001");
+    archive(&shared, 0, 100);
+    archive(&tally_install_dir(&home).join("Backup/10001/TBK1800.001"), 1, 100);
+
+    let (report, output) = run(&home, &["--check"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        report["warnings"],
+        json!([{"code": "tally_backup_stale", "message": "TallyPrime company 10000 has no backup"}])
+    );
+
+    let backups = &report["apps"]["tally"][0]["backups"];
+    assert_eq!(backups[0], json!({"company_number": "10000", "stale": true}));
+    assert_eq!(backups[1]["age_days"], 1);
+    assert_eq!(backups[2]["company_number"], "unknown");
+    assert_eq!(backups[2]["path"], shared.to_string_lossy().as_ref());
+    assert_eq!(backups[2]["age_days"], 0);
+    assert_eq!(backups[2]["stale"], false);
+}
+
+#[test]
+fn check_fails_on_stale_backups() {
+    let home = prefix_home("backup-stale-home", "[Tally]\nClient Server=None\n");
+    archive(&tally_install_dir(&home).join("Backup/10000/TBK1800.001"), 3, 100);
+    archive(&tally_install_dir(&home).join("Backup/10001/TBK1800.001"), 0, 100);
+
+    let (report, output) = run(&home, &["--backup-max-age-days", "2", "--check"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(
+        report["warnings"],
+        json!([{"code": "tally_backup_stale", "message": "TallyPrime company 10000: newest backup is 3 days old (limit 2)"}])
+    );
+    assert!(String::from_utf8_lossy(&output.stderr).contains("warning: TallyPrime company 10000"));
+
+    let backups = &report["apps"]["tally"][0]["backups"];
+    assert_eq!(backups[0]["stale"], true);
+    assert_eq!(backups[1]["stale"], false);
+}
+
+#[test]
+fn warns_about_companies_without_backups() {
+    let home = prefix_home("backup-none-home", "[Tally]\nClient Server=None\n");
+
+    // Without --check the warnings are reported but do not fail the run
+    let (report, output) = run(&home, &[]);
+    assert!(output.status.success());
+    assert_eq!(report["warnings"].as_array().unwrap().len(), 2);
+    assert_eq!(report["warnings"][0]["message"], "TallyPrime company 10000 has no backup");
+    assert_eq!(report["apps"]["tally"][0]["backups"][1], json!({"company_number": "10001", "stale": true}));
+
+    // --select leaving warnings out does not hide them from --check
+    let (_, output) = run_quicksys_with_env(&["--select", "os", "--check"], &[("HOME", &home)]);
+    assert_eq!(output.status.code(), Some(2));
+}
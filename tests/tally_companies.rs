@@ -0,0 +1,90 @@
+//! Company discovery through the XML API, against recorded Tally responses
+
+#![cfg(feature = "tally-xml")]
+
+mod support;
+
+use std::fs;
+
+use support::{FakeTallyServer, Reply, fixture, run_quicksys};
+
+const PRODUCT_RESPONSE: &str = "<RESPONSE>TallyPrime Server is Running</RESPONSE>";
+
+/// Serves the recorded company export for collection requests and the banner otherwise
+fn server_with_companies(recording: &str, utf16: bool) -> FakeTallyServer {
+    let companies = fs::read_to_string(fixture(recording)).unwrap();
+
+    FakeTallyServer::start(move |request| {
+        let body = request.body_text();
+        if !body.contains("<TYPE>Collection</TYPE>") {
+            return Reply::Xml(PRODUCT_RESPONSE.to_string());
+        }
+        if utf16 {
+            Reply::Utf16(companies.clone())
+        } else {
+            Reply::Xml(companies.clone())
+        }
+    })
+}
+
+#[test]
+fn lists_companies_from_tally_prime() {
+    let server = server_with_companies("tally/companies_prime.xml", false);
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(output.status.success());
+
+    let companies = report["companies"].as_array().unwrap();
+    assert_eq!(companies.len(), 2);
+
+    let first = &companies[0];
+    assert_eq!(first["name"], "Sharma Traders & Sons");
+    assert_eq!(first["guid"], "7f3c2a10-4b9e-4d61-9a2f-0c1d2e3f4a5b");
+    assert_eq!(first["company_number"], "10000");
+    assert_eq!(first["books_from"], "2023-04-01");
+    assert_eq!(first["last_voucher_date"], "2024-03-15");
+    assert_eq!(first["data_path"], "C:\\Users\\Public\\TallyPrime\\data\\10000");
+
+    // Empty dates are omitted rather than reported as blank strings
+    assert!(companies[1].get("last_voucher_date").is_none());
+    // A folder whose name merely ends in the company number is not the company folder
+    assert_eq!(companies[1]["data_path"], "D:\\Archive\\110001\\10001");
+}
+
+#[test]
+fn lists_companies_from_erp9_with_textual_dates() {
+    let server = server_with_companies("tally/companies_erp9.xml", true);
+
+    let (report, _) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+
+    let company = &report["companies"][0];
+    assert_eq!(company["name"], "Gupta Medicals");
+    assert_eq!(company["books_from"], "2019-04-01");
+    assert_eq!(company["last_voucher_date"], "2020-03-31");
+    // Already points at the company folder, so it is not joined twice
+    assert_eq!(company["data_path"], "D:\\Tally.ERP9\\Data\\00012");
+}
+
+#[test]
+fn sends_company_collection_export() {
+    let server = server_with_companies("tally/companies_prime.xml", false);
+
+    run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+
+    let export = server.requests().into_iter()
+        .map(|r| r.body_text())
+        .find(|body| body.contains("<TALLYREQUEST>Export</TALLYREQUEST>"))
+        .expect("no export request was sent");
+    assert!(export.contains("<TYPE>Company</TYPE>"));
+    assert!(export.contains("CompanyNumber"));
+}
+
+#[test]
+fn reports_tally_line_errors() {
+    let server = server_with_companies("tally/line_error.xml", false);
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    // The gateway answered, so the probe itself still succeeds
+    assert!(output.status.success());
+    assert!(report["companies"]["error"].as_str().unwrap().contains("Could not find Report"));
+}
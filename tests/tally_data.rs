@@ -0,0 +1,107 @@
+//! Offline inspection of synthetic Tally data directories
+
+mod support;
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use support::run_quicksys;
+
+/// 2024-03-15T10:30:00Z
+const MODIFIED: u64 = 1_710_498_600;
+
+fn write_file(path: &Path, size: usize, modified_secs: u64) {
+    fs::write(path, vec![0u8; size]).unwrap();
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(UNIX_EPOCH + Duration::from_secs(modified_secs))
+        .unwrap();
+}
+
+/// Builds a data directory with a Prime company, an ERP 9 company, a migrated
+/// company holding both formats, and folders that must be ignored
+fn synthetic_data_dir(name: &str) -> PathBuf {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&root);
+
+    let prime = root.join("10000");
+    fs::create_dir_all(&prime).unwrap();
+    write_file(&prime.join("Company.1800"), 4096, MODIFIED - 3600);
+    write_file(&prime.join("TranMgr.1800"), 8192, MODIFIED);
+    write_file(&prime.join("Manager.1800"), 1024, MODIFIED - 60);
+    write_file(&prime.join("notes.txt"), 10, MODIFIED + 3600);
+
+    let erp9 = root.join("00012");
+    fs::create_dir_all(&erp9).unwrap();
+    write_file(&erp9.join("Company.900"), 2048, MODIFIED);
+    write_file(&erp9.join("Tran.900"), 512, MODIFIED);
+
+    let migrated = root.join("10001");
+    fs::create_dir_all(&migrated).unwrap();
+    write_file(&migrated.join("Company.900"), 100, MODIFIED);
+    write_file(&migrated.join("Company.1800"), 200, MODIFIED);
+
+    // Not company folders: a non-numeric name and a numbered folder without a Company file
+    fs::create_dir_all(root.join("Backup")).unwrap();
+    write_file(&root.join("Backup").join("Company.900"), 1, MODIFIED);
+    fs::create_dir_all(root.join("10002")).unwrap();
+    write_file(&root.join("10002").join("Tran.1800"), 1, MODIFIED);
+
+    root
+}
+
+#[test]
+fn lists_numbered_company_folders() {
+    let root = synthetic_data_dir("tally_data_list");
+
+    let (folders, output) = run_quicksys(&["tally", "data", root.to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let numbers: Vec<&str> = folders.as_array().unwrap().iter()
+        .map(|f| f["company_number"].as_str().unwrap())
+        .collect();
+    assert_eq!(numbers, ["00012", "10000", "10001"]);
+}
+
+#[test]
+fn reports_sizes_times_and_format() {
+    let root = synthetic_data_dir("tally_data_details");
+
+    let (folders, _) = run_quicksys(&["tally", "data", root.to_str().unwrap()]);
+
+    let prime = &folders[1];
+    assert_eq!(prime["data_format"], "TallyPrime");
+    assert_eq!(prime["company_file_size_bytes"], 4096);
+    // notes.txt is not a Tally data file
+    assert_eq!(prime["file_count"], 3);
+    assert_eq!(prime["total_size_bytes"], 4096 + 8192 + 1024);
+    assert_eq!(prime["last_modified"], "2024-03-15T10:30:00Z");
+
+    let erp9 = &folders[0];
+    assert_eq!(erp9["data_format"], "TallyERP9");
+    assert_eq!(erp9["total_size_bytes"], 2048 + 512);
+
+    // A folder migrated from ERP 9 keeps the old file; the Prime one is live
+    let migrated = &folders[2];
+    assert_eq!(migrated["data_format"], "TallyPrime");
+    assert_eq!(migrated["company_file_size_bytes"], 200);
+}
+
+#[test]
+fn accepts_a_single_company_folder() {
+    let root = synthetic_data_dir("tally_data_single");
+
+    let (folders, _) = run_quicksys(&["tally", "data", root.join("00012").to_str().unwrap()]);
+    assert_eq!(folders.as_array().unwrap().len(), 1);
+    assert_eq!(folders[0]["company_number"], "00012");
+}
+
+#[test]
+fn missing_directory_yields_empty_list() {
+    let (folders, output) = run_quicksys(&["tally", "data", "/nonexistent/tally/data"]);
+    assert!(output.status.success());
+    assert_eq!(folders, serde_json::json!([]));
+}
@@ -0,0 +1,152 @@
+//! Gateway discovery with `--tally-discover`: ports Tally processes listen on
+//! and the `--tally-ports` range, fingerprinted with the product request
+
+#![cfg(all(not(windows), feature = "tally-xml"))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use support::{FakeProcess, FakeTallyServer, Reply, detect_tally, fixture, proc_tree, run_quicksys_with_env, tally_prefix_home, tcp_listen_row};
+
+const PRODUCT_RESPONSE: &str =
+    "<ENVELOPE><PRODUCT>TallyPrime Gold</PRODUCT><VERSION>4.1.1</VERSION><EDITION>Gold</EDITION></ENVELOPE>";
+
+fn detect(home: &Path, proc_root: &Path, extra_args: &[&str]) -> Vec<Value> {
+    detect_tally(extra_args, &[("HOME", home), ("HOST_PROC", proc_root)])
+}
+
+fn port(server: &FakeTallyServer) -> u16 {
+    server.endpoint.rsplit(':').next().unwrap().parse().unwrap()
+}
+
+fn empty_home(name: &str) -> PathBuf {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&home).unwrap();
+    home
+}
+
+#[test]
+fn finds_gateway_a_tally_process_listens_on() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+    let home = fixture("wine/home");
+    let proc_root = proc_tree(
+        "proc-discovery",
+        &[FakeProcess {
+            pid: 4242,
+            comm: "tally.exe",
+            args: &["C:\\Program Files\\TallyPrime\\tally.exe"],
+            environ: &[format!("WINEPREFIX={}", home.join(".wine").display())],
+            start_ticks: Some(250_000),
+            rss_kb: Some(183_456),
+            sockets: &[61001],
+        }],
+        &tcp_listen_row(port(&server), 61001),
+        "",
+    );
+
+    // tally.ini turns the server off, so the configured probe never asks
+    let installs = detect(&home, &proc_root, &[]);
+    assert!(installs[0].get("gateway").is_none());
+    assert!(server.requests().is_empty());
+
+    let installs = detect(&home, &proc_root, &["--tally-discover", "--tally-ports", "1-1"]);
+    let prime = &installs[0];
+    assert_eq!(prime["gateway"], server.endpoint.as_str());
+    assert_eq!(prime["version"], "4.1.1");
+    assert_eq!(prime["detection_sources"], json!(["registry_uninstall", "registry_vendor", "process", "http"]));
+    assert_eq!(prime["running"][0]["listening_ports"], json!([port(&server)]));
+}
+
+#[test]
+fn scans_candidate_range_and_reports_unclaimed_gateways() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+    let home = empty_home("discovery-empty-home");
+    let proc_root = proc_tree("proc-discovery-empty", &[], "", "");
+    let range = format!("{0}-{0}", port(&server));
+
+    assert!(detect(&home, &proc_root, &[]).is_empty());
+
+    // No installation was found on disk; the gateway itself is the evidence
+    let installs = detect(&home, &proc_root, &["--tally-discover", "--tally-ports", &range]);
+    assert_eq!(installs.len(), 1, "{:#?}", installs);
+    assert_eq!(installs[0]["variant"], "TallyPrime");
+    assert_eq!(installs[0]["version"], "4.1.1");
+    assert_eq!(installs[0]["gateway"], server.endpoint.as_str());
+    assert_eq!(installs[0]["detection_sources"], json!(["http"]));
+}
+
+#[test]
+fn credits_a_gateway_to_the_installation_configured_for_it() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+    let proc_root = proc_tree("proc-discovery-two-primes", &[], "", "");
+
+    // Two TallyPrime prefixes; only the second one's tally.ini names the gateway's port
+    let home = tally_prefix_home("discovery-two-primes-home", "Client Server=None\n");
+    let office = home.join("Games/office");
+    let install = office.join("drive_c/Program Files/TallyPrime");
+    fs::create_dir_all(&install).unwrap();
+    fs::copy(home.join(".wine/system.reg"), office.join("system.reg")).unwrap();
+    fs::write(install.join("tally.ini"), format!("Port={}\n", port(&server))).unwrap();
+
+    for extra_args in [&[][..], &["--tally-discover", "--tally-ports", "1-1"]] {
+        let installs = detect(&home, &proc_root, extra_args);
+        assert_eq!(installs.len(), 2, "{:#?}", installs);
+        assert!(installs[0].get("gateway").is_none(), "{:#?}", installs);
+        assert!(installs[1]["wine_prefix"].as_str().unwrap().ends_with("Games/office"));
+        assert_eq!(installs[1]["gateway"], server.endpoint.as_str());
+    }
+}
+
+#[test]
+fn configured_gateways_share_one_timeout() {
+    // Both gateways answer the product request, then stall on the exports that follow it
+    let stalling = || FakeTallyServer::start(|request| {
+        if request.body_text().contains("<TALLYREQUEST>Export</TALLYREQUEST>") {
+            thread::sleep(Duration::from_secs(3));
+        }
+        Reply::Xml(PRODUCT_RESPONSE.to_string())
+    });
+    let (first, second) = (stalling(), stalling());
+    let home = tally_prefix_home("discovery-two-gateways-home", format!("Port={}\n", port(&first)));
+    let office = home.join("Games/office");
+    let install = office.join("drive_c/Program Files/TallyPrime");
+    fs::create_dir_all(&install).unwrap();
+    fs::copy(home.join(".wine/system.reg"), office.join("system.reg")).unwrap();
+    fs::write(install.join("tally.ini"), format!("Port={}\n", port(&second))).unwrap();
+    let proc_root = proc_tree("proc-discovery-two-gateways", &[], "", "");
+
+    // The first gateway's exports use up the timeout, leaving none for the second gateway
+    let started = Instant::now();
+    let installs = detect(&home, &proc_root, &["--timeout-ms", "1000"]);
+    assert!(started.elapsed() < Duration::from_millis(1800), "took {:?}", started.elapsed());
+    assert_eq!(installs[0]["gateway"], first.endpoint.as_str());
+    assert!(installs[1].get("gateway").is_none(), "{:#?}", installs);
+}
+
+#[test]
+fn ignores_responders_that_are_not_tally() {
+    let server = FakeTallyServer::with_fixed_reply("<html><body>It works!</body></html>");
+    let home = empty_home("discovery-other-home");
+    let proc_root = proc_tree("proc-discovery-other", &[], "", "");
+    let range = format!("{0}-{0}", port(&server));
+
+    let installs = detect(&home, &proc_root, &["--tally-discover", "--tally-ports", &range]);
+    assert!(installs.is_empty(), "{:#?}", installs);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn rejects_invalid_port_ranges() {
+    let (_, output) = run_quicksys_with_env(&["--tally-discover", "--tally-ports", "9010-9000"], &[]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("range starts after it ends"));
+
+    // Discovery searches for the gateway; an explicit one leaves nothing to search for
+    let (_, output) = run_quicksys_with_env(&["--tally-discover", "--tally-http", "127.0.0.1:9000"], &[]);
+    assert!(!output.status.success());
+}
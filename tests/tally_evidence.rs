@@ -0,0 +1,90 @@
+//! Evidence lists and confidence scores of detected installations
+
+#![cfg(not(windows))]
+
+mod support;
+
+use serde_json::{Value, json};
+#[cfg(feature = "tally-xml")]
+use support::FakeTallyServer;
+use support::{detect_tally, ends_with, fixture, proc_tree, run_quicksys};
+
+#[cfg(feature = "tally-xml")]
+const PRODUCT_RESPONSE: &str =
+    "<ENVELOPE><PRODUCT>TallyPrime Gold</PRODUCT><VERSION>4.1.1</VERSION><EDITION>Gold</EDITION></ENVELOPE>";
+
+fn detect(extra_args: &[&str]) -> Vec<Value> {
+    let proc_root = proc_tree("proc-evidence", &[], "", "");
+    detect_tally(extra_args, &[("HOME", &fixture("wine/home")), ("HOST_PROC", &proc_root)])
+}
+
+fn sources(install: &Value) -> Vec<&str> {
+    install["evidence"].as_array().unwrap().iter().map(|e| e["source"].as_str().unwrap()).collect()
+}
+
+#[test]
+fn lists_registry_keys_and_files_behind_each_installation() {
+    let installs = detect(&[]);
+
+    let prime = &installs[0];
+    assert_eq!(sources(prime), ["registry_uninstall", "registry_vendor", "tally_ini"]);
+    assert_eq!(
+        prime["evidence"][0]["detail"],
+        "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{A1B2C3D4-TALLYPRIME}"
+    );
+    assert!(ends_with(&prime["evidence"][2]["detail"], "/drive_c/Program Files/TallyPrime/tally.ini"));
+
+    // The executable read for its version is not listed a second time
+    let erp9 = &installs[1];
+    assert_eq!(sources(erp9), ["file_version", "tally_ini"]);
+    assert!(ends_with(&erp9["evidence"][0]["detail"], "/Tally/ERP9/Tally.exe"));
+}
+
+#[test]
+fn scores_files_on_disk_above_registry_traces() {
+    let installs = detect(&[]);
+
+    // Registry keys and tally.ini, but no Tally.exe: what an uninstall leaves behind
+    let prime = installs[0]["confidence"].as_u64().unwrap();
+    let erp9 = installs[1]["confidence"].as_u64().unwrap();
+    let bottle = installs[2]["confidence"].as_u64().unwrap();
+    assert!(prime < 40, "{}", prime);
+    assert!(bottle < prime);
+    assert!(erp9 > prime);
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn live_gateway_raises_confidence() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+    let port = server.endpoint.rsplit(':').next().unwrap();
+    let range = format!("{0}-{0}", port);
+
+    let before = detect(&[])[0]["confidence"].as_u64().unwrap();
+    let installs = detect(&["--tally-discover", "--tally-ports", &range]);
+    let prime = &installs[0];
+
+    assert_eq!(sources(prime), ["registry_uninstall", "registry_vendor", "tally_ini", "http"]);
+    assert_eq!(
+        prime["evidence"][3]["detail"],
+        format!("{} answered: TallyPrime 4.1.1 Gold", server.endpoint)
+    );
+    let after = prime["confidence"].as_u64().unwrap();
+    assert!(after >= 80 && after > before, "{} -> {}", before, after);
+}
+
+#[test]
+fn registry_snapshots_carry_evidence_too() {
+    let (report, output) = run_quicksys(&["tally", "registry", fixture("registry/installs.json").to_str().unwrap()]);
+    assert!(output.status.success());
+
+    let prime = report.as_array().unwrap().iter().find(|i| i["variant"] == "TallyPrime").unwrap();
+    assert_eq!(
+        prime["evidence"],
+        json!([
+            {"source": "registry_uninstall", "detail": "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{A1B2C3D4-TALLYPRIME}"},
+            {"source": "registry_vendor", "detail": "HKLM\\SOFTWARE\\Tally Solutions\\TallyPrime"},
+        ])
+    );
+    assert_eq!(prime["confidence"], 25);
+}
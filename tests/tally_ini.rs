@@ -0,0 +1,132 @@
+//! tally.ini in the encodings Tally and editors save it in, and the settings
+//! read from it
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::path::Path;
+
+use serde_json::{Value, json};
+#[cfg(feature = "tally-xml")]
+use support::FakeTallyServer;
+use support::{detect_apps, tally_install_dir, tally_prefix_home};
+
+fn installation(home: &Path) -> Value {
+    detect_apps(&[], &[("HOME", home)])["tally"][0].clone()
+}
+
+fn config(home: &Path) -> Value {
+    let mut config = installation(home)["config"].clone();
+    config.as_object_mut().unwrap().remove("ini_path");
+    config
+}
+
+fn utf16(text: &str, bom: [u8; 2], encode: fn(u16) -> [u8; 2]) -> Vec<u8> {
+    bom.into_iter().chain(text.encode_utf16().flat_map(encode)).collect()
+}
+
+const INI: &str = "[Tally]\r\nData=C:\\Données\\Tally\r\nLoad=10000\r\nClient Server=None\r\n";
+
+fn expected() -> Value {
+    json!({ "data_directory": "C:\\Données\\Tally", "client_server": "None", "load_companies": ["10000"] })
+}
+
+#[test]
+fn reads_every_setting() {
+    let home = tally_prefix_home(
+        "ini-settings-home",
+        "; written by TallyPrime\n[Tally]\nData = \"D:\\Tally Data\"\nBackup Path=Backups\nServer Port=9100\n\
+         ODBC Server=Yes\nClient Server=None\nDefault Companies=Yes\nLoad=10000\nload=10002\nLoad=\n\
+         User TDL=yes\nTDL=C:\\TDL\\gst.tcp\nUnknown Key=1\nnot a setting\n",
+    );
+    let install = tally_install_dir(&home);
+
+    let config = installation(&home)["config"].clone();
+    assert_eq!(config["ini_path"], install.join("tally.ini").to_string_lossy().as_ref());
+    assert_eq!(
+        config,
+        json!({
+            "ini_path": config["ini_path"],
+            "data_directory": "D:\\Tally Data",
+            // Relative to the install directory
+            "backup_directory": install.join("Backups").to_string_lossy(),
+            "port": 9100,
+            "odbc_server": true,
+            "client_server": "None",
+            "default_companies": true,
+            "load_companies": ["10000", "10002"],
+            "user_tdl": true,
+            "tdl_files": ["C:\\TDL\\gst.tcp"],
+        })
+    );
+}
+
+#[test]
+fn parses_every_spelling_of_a_boolean() {
+    let spellings = [
+        ("Yes", json!(true)),
+        ("TRUE", json!(true)),
+        ("1", json!(true)),
+        ("no", json!(false)),
+        ("False", json!(false)),
+        ("0", json!(false)),
+        ("maybe", Value::Null),
+    ];
+    for (value, expected) in spellings {
+        let home = tally_prefix_home("ini-bool-home", format!("Client Server=None\nDefault Companies={}\n", value));
+        assert_eq!(config(&home)["default_companies"], expected, "Default Companies={}", value);
+    }
+}
+
+#[test]
+fn ignores_an_invalid_port() {
+    let home = tally_prefix_home("ini-port-home", "Port=99999\nClient Server=None\n");
+    assert_eq!(config(&home), json!({ "client_server": "None" }));
+}
+
+#[test]
+fn decodes_ansi() {
+    // "é" is 0xE9 in Windows-1252 and 0x80 is the euro sign; neither is valid UTF-8
+    let ansi = b"[Tally]\r\nData=C:\\Donn\xE9es\\Tally \x80\r\nClient Server=None\r\n";
+    let home = tally_prefix_home("ini-ansi-home", ansi);
+    assert_eq!(config(&home)["data_directory"], "C:\\Données\\Tally €");
+}
+
+#[test]
+fn decodes_utf8_with_and_without_a_bom() {
+    let home = tally_prefix_home("ini-utf8-home", INI);
+    assert_eq!(config(&home), expected());
+
+    // The byte order mark would otherwise stick to the first key
+    let bom = [b"\xEF\xBB\xBF".as_slice(), INI.trim_start_matches("[Tally]\r\n").as_bytes()].concat();
+    let home = tally_prefix_home("ini-utf8-bom-home", bom);
+    assert_eq!(config(&home), expected());
+}
+
+#[test]
+fn decodes_utf16() {
+    let home = tally_prefix_home("ini-utf16le-home", utf16(INI, [0xFF, 0xFE], u16::to_le_bytes));
+    assert_eq!(config(&home), expected());
+
+    let home = tally_prefix_home("ini-utf16be-home", utf16(INI, [0xFE, 0xFF], u16::to_be_bytes));
+    assert_eq!(config(&home), expected());
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn skips_the_gateway_when_the_server_is_off() {
+    let server = FakeTallyServer::with_fixed_reply("<ENVELOPE><PRODUCT>TallyPrime</PRODUCT></ENVELOPE>");
+    let port = server.endpoint.rsplit(':').next().unwrap();
+
+    for setting in ["ODBC Server=No", "Client Server=Client", "Client Server=none"] {
+        let home = tally_prefix_home("ini-server-off-home", format!("Port={}\n{}\n", port, setting));
+        assert_eq!(installation(&home).get("gateway"), None, "{}", setting);
+    }
+    assert!(server.requests().is_empty());
+
+    for setting in ["ODBC Server=Yes", "Client Server=Both"] {
+        let home = tally_prefix_home("ini-server-on-home", format!("Port={}\n{}\n", port, setting));
+        assert_eq!(installation(&home)["gateway"], server.endpoint, "{}", setting);
+    }
+}
@@ -0,0 +1,145 @@
+//! License details from the XML API and from license files
+
+mod support;
+
+use std::path::Path;
+
+use serde_json::Value;
+#[cfg(feature = "tally-xml")]
+use serde_json::json;
+#[cfg(feature = "tally-xml")]
+use support::{FakeTallyServer, Reply, detect_tally};
+use support::{fixture, run_quicksys};
+
+/// Answers `$$LicenseInfo` function exports from `answers` and the product request with a banner
+#[cfg(feature = "tally-xml")]
+fn license_server(answers: &'static [(&'static str, &'static str)]) -> FakeTallyServer {
+    FakeTallyServer::start(move |request| {
+        let body = request.body_text();
+        if !body.contains("<TYPE>Function</TYPE>") {
+            return Reply::Xml("<RESPONSE>TallyPrime Server is Running</RESPONSE>".to_string());
+        }
+        let result = answers
+            .iter()
+            .find(|(param, _)| body.contains(&format!("<PARAM>{}</PARAM>", param)))
+            .map(|(_, value)| *value)
+            .unwrap_or("");
+        Reply::Xml(format!(
+            "<ENVELOPE><HEADER><VERSION>1</VERSION><STATUS>1</STATUS></HEADER>\
+             <BODY><DESC></DESC><DATA><RESULT TYPE=\"String\">{}</RESULT></DATA></BODY></ENVELOPE>",
+            result
+        ))
+    })
+}
+
+#[cfg(feature = "tally-xml")]
+const GOLD: &[(&str, &str)] = &[
+    ("SerialNumber", "790012345"),
+    ("IsEducationalMode", "No"),
+    ("IsGold", "Yes"),
+    ("IsSilver", "No"),
+    ("IsLicensedMode", "Yes"),
+    ("TSSExpiryDate", "31-Mar-2025"),
+];
+
+#[cfg(feature = "tally-xml")]
+fn probe(server: &FakeTallyServer, extra_args: &[&str]) -> Value {
+    let mut args = vec!["tally", "probe", "--endpoint", &server.endpoint];
+    args.extend_from_slice(extra_args);
+    let (report, output) = run_quicksys(&args);
+    assert!(output.status.success());
+    report["license"].clone()
+}
+
+fn offline_installs(extra_args: &[&str]) -> Vec<Value> {
+    let root = fixture("offline/volume");
+    let mut args = vec!["--offline-windows", root.to_str().unwrap()];
+    args.extend_from_slice(extra_args);
+    let (report, output) = run_quicksys(&args);
+    assert!(output.status.success());
+    report["apps"]["tally"].as_array().cloned().unwrap()
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn reads_license_from_the_xml_api() {
+    let server = license_server(GOLD);
+
+    assert_eq!(
+        probe(&server, &[]),
+        json!({
+            "serial_number": "790012345",
+            "license_type": "Gold",
+            "multi_user": true,
+            "tss_expiry": "2025-03-31",
+            "activated": true,
+            "source": "http"
+        })
+    );
+
+    let queries: Vec<String> = server.requests().iter().map(|r| r.body_text()).filter(|b| b.contains("$$LicenseInfo")).collect();
+    assert!(queries.iter().any(|q| q.contains("<FUNCPARAMLIST><PARAM>SerialNumber</PARAM></FUNCPARAMLIST>")));
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn educational_mode_is_not_activated() {
+    let server = license_server(&[("SerialNumber", ""), ("IsEducationalMode", "Yes")]);
+
+    assert_eq!(
+        probe(&server, &[]),
+        json!({ "license_type": "Educational", "activated": false, "source": "http" })
+    );
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn masks_serials_on_request() {
+    let server = license_server(GOLD);
+    assert_eq!(probe(&server, &["--mask-serials"])["serial_number"], "79001...");
+
+    let installs = offline_installs(&["--mask-serials"]);
+    assert_eq!(installs[1]["license"]["serial_number"], "79001...");
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn gateways_without_license_details_report_an_error() {
+    // Older gateways answer every request with their banner
+    let server = FakeTallyServer::with_fixed_reply("<RESPONSE>TallyPrime Server is Running</RESPONSE>");
+    assert_eq!(probe(&server, &[])["error"], "malformed response: response carries no function result");
+
+    let installs = detect_tally(&["--tally-http", &server.endpoint], &[("HOME", &fixture("wine/home"))]);
+    assert!(installs.iter().all(|i| i.get("license").is_none()));
+}
+
+#[test]
+fn reads_license_files_in_the_install_directory() {
+    let installs = offline_installs(&[]);
+
+    let erp9 = &installs[1]["license"];
+    assert_eq!(erp9["serial_number"], "790012345");
+    assert_eq!(erp9["license_type"], "Gold");
+    assert_eq!(erp9["multi_user"], true);
+    assert_eq!(erp9["tss_expiry"], "2025-03-31");
+    assert_eq!(erp9["activated"], true);
+    assert_eq!(erp9["source"], "license_file");
+    assert!(erp9["license_file"].as_str().unwrap().ends_with("tally.lic"));
+
+    // An encrypted license file only tells that the installation was activated
+    let prime = &installs[0]["license"];
+    assert_eq!(prime["activated"], true);
+    assert!(prime.get("serial_number").is_none());
+    assert!(Path::new(prime["license_file"].as_str().unwrap()).ends_with("Program Files/TallyPrime/tallyprime.lic"));
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn credits_the_gateway_license_to_the_answering_installation() {
+    let server = license_server(GOLD);
+    let installs = detect_tally(&["--tally-http", &server.endpoint], &[("HOME", &fixture("wine/home"))]);
+    let prime = &installs[0];
+    assert_eq!(prime["variant"], "TallyPrime");
+    assert_eq!(prime["license"]["source"], "http");
+    assert_eq!(prime["license"]["tss_expiry"], "2025-03-31");
+}
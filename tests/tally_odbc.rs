@@ -0,0 +1,99 @@
+//! ODBC probe against unixODBC with the SQLite ODBC driver registered under
+//! Tally's driver name, serving tests/fixtures/odbc/tally.db.
+//!
+//! Needs a build with `--features tally-odbc`, unixODBC and the SQLite ODBC
+//! driver (Debian: `libsqliteodbc`); set `SQLITE_ODBC_DRIVER` when the driver
+//! lives elsewhere. Without the driver the tests are skipped, unless
+//! `REQUIRE_ODBC_TESTS` is set, which makes them fail instead.
+
+#![cfg(all(unix, feature = "tally-odbc"))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use support::{detect_apps, fixture};
+
+const DEFAULT_SQLITE_DRIVER: &str = "/usr/lib/x86_64-linux-gnu/odbc/libsqlite3odbc.so";
+
+/// Writes odbcinst.ini and odbc.ini registering the SQLite driver as
+/// "Tally ODBC Driver64" and a `TallyODBC64_9000` DSN over a copy of the
+/// fixture database; None when the driver is not installed and the tests
+/// are not required
+fn odbc_config(name: &str) -> Option<PathBuf> {
+    let driver = std::env::var("SQLITE_ODBC_DRIVER").unwrap_or_else(|_| DEFAULT_SQLITE_DRIVER.to_string());
+    if !Path::new(&driver).exists() {
+        if std::env::var_os("REQUIRE_ODBC_TESTS").is_some() {
+            panic!("REQUIRE_ODBC_TESTS is set but the SQLite ODBC driver is not at {}", driver);
+        }
+        eprintln!("skipping: SQLite ODBC driver not found at {} (set REQUIRE_ODBC_TESTS to fail instead)", driver);
+        return None;
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&dir).unwrap();
+    let database = dir.join("tally.db");
+    fs::copy(fixture("odbc/tally.db"), &database).unwrap();
+
+    fs::write(dir.join("odbcinst.ini"), format!("[Tally ODBC Driver64]\nDriver={}\n", driver)).unwrap();
+    fs::write(
+        dir.join("odbc.ini"),
+        format!("[TallyODBC64_9000]\nDriver=Tally ODBC Driver64\nDatabase={}\n", database.display()),
+    )
+    .unwrap();
+    Some(dir)
+}
+
+/// The TallyPrime installation of the Wine fixture home, with no gateway running
+fn detect_prime(config: &Path, extra_args: &[&str]) -> Value {
+    let apps = detect_apps(extra_args, &[("HOME", &fixture("wine/home")), ("ODBCSYSINI", config)]);
+
+    let prime = apps["tally"][0].clone();
+    assert_eq!(prime["variant"], "TallyPrime");
+    prime
+}
+
+#[test]
+fn lists_companies_through_tally_dsn() {
+    let Some(config) = odbc_config("odbc-dsn") else { return };
+
+    let prime = detect_prime(&config, &[]);
+    let odbc = &prime["odbc"];
+    assert_eq!(odbc["connection"], "DSN=TallyODBC64_9000");
+    assert_eq!(odbc["connected"], true);
+    assert_eq!(odbc["data_sources"], json!([{"name": "TallyODBC64_9000", "driver": "Tally ODBC Driver64"}]));
+
+    let companies = odbc["companies"].as_array().unwrap();
+    assert_eq!(companies.len(), 2);
+    assert_eq!(companies[0]["name"], "Sharma Traders & Sons");
+    assert_eq!(companies[0]["company_number"], "10000");
+    assert_eq!(companies[0]["books_from"], "2023-04-01");
+    assert_eq!(companies[0]["last_voucher_date"], "2024-03-15");
+    assert!(companies[1].get("last_voucher_date").is_none());
+
+    // ODBC is listed as a source but leaves the detected version alone
+    assert_eq!(prime["version"], "4.1");
+    assert_eq!(prime["detection_sources"], json!(["registry_uninstall", "registry_vendor", "odbc"]));
+}
+
+#[test]
+fn reports_why_an_explicit_dsn_failed() {
+    let Some(config) = odbc_config("odbc-missing-dsn") else { return };
+
+    let prime = detect_prime(&config, &["--tally-odbc-dsn", "NoSuchDsn"]);
+    let odbc = &prime["odbc"];
+    assert_eq!(odbc["connection"], "DSN=NoSuchDsn");
+    assert_eq!(odbc["connected"], false);
+    assert!(odbc["error"].as_str().is_some_and(|e| !e.is_empty()));
+    assert!(!prime["detection_sources"].as_array().unwrap().contains(&json!("odbc")));
+}
+
+#[test]
+fn connects_through_driver_on_gateway_port() {
+    let Some(config) = odbc_config("odbc-driver") else { return };
+
+    let prime = detect_prime(&config, &["--tally-odbc-driver", "Tally ODBC Driver64"]);
+    assert_eq!(prime["odbc"]["connection"], "DRIVER={Tally ODBC Driver64};SERVER=127.0.0.1;PORT=9000");
+}
@@ -0,0 +1,120 @@
+//! Running Tally processes, read from a fake proc tree passed in `HOST_PROC`
+//! and attributed to the Wine installations in tests/fixtures/wine/home
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+use support::{FakeProcess, detect_tally, fixture, proc_tree};
+
+fn detect(home: &Path, proc_root: &Path) -> Vec<Value> {
+    detect_tally(&[], &[("HOME", home), ("HOST_PROC", proc_root)])
+}
+
+#[test]
+fn attributes_processes_to_installations() {
+    let home = fixture("wine/home");
+    let prefix = home.join(".wine");
+    let proc_root = proc_tree(
+        "proc-tally",
+        &[
+            FakeProcess {
+                pid: 4242,
+                comm: "tally.exe",
+                args: &["C:\\Program Files\\TallyPrime\\tally.exe", "/NOINITDIALOG"],
+                environ: &[format!("WINEPREFIX={}", prefix.display()), "LANG=en_IN.UTF-8".to_string()],
+                start_ticks: Some(250_000),
+                rss_kb: Some(183_456),
+                sockets: &[55501, 55502, 55503],
+            },
+            // Started from outside Program Files; the prefix comes from the default under $HOME
+            FakeProcess {
+                pid: 4300,
+                comm: "TallyPrime.exe",
+                args: &["C:\\Tally\\TallyPrime.exe"],
+                environ: &[format!("HOME={}", home.display())],
+                start_ticks: Some(400_000),
+                rss_kb: Some(90_000),
+                sockets: &[],
+            },
+            FakeProcess {
+                pid: 5000,
+                comm: "python3",
+                args: &["python3", "-m", "http.server", "9100"],
+                environ: &[],
+                start_ticks: Some(1),
+                rss_kb: Some(1),
+                sockets: &[55504],
+            },
+        ],
+        // 0.0.0.0:9000 LISTEN, an established connection, and python's 0.0.0.0:9100 LISTEN
+        "   0: 00000000:2328 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 55501 1 0000000000000000 100 0 0 10 0\n\
+            1: 0100007F:2328 0100007F:D431 01 00000000:00000000 00:00000000 00000000  1000        0 55502 1 0000000000000000 20 4 30 10 -1\n\
+            2: 00000000:238C 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 55504 1 0000000000000000 100 0 0 10 0\n",
+        // [::]:9999 LISTEN
+        "   0: 00000000000000000000000000000000:270F 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 55503 1 0000000000000000 100 0 0 10 0\n",
+    );
+
+    let installs = detect(&home, &proc_root);
+    assert_eq!(installs.len(), 4, "{:#?}", installs);
+
+    let prime = &installs[0];
+    assert_eq!(prime["variant"], "TallyPrime");
+    assert_eq!(prime["detection_sources"], json!(["registry_uninstall", "registry_vendor", "process"]));
+    assert_eq!(
+        prime["running"],
+        json!([{
+            "pid": 4242,
+            "start_time": "2024-03-15T06:01:40Z",
+            "memory_bytes": 183_456u64 * 1024,
+            "command_line": "C:\\Program Files\\TallyPrime\\tally.exe /NOINITDIALOG",
+            "listening_ports": [9000, 9999],
+        }])
+    );
+
+    // Installations nobody started have no running block
+    assert_eq!(installs[1]["variant"], "TallyERP9");
+    assert!(installs[1].get("running").is_none());
+    assert!(installs[2].get("running").is_none());
+
+    let portable = &installs[3];
+    assert_eq!(portable["variant"], "TallyPrime");
+    assert_eq!(portable["detection_sources"], json!(["process"]));
+    assert_eq!(portable["install_path"], prefix.join("drive_c/Tally").to_string_lossy().as_ref());
+    assert_eq!(portable["wine_prefix"], prefix.to_string_lossy().as_ref());
+    assert_eq!(portable["running"][0]["pid"], 4300);
+    assert_eq!(portable["running"][0]["start_time"], "2024-03-15T06:26:40Z");
+    assert!(portable["running"][0].get("listening_ports").is_none());
+}
+
+#[test]
+fn reports_processes_with_unreadable_details() {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("proc-sparse-home");
+    fs::create_dir_all(&home).unwrap();
+    let proc_root = proc_tree(
+        "proc-sparse",
+        &[FakeProcess {
+            pid: 77,
+            comm: "Tally.exe",
+            args: &["/opt/tally/Tally.exe"],
+            environ: &[],
+            start_ticks: None,
+            rss_kb: None,
+            sockets: &[],
+        }],
+        "",
+        "",
+    );
+
+    // A native path: no Wine prefix, and the directory does not have to exist
+    let installs = detect(&home, &proc_root);
+    assert_eq!(installs.len(), 1, "{:#?}", installs);
+    assert_eq!(installs[0]["variant"], "Tally");
+    assert_eq!(installs[0]["install_path"], "/opt/tally");
+    assert!(installs[0].get("wine_prefix").is_none());
+    assert_eq!(installs[0]["running"], json!([{ "pid": 77, "command_line": "/opt/tally/Tally.exe" }]));
+}
@@ -0,0 +1,105 @@
+//! `quicksys tally query`: report export envelopes and the XML-to-JSON conversion
+
+#![cfg(feature = "tally-xml")]
+
+mod support;
+
+use serde_json::json;
+use support::{FakeTallyServer, Reply, run_quicksys};
+
+const TRIAL_BALANCE: &str = "<ENVELOPE>\
+    <DSPACCNAME><DSPDISPNAME>Capital Account</DSPDISPNAME></DSPACCNAME>\
+    <DSPACCINFO><DSPCLDRAMT><DSPCLDRAMTA></DSPCLDRAMTA></DSPCLDRAMT><DSPCLCRAMT><DSPCLCRAMTA>500000.00</DSPCLCRAMTA></DSPCLCRAMT></DSPACCINFO>\
+    <DSPACCNAME><DSPDISPNAME>Sundry Debtors &amp; Others</DSPDISPNAME></DSPACCNAME>\
+    <DSPACCINFO><DSPCLDRAMT><DSPCLDRAMTA>-125000.00</DSPCLDRAMTA></DSPCLDRAMT><DSPCLCRAMT><DSPCLCRAMTA></DSPCLCRAMTA></DSPCLCRAMT></DSPACCINFO>\
+    </ENVELOPE>";
+
+#[test]
+fn sends_report_export_envelope() {
+    let server = FakeTallyServer::with_fixed_reply(TRIAL_BALANCE);
+    let (_, output) = run_quicksys(&[
+        "tally", "query", "--endpoint", &server.endpoint,
+        "--report", "Trial Balance", "--company", "Sharma & Sons",
+        "--from", "2024-04-01", "--to", "31-Mar-2025",
+    ]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].request_line, "POST / HTTP/1.1");
+    let body = requests[0].body_text();
+    assert!(body.contains("<TALLYREQUEST>Export</TALLYREQUEST><TYPE>Data</TYPE><ID>Trial Balance</ID>"), "{}", body);
+    assert!(body.contains("<SVEXPORTFORMAT>$$SysName:XML</SVEXPORTFORMAT>"));
+    assert!(body.contains("<SVCURRENTCOMPANY>Sharma &amp; Sons</SVCURRENTCOMPANY>"));
+    assert!(body.contains("<SVFROMDATE>20240401</SVFROMDATE><SVTODATE>20250331</SVTODATE>"));
+}
+
+#[test]
+fn converts_response_to_json() {
+    let server = FakeTallyServer::with_fixed_reply(TRIAL_BALANCE);
+    let (report, output) = run_quicksys(&["tally", "query", "--endpoint", &server.endpoint, "--report", "Trial Balance"]);
+    assert!(output.status.success());
+
+    // Only the parameters given are sent
+    assert!(!server.requests()[0].body_text().contains("SVCURRENTCOMPANY"));
+
+    // Repeated tags become arrays; empty elements become empty strings
+    assert_eq!(
+        report["ENVELOPE"]["DSPACCNAME"],
+        json!([{"DSPDISPNAME": "Capital Account"}, {"DSPDISPNAME": "Sundry Debtors & Others"}])
+    );
+    assert_eq!(report["ENVELOPE"]["DSPACCINFO"][0]["DSPCLCRAMT"]["DSPCLCRAMTA"], "500000.00");
+    assert_eq!(report["ENVELOPE"]["DSPACCINFO"][1]["DSPCLCRAMT"]["DSPCLCRAMTA"], "");
+}
+
+#[test]
+fn keeps_attributes_and_decodes_utf16() {
+    let xml = "<ENVELOPE><LEDGER NAME=\"Cash\" RESERVEDNAME=\"\"><PARENT TYPE=\"String\">Cash-in-Hand</PARENT><ISBILLWISEON/></LEDGER></ENVELOPE>";
+    let server = FakeTallyServer::start(move |_| Reply::Utf16(xml.to_string()));
+    let (report, output) = run_quicksys(&["tally", "query", "--endpoint", &server.endpoint, "--report", "List of Accounts"]);
+    assert!(output.status.success());
+    assert_eq!(
+        report["ENVELOPE"]["LEDGER"],
+        json!({"@NAME": "Cash", "@RESERVEDNAME": "", "PARENT": {"@TYPE": "String", "#text": "Cash-in-Hand"}, "ISBILLWISEON": ""})
+    );
+}
+
+#[test]
+fn raw_prints_xml_unchanged() {
+    let server = FakeTallyServer::with_fixed_reply(TRIAL_BALANCE);
+    let (_, output) = run_quicksys(&["tally", "query", "--endpoint", &server.endpoint, "--report", "Trial Balance", "--raw"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), TRIAL_BALANCE);
+}
+
+#[test]
+fn reports_tally_errors() {
+    let server = FakeTallyServer::with_fixed_reply(
+        "<ENVELOPE><HEADER><VERSION>1</VERSION><STATUS>0</STATUS></HEADER><BODY><DATA><LINEERROR>Could not find Report 'Trail Balance'!</LINEERROR></DATA></BODY></ENVELOPE>",
+    );
+    let (report, output) = run_quicksys(&["tally", "query", "--endpoint", &server.endpoint, "--report", "Trail Balance"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(report["report"], "Trail Balance");
+    assert!(report["error"].as_str().unwrap().contains("Could not find Report 'Trail Balance'!"));
+
+    let (_, output) = run_quicksys(&["tally", "query", "--report", "Day Book", "--from", "yesterday"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a date: yesterday"));
+}
+
+#[test]
+fn rejects_days_the_month_does_not_have() {
+    for date in ["20241399", "2024-13-01", "2023-02-29", "2024-04-31", "2024-01-00", "30-Feb-2024", "29-Feb-1900"] {
+        let (_, output) = run_quicksys(&["tally", "query", "--report", "Day Book", "--from", date]);
+        assert_eq!(output.status.code(), Some(2), "{}", date);
+        assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("not a date: {}", date)), "{}", date);
+    }
+
+    // Leap days are kept
+    let server = FakeTallyServer::with_fixed_reply(TRIAL_BALANCE);
+    let (_, output) = run_quicksys(&[
+        "tally", "query", "--endpoint", &server.endpoint, "--report", "Day Book", "--from", "2024-02-29", "--to", "29-Feb-2000",
+    ]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(server.requests()[0].body_text().contains("<SVFROMDATE>20240229</SVFROMDATE><SVTODATE>20000229</SVTODATE>"));
+}
@@ -0,0 +1,176 @@
+//! Registry-based Tally detection, driven by snapshots through
+//! `quicksys tally registry` so every branch runs on any platform
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{Value, json};
+use support::{fixture, run_quicksys};
+
+fn detect_snapshot(path: &std::path::Path) -> (Value, bool) {
+    let (report, output) = run_quicksys(&["tally", "registry", path.to_str().unwrap()]);
+    (report, output.status.success())
+}
+
+/// Writes `snapshot` to a scratch file and detects from it
+fn detect(name: &str, snapshot: Value) -> Vec<Value> {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("registry-{}.json", name));
+    fs::write(&path, snapshot.to_string()).unwrap();
+
+    let (report, ok) = detect_snapshot(&path);
+    assert!(ok, "{}", report);
+    report.as_array().cloned().unwrap()
+}
+
+fn uninstall_entry(display_name: &str) -> Value {
+    json!({
+        "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Entry": {
+            "DisplayName": display_name,
+            "InstallLocation": "C:\\Tally"
+        }
+    })
+}
+
+#[test]
+fn merges_uninstall_and_vendor_keys_of_both_views() {
+    let (report, ok) = detect_snapshot(&fixture("registry/installs.json"));
+    assert!(ok);
+
+    assert_eq!(
+        report,
+        json!([
+            // Subkeys are enumerated in name order, as regedit lists them
+            {
+                "variant": "Tally",
+                "version": "4.1",
+                "install_path": "C:\\Program Files\\Tally Developer",
+                "detection_sources": ["registry_uninstall"],
+                "evidence": [
+                    {"source": "registry_uninstall", "detail": "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\TallyDeveloper"}
+                ],
+                "confidence": 15
+            },
+            {
+                "variant": "TallyPrime",
+                "version": "4.1",
+                "install_path": "C:\\Program Files\\TallyPrime\\",
+                "detection_sources": ["registry_uninstall", "registry_vendor"],
+                "evidence": [
+                    {"source": "registry_uninstall", "detail": "HKLM\\SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\{A1B2C3D4-TALLYPRIME}"},
+                    {"source": "registry_vendor", "detail": "HKLM\\SOFTWARE\\Tally Solutions\\TallyPrime"}
+                ],
+                "confidence": 25
+            },
+            {
+                // The uninstall entry has no version; the vendor key without a path supplies it
+                "variant": "TallyERP9",
+                "version": "6.6.3",
+                "install_path": "C:\\Program Files (x86)\\Tally\\ERP9",
+                "detection_sources": ["registry_uninstall", "registry_vendor"],
+                "evidence": [
+                    {"source": "registry_uninstall", "detail": "HKLM\\SOFTWARE\\WOW6432Node\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Tally.ERP 9"},
+                    {"source": "registry_vendor", "detail": "HKLM\\SOFTWARE\\WOW6432Node\\Tally Solutions\\TallyERP9"}
+                ],
+                "confidence": 25
+            }
+        ])
+    );
+}
+
+#[test]
+fn classifies_variants_from_display_names() {
+    for (display_name, variant) in [
+        ("TallyPrime", "TallyPrime"),
+        ("TallyPrime Server", "TallyPrime"),
+        ("Tally.ERP 9", "TallyERP9"),
+        ("TallyERP9", "TallyERP9"),
+        ("Tally.ERP", "TallyERP9"),
+        ("Tally 7.2", "Tally"),
+    ] {
+        let installs = detect("classify", uninstall_entry(display_name));
+        assert_eq!(installs.len(), 1, "{}", display_name);
+        assert_eq!(installs[0]["variant"], variant, "{}", display_name);
+    }
+
+    // The match is on "Tally", case-sensitively, like the live probe
+    assert!(detect("unrelated", uninstall_entry("Prime Video")).is_empty());
+    assert!(detect("lowercase", uninstall_entry("tallyprime")).is_empty());
+}
+
+#[test]
+fn missing_values_are_left_out() {
+    let installs = detect(
+        "missing",
+        json!({
+            "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\Prime": { "DisplayName": "TallyPrime" },
+            "SOFTWARE\\Microsoft\\Windows\\CurrentVersion\\Uninstall\\NoName": { "InstallLocation": "C:\\Tally" },
+            "SOFTWARE\\Tally Solutions\\Tally.ERP 9": { "Version": "Unknown", "InstallDir": "" }
+        }),
+    );
+
+    let summary: Vec<Value> = installs
+        .iter()
+        .map(|i| {
+            let mut i = i.clone();
+            let fields = i.as_object_mut().unwrap();
+            fields.remove("evidence");
+            fields.remove("confidence");
+            i
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            json!({ "variant": "TallyPrime", "detection_sources": ["registry_uninstall"] }),
+            json!({ "variant": "TallyERP9", "detection_sources": ["registry_vendor"] }),
+        ]
+    );
+}
+
+#[test]
+fn keeps_separate_installations_apart() {
+    let installs = detect(
+        "separate",
+        json!({
+            "SOFTWARE\\Tally Solutions\\TallyPrime": { "InstallDir": "C:\\TallyPrime", "Version": "4.1" },
+            "SOFTWARE\\Tally Solutions\\TallyPrime 5": { "InstallDir": "D:\\TallyPrime5", "Version": "5.0" },
+            "SOFTWARE\\WOW6432Node\\Tally Solutions\\TallyPrime": { "InstallDir": "C:\\TallyPrime\\" }
+        }),
+    );
+
+    assert_eq!(installs.len(), 2, "{:#?}", installs);
+    // Both views name C:\TallyPrime; a source is listed once however often it matched
+    assert_eq!(installs[0]["detection_sources"], json!(["registry_vendor"]));
+    assert_eq!(installs[1]["version"], "5.0");
+}
+
+#[test]
+fn reads_hive_snapshots() {
+    let (report, ok) = detect_snapshot(&fixture("offline/volume/Windows/System32/config/SOFTWARE"));
+    assert!(ok);
+
+    let variants: Vec<&str> = report.as_array().unwrap().iter().map(|i| i["variant"].as_str().unwrap()).collect();
+    assert_eq!(variants, ["TallyPrime", "TallyERP9"]);
+}
+
+#[test]
+fn rejects_malformed_snapshots() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("registry-malformed.json");
+    for (snapshot, message) in [
+        ("not json", "invalid registry snapshot"),
+        ("[1, 2]", "expected an object of registry keys"),
+        (r#"{"SOFTWARE\\Tally Solutions": "x"}"#, "must be an object"),
+        (r#"{"SOFTWARE\\Tally Solutions\\TallyPrime": {"Version": 4.5}}"#, "unsupported value"),
+    ] {
+        fs::write(&path, snapshot).unwrap();
+        let (report, ok) = detect_snapshot(&path);
+        assert!(!ok, "{}", snapshot);
+        assert!(report["error"].as_str().unwrap().contains(message), "{}", report);
+    }
+
+    let (report, ok) = detect_snapshot(&fixture("registry/missing.json"));
+    assert!(!ok);
+    assert!(report["error"].as_str().unwrap().starts_with("I/O error"));
+}
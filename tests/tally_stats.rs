@@ -0,0 +1,107 @@
+//! Opt-in company statistics with `--tally-stats`, against recorded Tally responses
+
+#![cfg(all(not(windows), feature = "tally-xml"))]
+
+mod support;
+
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::{Value, json};
+use support::{FakeTallyServer, Reply, detect_apps, fixture, proc_tree};
+
+const PRODUCT_RESPONSE: &str = "<RESPONSE>TallyPrime Server is Running</RESPONSE>";
+
+/// Answers each export with its recording, keyed by the inline collection name
+fn stats_server() -> FakeTallyServer {
+    stats_server_taking(Duration::ZERO)
+}
+
+/// Like `stats_server`, taking `masters_delay` to answer each master count
+fn stats_server_taking(masters_delay: Duration) -> FakeTallyServer {
+    let recording = |name: &str| fs::read_to_string(fixture(name)).unwrap();
+    let companies = recording("tally/companies_prime.xml");
+    let masters = recording("tally/stats_masters.xml");
+    let vouchers = recording("tally/stats_vouchers.xml");
+
+    FakeTallyServer::start(move |request| {
+        let body = request.body_text();
+        let reply = if body.contains("<ID>QuickSys Companies</ID>") {
+            &companies
+        } else if body.contains("<ID>QuickSys Master Counts</ID>") {
+            thread::sleep(masters_delay);
+            &masters
+        } else if body.contains("<ID>QuickSys Voucher Summary</ID>") {
+            &vouchers
+        } else {
+            PRODUCT_RESPONSE
+        };
+        Reply::Xml(reply.to_string())
+    })
+}
+
+/// Companies of the gateway found by discovery, on a host with no installation
+fn discovered_companies(server: &FakeTallyServer, name: &str, extra_args: &[&str]) -> Value {
+    let home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    fs::create_dir_all(&home).unwrap();
+    let proc_root = proc_tree(&format!("proc-{}", name), &[], "", "");
+    let port = server.endpoint.rsplit(':').next().unwrap();
+    let range = format!("{0}-{0}", port);
+
+    let mut args = vec!["--tally-discover", "--tally-ports", &range];
+    args.extend_from_slice(extra_args);
+    detect_apps(&args, &[("HOME", &home), ("HOST_PROC", &proc_root)])["tally"][0]["companies"].clone()
+}
+
+#[test]
+fn counts_masters_and_vouchers_per_company() {
+    let server = stats_server();
+
+    let companies = discovered_companies(&server, "stats-home", &["--tally-stats"]);
+    let stats = &companies[0]["stats"];
+    assert_eq!(stats["ledgers"], 1204);
+    assert_eq!(stats["stock_items"], 387);
+    assert_eq!(stats["vouchers"], 2971);
+    assert_eq!(stats["vouchers_by_type"], json!({"Journal": 12, "Payment": 641, "Sales": 2318}));
+    assert_eq!(stats["last_voucher_date"], "2024-03-31");
+    assert!(companies[1]["stats"].is_object());
+}
+
+#[test]
+fn asks_for_each_company_by_name() {
+    let server = stats_server();
+
+    discovered_companies(&server, "stats-requests", &["--tally-stats"]);
+
+    let bodies: Vec<String> = server.requests().into_iter().map(|r| r.body_text()).collect();
+    let summaries: Vec<&String> = bodies.iter().filter(|b| b.contains("<ID>QuickSys Voucher Summary</ID>")).collect();
+    assert_eq!(summaries.len(), 2);
+    assert!(summaries[0].contains("<SVCURRENTCOMPANY>Sharma Traders &amp; Sons</SVCURRENTCOMPANY>"));
+    assert!(summaries[1].contains("<SVCURRENTCOMPANY>Mehta Hardware</SVCURRENTCOMPANY>"));
+    // Grouped inside Tally; individual vouchers are never exported
+    assert!(summaries[0].contains("<BY>VoucherTypeName : $VoucherTypeName</BY>"));
+    assert!(summaries[0].contains("<AGGRCOMPUTE>VoucherCount : SUM : 1</AGGRCOMPUTE>"));
+}
+
+#[test]
+fn leaves_stats_out_unless_asked() {
+    let server = stats_server();
+
+    let companies = discovered_companies(&server, "stats-off", &[]);
+    assert_eq!(companies[0]["name"], "Sharma Traders & Sons");
+    assert!(companies[0].get("stats").is_none());
+    assert!(!server.requests().iter().any(|r| r.body_text().contains("QuickSys Master Counts")));
+}
+
+#[test]
+fn stops_asking_once_the_timeout_is_spent() {
+    // The first company's counts use 300 ms of the 500 ms the probe may take,
+    // leaving too little for the second company's
+    let server = stats_server_taking(Duration::from_millis(300));
+
+    let companies = discovered_companies(&server, "stats-deadline", &["--tally-stats", "--timeout-ms", "500"]);
+    assert!(companies[0]["stats"].is_object(), "{:#}", companies);
+    assert_eq!(companies[1].get("stats"), None, "{:#}", companies);
+}
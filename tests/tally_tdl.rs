@@ -0,0 +1,106 @@
+//! TDL/TCP inventory from tally.ini and the XML API
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+#[cfg(feature = "tally-xml")]
+use support::{FakeTallyServer, Reply, run_quicksys};
+use support::{detect_apps, tally_install_dir, tally_prefix_home};
+
+const GST_TCP: &str = "Sample TDL\n";
+const GST_SHA256: &str = "de3a3ecdbe607c4224572c80372c062d3a61f99255e551fe2cbb4f08e0e9aa0a";
+const MENU_TDL: &str = "[#Menu: Gateway of Tally]\n";
+const MENU_SHA256: &str = "323835f86f498fa4e0ae8ec9c389384378bd7c8b3e6398c9d2549239d6631065";
+
+/// A home directory with a Wine prefix holding TallyPrime, its tally.ini and two TDL files
+fn prefix_home(name: &str, ini: &str) -> PathBuf {
+    let home = tally_prefix_home(name, ini);
+    let drive_c = home.join(".wine/drive_c");
+    fs::create_dir_all(drive_c.join("TDL")).unwrap();
+    fs::write(drive_c.join("TDL/gst.tcp"), GST_TCP).unwrap();
+    fs::write(tally_install_dir(&home).join("Menu.tdl"), MENU_TDL).unwrap();
+    home
+}
+
+fn tdls(home: &Path, extra_args: &[&str]) -> Value {
+    detect_apps(extra_args, &[("HOME", home)])["tally"][0]["tdls"].clone()
+}
+
+/// Answers the TDL export with `paths` and the product request with a TallyPrime envelope
+#[cfg(feature = "tally-xml")]
+fn tdl_server(paths: Vec<String>) -> FakeTallyServer {
+    FakeTallyServer::start(move |request| {
+        let body = request.body_text();
+        if body.contains("QuickSys TDL Files") {
+            let records: String = paths.iter().map(|p| format!("<TDLFILE NAME=\"{0}\"><NAME>{0}</NAME></TDLFILE>", p)).collect();
+            Reply::Xml(format!("<ENVELOPE><BODY><DATA><COLLECTION>{}</COLLECTION></DATA></BODY></ENVELOPE>", records))
+        } else if body.contains("<TALLYREQUEST>Export</TALLYREQUEST>") {
+            Reply::Xml("<ENVELOPE><BODY><DATA><COLLECTION></COLLECTION></DATA></BODY></ENVELOPE>".to_string())
+        } else {
+            Reply::Xml("<ENVELOPE><PRODUCT>TallyPrime</PRODUCT><VERSION>4.1</VERSION></ENVELOPE>".to_string())
+        }
+    })
+}
+
+#[test]
+fn lists_tdls_from_tally_ini() {
+    let home = prefix_home(
+        "tdl-ini-home",
+        "[Tally]\nUser TDL=Yes\nTDL=C:\\TDL\\gst.tcp\nTDL=Menu.tdl\nTDL=D:\\Shared\\missing.tcp\nClient Server=None\n",
+    );
+    let install = tally_install_dir(&home);
+    let tdls = tdls(&home, &[]);
+
+    assert_eq!(
+        tdls[0],
+        json!({"path": "C:\\TDL\\gst.tcp", "exists": true, "size_bytes": GST_TCP.len(), "sha256": GST_SHA256,
+               "last_modified": tdls[0]["last_modified"], "sources": ["tally_ini"]})
+    );
+    assert!(tdls[0]["last_modified"].as_str().is_some_and(|t| t.ends_with('Z')));
+
+    // Relative entries are resolved against the install directory
+    assert_eq!(tdls[1]["path"], install.join("Menu.tdl").to_string_lossy().as_ref());
+    assert_eq!(tdls[1]["sha256"], MENU_SHA256);
+
+    assert_eq!(tdls[2], json!({"path": "D:\\Shared\\missing.tcp", "exists": false, "sources": ["tally_ini"]}));
+}
+
+#[test]
+fn skips_tdls_when_user_tdl_is_off() {
+    let home = prefix_home("tdl-off-home", "[Tally]\nUser TDL=No\nTDL=C:\\TDL\\gst.tcp\nClient Server=None\n");
+    assert_eq!(tdls(&home, &[]), Value::Null);
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn merges_tdls_loaded_in_the_running_instance() {
+    let home = prefix_home("tdl-http-home", "[Tally]\nUser TDL=Yes\nTDL=C:\\TDL\\gst.tcp\n");
+    let server = tdl_server(vec!["c:\\tdl\\GST.tcp".to_string(), "C:\\TDL\\account.tcp".to_string()]);
+
+    let tdls = tdls(&home, &["--tally-http", &server.endpoint]);
+    assert_eq!(tdls.as_array().unwrap().len(), 2, "{:#?}", tdls);
+    assert_eq!(tdls[0]["path"], "C:\\TDL\\gst.tcp");
+    assert_eq!(tdls[0]["sources"], json!(["tally_ini", "http"]));
+    assert_eq!(tdls[1], json!({"path": "C:\\TDL\\account.tcp", "exists": false, "sources": ["http"]}));
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn probe_lists_loaded_tdls() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("tdl-probe");
+    fs::create_dir_all(&dir).unwrap();
+    let menu = dir.join("Menu.tdl");
+    fs::write(&menu, MENU_TDL).unwrap();
+    let server = tdl_server(vec![menu.to_string_lossy().into_owned()]);
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(output.status.success());
+    assert_eq!(report["tdls"][0]["path"], menu.to_string_lossy().as_ref());
+    assert_eq!(report["tdls"][0]["sha256"], MENU_SHA256);
+    assert_eq!(report["tdls"][0]["sources"], json!(["http"]));
+}
@@ -0,0 +1,94 @@
+//! Tally detection in Wine prefixes, against the fixture home directory in
+//! tests/fixtures/wine/home and synthetic prefixes
+
+#![cfg(not(windows))]
+
+mod support;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+#[cfg(feature = "tally-xml")]
+use support::{FakeTallyServer, Reply};
+use support::{detect_tally, ends_with, fixture, tally_prefix_home};
+
+fn detect(home: &Path, extra_args: &[&str]) -> Vec<Value> {
+    detect_tally(extra_args, &[("HOME", home)])
+}
+
+#[test]
+fn finds_installations_in_wine_and_bottles_prefixes() {
+    let installs = detect(&fixture("wine/home"), &[]);
+    assert_eq!(installs.len(), 3, "{:#?}", installs);
+
+    // Uninstall and vendor keys name the same directory in different case
+    let prime = &installs[0];
+    assert_eq!(prime["variant"], "TallyPrime");
+    assert_eq!(prime["version"], "4.1");
+    assert_eq!(prime["detection_sources"], serde_json::json!(["registry_uninstall", "registry_vendor"]));
+    assert!(ends_with(&prime["install_path"], "/.wine/drive_c/Program Files/TallyPrime"));
+    assert!(ends_with(&prime["wine_prefix"], "/.wine"));
+    assert_eq!(prime["config"]["client_server"], "None");
+
+    // ERP 9 is only on disk; its version comes from Tally.exe
+    let erp9 = &installs[1];
+    assert_eq!(erp9["variant"], "TallyERP9");
+    assert_eq!(erp9["version"], "Release 6.6.3");
+    assert_eq!(erp9["detection_sources"], serde_json::json!(["file_version"]));
+    assert!(ends_with(&erp9["install_path"], "/drive_c/Program Files (x86)/Tally/ERP9"));
+
+    let bottle = &installs[2];
+    assert_eq!(bottle["version"], "5.0");
+    assert!(ends_with(&bottle["wine_prefix"], "/bottles/Accounts"));
+    assert!(ends_with(&bottle["install_path"], "/bottles/Accounts/drive_c/TallyPrime"));
+}
+
+#[test]
+fn honours_wineprefix_outside_home() {
+    let empty_home = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("wine-empty-home");
+    fs::create_dir_all(&empty_home).unwrap();
+
+    assert!(detect(&empty_home, &[]).is_empty());
+
+    let installs = detect_tally(&[], &[("HOME", &empty_home), ("WINEPREFIX", &fixture("wine/home/.wine"))]);
+    assert_eq!(installs.len(), 2);
+}
+
+#[test]
+fn maps_data_directory_into_the_prefix() {
+    // Windows paths are case-insensitive; the prefix spells it "users"
+    let home = tally_prefix_home("wine-data-home", "[Tally]\r\nData=C:\\Users\\Public\\TallyPrime\\Data\r\nClient Server=None\r\n");
+    let company = home.join(".wine/drive_c/users/Public/TallyPrime/data/10000");
+    fs::create_dir_all(&company).unwrap();
+    fs::write(company.join("Company.1800"), vec![0u8; 64]).unwrap();
+
+    let installs = detect(&home, &[]);
+    let folders = installs[0]["data_folders"].as_array().unwrap();
+    assert_eq!(folders.len(), 1);
+    assert_eq!(folders[0]["company_number"], "10000");
+    assert!(ends_with(&folders[0]["path"], "/drive_c/users/Public/TallyPrime/data/10000"));
+}
+
+#[cfg(feature = "tally-xml")]
+#[test]
+fn credits_the_gateway_to_the_matching_installation_once() {
+    let server = FakeTallyServer::start(|request| {
+        if request.body_text().contains("<TYPE>Collection</TYPE>") {
+            Reply::Xml("<ENVELOPE><COMPANY NAME=\"Wine Traders\"><NAME>Wine Traders</NAME></COMPANY></ENVELOPE>".to_string())
+        } else {
+            Reply::Xml("<RESPONSE>TallyPrime Server is Running</RESPONSE>".to_string())
+        }
+    });
+
+    let installs = detect(&fixture("wine/home"), &["--tally-http", &server.endpoint]);
+
+    let with_http: Vec<&Value> = installs
+        .iter()
+        .filter(|i| i["detection_sources"].as_array().unwrap().contains(&Value::from("http")))
+        .collect();
+    assert_eq!(with_http.len(), 1);
+    assert!(ends_with(&with_http[0]["wine_prefix"], "/.wine"));
+    assert_eq!(with_http[0]["variant"], "TallyPrime");
+    assert_eq!(with_http[0]["companies"][0]["name"], "Wine Traders");
+}
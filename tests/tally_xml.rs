@@ -0,0 +1,138 @@
+//! The Tally XML probe speaks real HTTP/1.1 to a stand-in gateway
+
+#![cfg(feature = "tally-xml")]
+
+mod support;
+
+use std::time::{Duration, Instant};
+
+use support::{FakeTallyServer, Reply, run_quicksys};
+
+const PRODUCT_RESPONSE: &str =
+    "<ENVELOPE><PRODUCT>TallyPrime Gold</PRODUCT><VERSION>4.1</VERSION><EDITION>Gold</EDITION></ENVELOPE>";
+
+#[test]
+fn probe_sends_well_formed_post() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(output.status.success());
+    assert_eq!(report["reachable"], true);
+
+    // The product request comes first; a company export follows
+    let requests = server.requests();
+    let request = &requests[0];
+    assert_eq!(request.request_line, "POST / HTTP/1.1");
+    assert_eq!(request.header("Content-Type"), Some("text/xml; charset=utf-8"));
+    assert_eq!(request.header("Content-Length"), Some(request.body.len().to_string().as_str()));
+    assert!(request.header("Host").is_some());
+    assert!(request.body_text().starts_with("<ENVELOPE>"));
+}
+
+#[test]
+fn probe_parses_product_envelope() {
+    let server = FakeTallyServer::with_fixed_reply(PRODUCT_RESPONSE);
+
+    let (report, _) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert_eq!(report["variant"], "TallyPrime");
+    assert_eq!(report["version"], "4.1");
+    assert_eq!(report["edition"], "Gold");
+}
+
+#[test]
+fn probe_decodes_utf16_responses() {
+    let server = FakeTallyServer::start(|_| Reply::Utf16(PRODUCT_RESPONSE.to_string()));
+
+    let (report, _) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert_eq!(report["variant"], "TallyPrime");
+    assert_eq!(report["version"], "4.1");
+}
+
+#[test]
+fn probe_decodes_big_endian_utf16_without_a_bom() {
+    let body: Vec<u8> = PRODUCT_RESPONSE.encode_utf16().flat_map(u16::to_be_bytes).collect();
+    let mut reply = format!("HTTP/1.1 200 OK\r\nContent-Type: text/xml; charset=utf-16be\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+    reply.extend_from_slice(&body);
+    let server = FakeTallyServer::start(move |_| Reply::Raw(reply.clone()));
+
+    let (report, _) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert_eq!(report["variant"], "TallyPrime");
+    assert_eq!(report["version"], "4.1");
+}
+
+#[test]
+fn probe_reassembles_chunked_responses() {
+    let server = FakeTallyServer::start(|_| Reply::Chunked(PRODUCT_RESPONSE.to_string(), 7));
+
+    let (report, _) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert_eq!(report["variant"], "TallyPrime");
+    assert_eq!(report["edition"], "Gold");
+}
+
+#[test]
+fn probe_recognises_server_banner() {
+    let server = FakeTallyServer::with_fixed_reply("<RESPONSE>TallyPrime Server is Running</RESPONSE>");
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(output.status.success());
+    assert_eq!(report["variant"], "TallyPrime");
+    assert!(report["version"].is_null());
+}
+
+#[test]
+fn probe_rejects_non_http_replies() {
+    let server = FakeTallyServer::start(|_| Reply::Raw(b"<ENVELOPE></ENVELOPE>".to_vec()));
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(!output.status.success());
+    assert_eq!(report["reachable"], false);
+    assert!(report["error"].as_str().unwrap().contains("malformed"));
+}
+
+#[test]
+fn probe_reports_unreachable_gateway() {
+    // Bind and drop a listener to get a port nothing is listening on
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let endpoint = format!("127.0.0.1:{}", port);
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &endpoint]);
+    assert!(!output.status.success());
+    assert_eq!(report["reachable"], false);
+}
+
+#[test]
+fn probe_refuses_oversized_responses() {
+    // Announced sizes are checked before anything is allocated for them
+    for head in [
+        "HTTP/1.1 200 OK\r\nContent-Length: 10737418240\r\n\r\n<ENVELOPE>",
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffff\r\n<ENVELOPE>",
+    ] {
+        let server = FakeTallyServer::start(move |_| Reply::Raw(head.as_bytes().to_vec()));
+
+        let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+        assert!(!output.status.success());
+        assert_eq!(report["reachable"], false);
+        assert_eq!(report["error"], "response larger than 67108864 bytes", "{}", head);
+    }
+}
+
+#[test]
+fn probe_rejects_truncated_bodies() {
+    let server = FakeTallyServer::start(|_| Reply::Raw(b"HTTP/1.1 200 OK\r\nContent-Length: 500\r\n\r\n<ENVELOPE>".to_vec()));
+
+    let (report, output) = run_quicksys(&["tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(!output.status.success());
+    assert!(report["error"].as_str().unwrap().contains("shorter than Content-Length"), "{}", report);
+}
+
+#[test]
+fn probe_gives_up_on_a_trickling_reply_at_the_timeout() {
+    // Every byte arrives well within the timeout, the whole body does not
+    let server = FakeTallyServer::start(|_| Reply::Drip(PRODUCT_RESPONSE.to_string(), Duration::from_millis(50)));
+
+    let started = Instant::now();
+    let (report, output) = run_quicksys(&["--timeout-ms", "500", "tally", "probe", "--endpoint", &server.endpoint]);
+    assert!(!output.status.success());
+    assert!(report["error"].as_str().unwrap().contains("timed out"), "{}", report);
+    assert!(started.elapsed() < Duration::from_secs(3), "took {:?}", started.elapsed());
+}